@@ -1,5 +1,3 @@
-#![feature(proc_macro_hygiene)]
-
 use failure::format_err;
 use futures_locks::{RwLock, RwLockReadGuard};
 use tokio::executor::current_thread::block_on_all;
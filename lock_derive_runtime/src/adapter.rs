@@ -0,0 +1,76 @@
+use futures::{Future, Stream};
+use std::time::Duration;
+
+/// A future completing once its duration elapsed.
+pub type Delay = Box<dyn Future<Item = (), Error = failure::Error> + Send>;
+
+/// The timer and the spawner of the executor, for the timeouts, the builder and the supervisor,
+/// so the generated code is not tied to one executor. `Tokio` and `AsyncStd` come with the
+/// features of the same name, the invocations use `DefaultAdapter` unless given
+/// `adapter: Type`.
+pub trait RuntimeAdapter {
+    /// A future completing once `duration` elapsed.
+    fn delay(duration: Duration) -> Delay;
+
+    /// Runs `future` in the background.
+    fn spawn<F>(future: F)
+    where
+        F: Future<Item = (), Error = ()> + Send + 'static;
+}
+
+/// The timer and the spawner of `tokio`, which the futures must run on.
+#[cfg(feature = "tokio")]
+pub struct Tokio;
+
+#[cfg(feature = "tokio")]
+impl RuntimeAdapter for Tokio {
+    fn delay(duration: Duration) -> Delay {
+        let deadline = std::time::Instant::now() + duration;
+        Box::new(tokio::timer::Delay::new(deadline).map_err(failure::Error::from))
+    }
+
+    fn spawn<F>(future: F)
+    where
+        F: Future<Item = (), Error = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+}
+
+/// The timer and the spawner of `async-std`, polling the futures 0.1 from its tasks.
+#[cfg(feature = "async-std")]
+pub struct AsyncStd;
+
+#[cfg(feature = "async-std")]
+impl RuntimeAdapter for AsyncStd {
+    fn delay(duration: Duration) -> Delay {
+        Box::new(crate::bridge::AsyncResolve::new(async move {
+            async_std::task::sleep(duration).await;
+            Ok(())
+        }))
+    }
+
+    fn spawn<F>(future: F)
+    where
+        F: Future<Item = (), Error = ()> + Send + 'static,
+    {
+        async_std::task::spawn(async move {
+            let _ = crate::compat::Compat::new(future).await;
+        });
+    }
+}
+
+/// The adapter of the invocations without `adapter: Type`, `Tokio`, or `AsyncStd` when only its
+/// feature is on.
+#[cfg(feature = "tokio")]
+pub type DefaultAdapter = Tokio;
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+pub type DefaultAdapter = AsyncStd;
+
+/// Ticks every `period` on the timer of the adapter, for the supervisor.
+pub fn interval<A: RuntimeAdapter>(
+    period: Duration,
+) -> impl Stream<Item = (), Error = failure::Error> {
+    futures::stream::unfold((), move |_| Some(A::delay(period).map(|_| ((), ()))))
+}
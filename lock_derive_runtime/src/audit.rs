@@ -0,0 +1,50 @@
+use crate::model::thread;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+futures::task_local! {
+    static TASK: usize = NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The task polling the chain, numbered on its first acquisition.
+pub fn task() -> String {
+    format!("task {}", TASK.with(|t| *t))
+}
+
+pub fn thread() -> String {
+    format!("thread {:?}", thread::current().id())
+}
+
+/// Logs the release of the locks on the `lock_derive::audit` target when the struct holding it
+/// is dropped.
+pub struct Audit {
+    site: &'static str,
+    locks: &'static str,
+    owner: String,
+}
+
+/// Logs the acquisition of the locks on the `lock_derive::audit` target.
+pub fn acquired(site: &'static str, locks: &'static str, owner: String) -> Audit {
+    log::info!(
+        target: "lock_derive::audit",
+        site = site,
+        locks = locks,
+        owner = owner.as_str();
+        "locks acquired"
+    );
+
+    Audit { site, locks, owner }
+}
+
+impl Drop for Audit {
+    fn drop(&mut self) {
+        log::info!(
+            target: "lock_derive::audit",
+            site = self.site,
+            locks = self.locks,
+            owner = self.owner.as_str();
+            "locks released"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+/// The future of a `resolve` arm, told apart by autoref specialization: the `Legacy` impl is
+/// found first for the futures 0.1, `Async` otherwise. A futures 0.1 future is taken as is, a
+/// `std::future::Future` such as an `async` block or an `async fn` call is polled from the task
+/// polling the chain.
+pub struct Resolve<F>(pub Option<F>);
+
+pub trait Legacy {
+    type Future;
+    fn __bridge(self) -> Self::Future;
+}
+
+impl<F: futures::Future> Legacy for &mut Resolve<F> {
+    type Future = F;
+
+    fn __bridge(self) -> F {
+        self.0.take().expect("resolve")
+    }
+}
+
+pub trait Async {
+    type Future;
+    fn __bridge(&mut self) -> Self::Future;
+}
+
+impl<F, T, E> Async for Resolve<F>
+where
+    F: std::future::Future<Output = Result<T, E>>,
+{
+    type Future = AsyncResolve<F>;
+
+    fn __bridge(&mut self) -> AsyncResolve<F> {
+        AsyncResolve(Box::pin(self.0.take().expect("resolve")))
+    }
+}
+
+/// Under `infallible;`, the `Async` impl resolves to the output of the future as is, such as
+/// the guard of `tokio::sync::RwLock::write()`, rather than to a `Result`.
+pub trait LegacyInfallible {
+    type Future;
+    fn __bridge_infallible(self) -> Self::Future;
+}
+
+impl<F: futures::Future> LegacyInfallible for &mut Resolve<F> {
+    type Future = F;
+
+    fn __bridge_infallible(self) -> F {
+        self.0.take().expect("resolve")
+    }
+}
+
+pub trait AsyncInfallible {
+    type Future;
+    fn __bridge_infallible(&mut self) -> Self::Future;
+}
+
+impl<F: std::future::Future> AsyncInfallible for Resolve<F> {
+    type Future = InfallibleResolve<F>;
+
+    fn __bridge_infallible(&mut self) -> InfallibleResolve<F> {
+        InfallibleResolve(Box::pin(self.0.take().expect("resolve")))
+    }
+}
+
+pub struct AsyncResolve<F>(Pin<Box<F>>);
+
+impl<F> AsyncResolve<F> {
+    #[cfg_attr(not(feature = "async-std"), allow(dead_code))]
+    pub(crate) fn new(future: F) -> Self {
+        AsyncResolve(Box::pin(future))
+    }
+}
+
+impl<F, T, E> futures::Future for AsyncResolve<F>
+where
+    F: std::future::Future<Output = Result<T, E>>,
+{
+    type Item = T;
+    type Error = E;
+
+    fn poll(&mut self) -> futures::Poll<T, E> {
+        let waker = waker();
+
+        match self.0.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(Ok(v)) => Ok(futures::Async::Ready(v)),
+            Poll::Ready(Err(e)) => Err(e),
+            Poll::Pending => Ok(futures::Async::NotReady),
+        }
+    }
+}
+
+pub struct InfallibleResolve<F>(Pin<Box<F>>);
+
+impl<F: std::future::Future> futures::Future for InfallibleResolve<F> {
+    type Item = F::Output;
+    type Error = failure::Error;
+
+    fn poll(&mut self) -> futures::Poll<F::Output, failure::Error> {
+        let waker = waker();
+
+        match self.0.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(v) => Ok(futures::Async::Ready(v)),
+            Poll::Pending => Ok(futures::Async::NotReady),
+        }
+    }
+}
+
+struct TaskWake(futures::task::Task);
+
+impl Wake for TaskWake {
+    fn wake(self: Arc<Self>) {
+        self.0.notify()
+    }
+}
+
+/// Wakes the futures 0.1 task polling the chain.
+fn waker() -> Waker {
+    Waker::from(Arc::new(TaskWake(futures::task::current())))
+}
@@ -0,0 +1,183 @@
+use crate::adapter::{Delay, RuntimeAdapter};
+use crate::compat::Compat;
+use futures::future::{Either, Select2};
+use futures::Future;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+/// Called after each attempt with its error, if any, and its duration.
+pub type Observer = Box<dyn Fn(Option<&failure::Error>, Duration) + Send>;
+
+/// Names the lock waited for when the timeout elapses, and its holder.
+type Blocked = Box<dyn Fn() -> Option<String> + Send>;
+
+/// The value of `builder;`, resolving the chain returned by `resolve` with the timeout, the
+/// retries and the observers set at runtime, the timeout elapsing on the timer of the adapter
+/// `A`. Each attempt resolves a new chain, the guards of a failed attempt being released. The
+/// builder is also `IntoFuture`, for `.await`.
+pub struct LocksBuilder<F, A> {
+    resolve: F,
+    timeout: Option<Duration>,
+    retry: usize,
+    observers: Vec<Observer>,
+    blocked: Option<Blocked>,
+    adapter: PhantomData<fn() -> A>,
+}
+
+impl<F, R, A> LocksBuilder<F, A>
+where
+    F: FnMut() -> R,
+    R: Future<Error = failure::Error>,
+    A: RuntimeAdapter,
+{
+    pub fn new(resolve: F) -> Self {
+        Self {
+            resolve,
+            timeout: None,
+            retry: 0,
+            observers: Vec::new(),
+            blocked: None,
+            adapter: PhantomData,
+        }
+    }
+
+    /// Fails an attempt not resolved within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Makes up to `retry` more attempts after a failure.
+    pub fn with_retry(mut self, retry: usize) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Calls `observer` after each attempt with its error, if any, and its duration.
+    pub fn with_observer<O>(mut self, observer: O) -> Self
+    where
+        O: Fn(Option<&failure::Error>, Duration) + Send + 'static,
+    {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Appends the lock waited for and its holder, as told by `blocked`, to the error of the
+    /// timeout, for the `owners` feature of `lock_derive`.
+    #[doc(hidden)]
+    pub fn with_blocked<B>(mut self, blocked: B) -> Self
+    where
+        B: Fn() -> Option<String> + Send + 'static,
+    {
+        self.blocked = Some(Box::new(blocked));
+        self
+    }
+
+    pub fn resolve(mut self) -> LocksFuture<F, R, A> {
+        let attempt = self.attempt();
+
+        LocksFuture {
+            builder: self,
+            attempt,
+            attempts: 0,
+            start: Instant::now(),
+        }
+    }
+
+    /// The error of an attempt whose timeout elapsed.
+    fn elapsed(&self) -> failure::Error {
+        let timeout = self.timeout.expect("timeout");
+
+        let e = match self.blocked.as_ref().and_then(|blocked| blocked()) {
+            Some(blocked) => {
+                failure::format_err!(
+                    "resolving the locks timed out after {:?}, {}",
+                    timeout,
+                    blocked
+                )
+            }
+            None => failure::format_err!("resolving the locks timed out after {:?}", timeout),
+        };
+
+        crate::timeout::timed_out(e)
+    }
+
+    fn attempt(&mut self) -> Attempt<R> {
+        let resolve = (self.resolve)();
+
+        match self.timeout {
+            Some(timeout) => Attempt::Timed(resolve.select2(A::delay(timeout))),
+            None => Attempt::Untimed(resolve),
+        }
+    }
+}
+
+enum Attempt<R> {
+    Timed(Select2<R, Delay>),
+    Untimed(R),
+}
+
+/// The future of the struct, resolving the chain again after a failed attempt.
+pub struct LocksFuture<F, R, A> {
+    builder: LocksBuilder<F, A>,
+    attempt: Attempt<R>,
+    attempts: usize,
+    start: Instant,
+}
+
+impl<F, R, A> Future for LocksFuture<F, R, A>
+where
+    F: FnMut() -> R,
+    R: Future<Error = failure::Error>,
+    A: RuntimeAdapter,
+{
+    type Item = R::Item;
+    type Error = failure::Error;
+
+    fn poll(&mut self) -> futures::Poll<R::Item, failure::Error> {
+        loop {
+            let result = match &mut self.attempt {
+                Attempt::Timed(resolve) => match resolve.poll() {
+                    Ok(futures::Async::Ready(Either::A((locks, _)))) => Ok(locks),
+                    Ok(futures::Async::Ready(Either::B(_))) => Err(self.builder.elapsed()),
+                    Ok(futures::Async::NotReady) => return Ok(futures::Async::NotReady),
+                    Err(Either::A((e, _))) | Err(Either::B((e, _))) => Err(e),
+                },
+                Attempt::Untimed(resolve) => match resolve.poll() {
+                    Ok(futures::Async::Ready(locks)) => Ok(locks),
+                    Ok(futures::Async::NotReady) => return Ok(futures::Async::NotReady),
+                    Err(e) => Err(e),
+                },
+            };
+
+            for observer in &self.builder.observers {
+                observer(result.as_ref().err(), self.start.elapsed());
+            }
+
+            match result {
+                Ok(locks) => return Ok(futures::Async::Ready(locks)),
+                Err(_) if self.attempts < self.builder.retry => {
+                    self.attempts += 1;
+                    self.attempt = self.builder.attempt();
+                    self.start = Instant::now();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+// `.await` on the builder resolves the locks with the knobs set beforehand.
+impl<F, R, A> std::future::IntoFuture for LocksBuilder<F, A>
+where
+    F: FnMut() -> R,
+    R: Future<Error = failure::Error>,
+    A: RuntimeAdapter,
+{
+    type Output = Result<R::Item, failure::Error>;
+    type IntoFuture = Compat<LocksFuture<F, R, A>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Compat::new(self.resolve())
+    }
+}
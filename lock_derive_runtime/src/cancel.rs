@@ -0,0 +1,13 @@
+use std::fmt;
+
+/// The error of an acquisition abandoned through `cancel: token`.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the acquisition of the locks was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
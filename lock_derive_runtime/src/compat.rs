@@ -0,0 +1,37 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+/// Polls the futures 0.1 chain from a `std::future::Future`, for `.await`.
+pub struct Compat<F>(pub futures::executor::Spawn<F>);
+
+impl<F> Compat<F> {
+    pub fn new(future: F) -> Self {
+        Compat(futures::executor::spawn(future))
+    }
+}
+
+// futures 0.1 do not rely on pinning.
+impl<F> Unpin for Compat<F> {}
+
+struct Notify(Waker);
+
+impl futures::executor::Notify for Notify {
+    fn notify(&self, _: usize) {
+        self.0.wake_by_ref()
+    }
+}
+
+impl<F: futures::Future> std::future::Future for Compat<F> {
+    type Output = Result<F::Item, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let notify = futures::executor::NotifyHandle::from(Arc::new(Notify(cx.waker().clone())));
+
+        match self.get_mut().0.poll_future_notify(&notify, 0) {
+            Ok(futures::Async::Ready(v)) => Poll::Ready(Ok(v)),
+            Ok(futures::Async::NotReady) => Poll::Pending,
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use futures::stream::FuturesUnordered;
+use futures::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::{Async, Future, Poll, Stream};
+use std::sync::Mutex;
+use std::thread;
+
+type Abandoned = Box<dyn Future<Item = (), Error = ()> + Send>;
+
+static REAPER: Mutex<Option<UnboundedSender<Abandoned>>> = Mutex::new(None);
+
+/// An acquisition kept alive once abandoned by a timeout, a cancellation, a failed attempt of
+/// the builder or a forced acquisition, for the locks whose queue a waiter cannot drop out of,
+/// such as the locks of `futures_locks`, which panic on their next release otherwise. Dropped
+/// while pending, the acquisition is handed to a background thread, which drops the guard as
+/// soon as the lock is granted. The recipes of `define_lock!` detach their acquisitions.
+///
+/// ```
+/// use futures::Future;
+/// use futures_locks::RwLock;
+///
+/// let lock = RwLock::new(10);
+/// let held = lock.try_write().unwrap();
+///
+/// // dropped while pending, the acquisition is still granted the lock.
+/// drop(lock_derive_runtime::detach(lock.write()));
+/// drop(held);
+///
+/// assert_eq!(10, *lock.write().wait().unwrap());
+/// ```
+#[must_use = "futures do nothing unless polled"]
+pub struct Detached<F: Future + Send + 'static>(Option<F>);
+
+pub fn detach<F>(future: F) -> Detached<F>
+where
+    F: Future + Send + 'static,
+{
+    Detached(Some(future))
+}
+
+impl<F> Future for Detached<F>
+where
+    F: Future + Send + 'static,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let poll = self.0.as_mut().expect("polled after completion").poll();
+
+        if let Ok(Async::NotReady) = poll {
+            return poll;
+        }
+
+        self.0 = None;
+        poll
+    }
+}
+
+impl<F> Drop for Detached<F>
+where
+    F: Future + Send + 'static,
+{
+    fn drop(&mut self) {
+        if let Some(future) = self.0.take() {
+            reap(Box::new(future.then(|_| Ok(()))));
+        }
+    }
+}
+
+/// Hands the acquisition to the reaper, a thread of its own rather than a task of the runtime
+/// adapter, which may no longer be running when the lock is released.
+fn reap(future: Abandoned) {
+    let mut reaper = REAPER.lock().unwrap_or_else(|e| e.into_inner());
+
+    let future = match reaper.as_ref() {
+        Some(tx) => match tx.unbounded_send(future) {
+            Ok(()) => return,
+            Err(e) => e.into_inner(),
+        },
+        None => future,
+    };
+
+    let (tx, rx) = mpsc::unbounded();
+    let _ = tx.unbounded_send(future);
+
+    let spawned = thread::Builder::new()
+        .name("lock_derive-reaper".into())
+        .spawn(move || {
+            let _ = Reaper {
+                rx,
+                pending: FuturesUnordered::new(),
+            }
+            .wait();
+        });
+
+    if spawned.is_ok() {
+        *reaper = Some(tx);
+    }
+}
+
+/// Polls the acquisitions abandoned until they are granted, the guards being dropped at once.
+struct Reaper {
+    rx: UnboundedReceiver<Abandoned>,
+    pending: FuturesUnordered<Abandoned>,
+}
+
+impl Future for Reaper {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        while let Async::Ready(Some(future)) = self.rx.poll()? {
+            self.pending.push(future);
+        }
+
+        while let Async::Ready(Some(())) = self.pending.poll()? {}
+        Ok(Async::NotReady)
+    }
+}
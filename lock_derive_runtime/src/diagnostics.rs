@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+/// Logs a warning on the `lock_derive::diagnostics` target when `start` is older than
+/// `threshold`, `what` being `acquiring` or `holding` and `locks` the locks of the invocation,
+/// `accounts:read,users:write`.
+pub fn check(what: &'static str, locks: &'static str, start: Instant, threshold: Duration) {
+    let elapsed = start.elapsed();
+
+    if elapsed > threshold {
+        log::warn!(
+            target: "lock_derive::diagnostics",
+            "{} {} took {:?}, over {:?}",
+            what,
+            locks,
+            elapsed,
+            threshold
+        );
+    }
+}
+
+/// The hidden field of the struct warning when the struct is held for too long, once dropped.
+pub struct Hold {
+    locks: &'static str,
+    start: Instant,
+    threshold: Duration,
+}
+
+impl Hold {
+    pub fn start(locks: &'static str, threshold: Duration) -> Self {
+        Self {
+            locks,
+            start: Instant::now(),
+            threshold,
+        }
+    }
+}
+
+impl Drop for Hold {
+    fn drop(&mut self) {
+        check("holding", self.locks, self.start, self.threshold);
+    }
+}
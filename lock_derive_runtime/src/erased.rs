@@ -0,0 +1,18 @@
+use core::any::Any;
+
+/// The structs of `erased;`, giving the values of their guards by the name of their field, for
+/// the middlewares inspecting the guards held.
+pub trait ErasedLocks {
+    /// The names of the fields holding a single guard.
+    fn names(&self) -> &'static [&'static str];
+
+    /// The value of the guard in the field named `name`, if any.
+    fn get_any(&self, name: &str) -> Option<&dyn Any>;
+}
+
+impl<'e> dyn ErasedLocks + 'e {
+    /// The value of the guard in the field named `name`, if any and of type `T`.
+    pub fn get<T: Any>(&self, name: &str) -> Option<&T> {
+        self.get_any(name).and_then(|v| v.downcast_ref())
+    }
+}
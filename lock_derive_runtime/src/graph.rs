@@ -0,0 +1,86 @@
+/// A lock of a call site and its access.
+#[derive(Debug)]
+pub struct LockUse {
+    pub name: &'static str,
+    pub access: &'static str,
+}
+
+/// A call site and its locks, in acquisition order.
+#[derive(Debug)]
+pub struct LockSite {
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+    pub locks: &'static [LockUse],
+}
+
+inventory::collect!(LockSite);
+
+/// The call sites of all the crates of the program, sorted by location.
+pub fn lock_sites() -> Vec<&'static LockSite> {
+    let mut sites = inventory::iter::<LockSite>.into_iter().collect::<Vec<_>>();
+    sites.sort_by_key(|s| (s.file, s.line, s.column));
+    sites
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Renders the graph as DOT, an edge going from a lock held to the lock acquired next,
+/// labeled with the call site.
+pub fn lock_graph_dot() -> String {
+    let mut dot = String::from("digraph locks {\n");
+
+    for site in lock_sites() {
+        let label = escape(&format!("{}:{}:{}", site.file, site.line, site.column));
+
+        for lock in site.locks {
+            dot.push_str(&format!("    \"{}\";\n", escape(lock.name)));
+        }
+
+        for pair in site.locks.windows(2) {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{} ({} then {})\"];\n",
+                escape(pair[0].name),
+                escape(pair[1].name),
+                label,
+                pair[0].access,
+                pair[1].access,
+            ));
+        }
+    }
+
+    dot.push('}');
+    dot
+}
+
+/// Renders the call sites as JSON, each one with its locks in acquisition order.
+pub fn lock_graph_json() -> String {
+    let sites = lock_sites()
+        .into_iter()
+        .map(|site| {
+            let locks = site
+                .locks
+                .iter()
+                .map(|l| {
+                    format!(
+                        "{{\"name\":\"{}\",\"access\":\"{}\"}}",
+                        escape(l.name),
+                        l.access
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            format!(
+                "{{\"file\":\"{}\",\"line\":{},\"column\":{},\"locks\":[{}]}}",
+                escape(site.file),
+                site.line,
+                site.column,
+                locks.join(",")
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!("[{}]", sites.join(","))
+}
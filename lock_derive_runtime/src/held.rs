@@ -0,0 +1,231 @@
+use crate::model::thread::{self, ThreadId};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+static HELD: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+static TASKS: AtomicUsize = AtomicUsize::new(0);
+
+// the tasks are numbered on their first acquisition, the executors reusing the same `Task` for
+// the futures they run one after the other, `block_on` being one.
+futures::task_local! {
+    static TASK: usize = TASKS.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The holder of the locks: the task polling the chain of `locks!`, or the thread of
+/// `locks_sync!`.
+#[derive(Clone, PartialEq)]
+pub enum Owner {
+    Task(usize),
+    Thread(ThreadId),
+}
+
+impl Owner {
+    /// The task polling the chain, panics outside of a task. The invocations only take it while
+    /// their future is polled.
+    pub fn task() -> Self {
+        Owner::Task(TASK.with(|t| *t))
+    }
+
+    pub fn thread() -> Self {
+        Owner::Thread(thread::current().id())
+    }
+}
+
+/// A lock held by an invocation, `value` being the address of the value of its guard when it
+/// can be lent to `reentrant: [..]`, zero otherwise. The locks are told apart by crate, the
+/// names of the recipes being unique to a crate.
+struct Entry {
+    held: usize,
+    krate: &'static str,
+    id: &'static str,
+    owner: Owner,
+    write: bool,
+    value: usize,
+}
+
+impl Entry {
+    /// Whether the entry is a lock of the crate held by the holder.
+    fn is_held(&self, krate: &str, owner: &Owner) -> bool {
+        self.krate == krate && self.owner == *owner
+    }
+
+    /// Whether the guard can be lent for the access given, a written guard being lent for
+    /// reading as well.
+    fn lends(&self, write: bool) -> bool {
+        self.value != 0 && (self.write || !write)
+    }
+}
+
+fn held() -> MutexGuard<'static, Vec<Entry>> {
+    HELD.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The crate of the `module_path!()` of an invocation.
+fn krate(module: &'static str) -> &'static str {
+    module.split("::").next().unwrap_or(module)
+}
+
+/// Removes the locks from the registry when the struct is dropped.
+pub struct Held(usize);
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        held().retain(|t| t.held != self.0);
+    }
+}
+
+/// Panics if the holder already holds one of the locks, or in debug builds, one of the locks
+/// acquired after them by the other invocations of the crate.
+pub fn check(
+    module: &'static str,
+    ids: &[&'static str],
+    reentrant: &[(&'static str, bool)],
+    owner: &Owner,
+) {
+    let krate = krate(module);
+    let held = held();
+
+    // a lock of `reentrant: [..]` held in a compatible access is borrowed, not acquired.
+    let lent = |id: &str| {
+        reentrant.iter().any(|r| {
+            r.0 == id
+                && held
+                    .iter()
+                    .any(|t| t.id == id && t.is_held(krate, owner) && t.lends(r.1))
+        })
+    };
+
+    let ids = ids.iter().filter(|id| !lent(id)).collect::<Vec<_>>();
+
+    if let Some(t) = held
+        .iter()
+        .find(|t| ids.contains(&&t.id) && t.is_held(krate, owner))
+    {
+        let id = t.id;
+        drop(held);
+        panic!(
+            "lock `{}` acquired again while already held by the same task or thread",
+            id
+        );
+    }
+
+    // the chain acquires the locks by decreasing identity, a lock sorting after a held one is
+    // acquired in the opposite order of the other invocations.
+    if cfg!(debug_assertions) {
+        let nested = held
+            .iter()
+            .filter(|t| t.is_held(krate, owner))
+            .find_map(|t| ids.iter().find(|id| ***id > t.id).map(|id| (**id, t.id)));
+
+        if let Some((id, other)) = nested {
+            drop(held);
+            panic!(
+                "lock `{}` acquired while holding `{}`, which is acquired after it elsewhere; \
+                 acquire both in the same invocation",
+                id, other
+            );
+        }
+    }
+}
+
+/// Registers the locks, `(id, write, value)`, as held by the holder until the struct is
+/// dropped.
+pub fn enter(module: &'static str, locks: &[(&'static str, bool, usize)], owner: Owner) -> Held {
+    let id = NEXT.fetch_add(1, Ordering::Relaxed);
+    let krate = krate(module);
+    let mut held = held();
+
+    for t in locks {
+        held.push(Entry {
+            held: id,
+            krate,
+            id: t.0,
+            owner: owner.clone(),
+            write: t.1,
+            value: t.2,
+        });
+    }
+
+    Held(id)
+}
+
+/// The address of the value of a guard, lent to the nested invocations. The type of the guard is
+/// left to be inferred from the field of the struct.
+pub fn address<G: Deref>(guard: &G) -> usize {
+    &**guard as *const G::Target as *const () as usize
+}
+
+/// The address of the value of a guard held by the holder which can be lent for the access
+/// given, if any.
+pub fn lookup(module: &'static str, id: &str, write: bool, owner: &Owner) -> Option<usize> {
+    let krate = krate(module);
+
+    held()
+        .iter()
+        .find(|t| t.id == id && t.is_held(krate, owner) && t.lends(write))
+        .map(|t| t.value)
+}
+
+/// The guard of a lock of `reentrant: [..]`, either acquired by the invocation or borrowed from
+/// the guard held by an outer one.
+pub struct Reentrant<G: Deref>(Guard<G>);
+
+enum Guard<G: Deref> {
+    Acquired(G),
+    Held(*mut G::Target),
+}
+
+impl<G: Deref> Reentrant<G> {
+    /// The guard acquired by the invocation.
+    pub fn acquired(guard: G) -> Self {
+        Reentrant(Guard::Acquired(guard))
+    }
+
+    /// Borrows the value of the guard held by an outer invocation, at the address found by
+    /// `lookup()`.
+    ///
+    /// # Safety
+    ///
+    /// The outer struct must outlive the inner one and must not be used while the inner one
+    /// lives, the borrow being unknown to the compiler. The caller of the invocation vouches for
+    /// it by writing `unsafe reentrant: [..]`.
+    pub unsafe fn held(value: usize) -> Self
+    where
+        G::Target: Sized,
+    {
+        Reentrant(Guard::Held(value as *mut G::Target))
+    }
+}
+
+impl<G: Deref> Deref for Reentrant<G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &G::Target {
+        match &self.0 {
+            Guard::Acquired(guard) => guard,
+            // SAFETY: the value outlives `self` and is not written through the outer guard
+            // meanwhile, by the contract of `held()`.
+            Guard::Held(value) => unsafe { &**value },
+        }
+    }
+}
+
+impl<G: DerefMut> DerefMut for Reentrant<G> {
+    fn deref_mut(&mut self) -> &mut G::Target {
+        match &mut self.0 {
+            Guard::Acquired(guard) => guard,
+            // SAFETY: the value outlives `self` and the outer struct is not used while `self`
+            // lives, by the contract of `held()`, so this is the only access to the value.
+            Guard::Held(value) => unsafe { &mut **value },
+        }
+    }
+}
+
+// SAFETY: `Reentrant` only adds the raw pointer of `Guard::Held` to `G`. The value it points to is
+// guarded by the outer guard, which is not used while the inner one lives by the contract of
+// `held()`, so sending or sharing the borrow is sending or sharing the value itself, hence the
+// bounds on `G::Target`.
+unsafe impl<G: Deref + Send> Send for Reentrant<G> where G::Target: Send {}
+unsafe impl<G: Deref + Sync> Sync for Reentrant<G> where G::Target: Sync {}
@@ -0,0 +1,63 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Told the label of the call site and how long the locks were held.
+type Observer = fn(&'static str, Duration);
+
+static OBSERVER: Mutex<Option<Observer>> = Mutex::new(None);
+
+/// Reports how long the locks of the struct holding it were held, once dropped, for
+/// `report_hold: "label"`.
+pub struct Timer {
+    label: &'static str,
+    start: Instant,
+}
+
+impl Timer {
+    pub fn start(label: &'static str) -> Self {
+        Self {
+            label,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let observer = *OBSERVER.lock().unwrap_or_else(|e| e.into_inner());
+        observer.unwrap_or(log)(self.label, self.start.elapsed());
+    }
+}
+
+fn log(label: &'static str, elapsed: Duration) {
+    log::info!(target: "lock_derive::hold", "{} held for {:?}", label, elapsed);
+}
+
+/// Reports the hold durations of `report_hold: "label"` to `observer`, with the label and the
+/// duration, rather than to `log` on the `lock_derive::hold` target. The observer is the one of
+/// all the crates of the program.
+pub fn set_hold_observer(observer: Observer) {
+    *OBSERVER.lock().unwrap_or_else(|e| e.into_inner()) = Some(observer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static REPORTED: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn the_timer_reports_to_the_observer_once_dropped() {
+        set_hold_observer(|label, _| {
+            assert_eq!("transfer", label);
+            REPORTED.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let timer = Timer::start("transfer");
+        assert_eq!(0, REPORTED.load(Ordering::SeqCst));
+
+        drop(timer);
+        assert_eq!(1, REPORTED.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::tokens::krate;
+use std::sync::{Mutex, MutexGuard};
+
+type Inits = Vec<(&'static str, &'static str, Option<futures_locks::Mutex<()>>)>;
+
+static INITS: Mutex<Inits> = Mutex::new(Vec::new());
+
+/// The initialization token of a lock of `init: [..]`, `None` once the lock is initialized.
+pub fn init(module: &'static str, id: &'static str) -> Option<futures_locks::Mutex<()>> {
+    let krate = krate(module);
+    let mut inits = inits();
+
+    match inits.iter().find(|t| t.0 == krate && t.1 == id) {
+        Some(t) => t.2.clone(),
+        None => {
+            let token = futures_locks::Mutex::new(());
+            inits.push((krate, id, Some(token.clone())));
+            Some(token)
+        }
+    }
+}
+
+/// Drops the initialization token of the lock, its value being resolved.
+pub fn initialized(module: &'static str, id: &'static str) {
+    let krate = krate(module);
+    let mut inits = inits();
+
+    match inits.iter_mut().find(|t| t.0 == krate && t.1 == id) {
+        Some(t) => t.2 = None,
+        None => inits.push((krate, id, None)),
+    }
+}
+
+fn inits() -> MutexGuard<'static, Inits> {
+    INITS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_token_is_dropped_once_initialized() {
+        let token = init("bank", "init_accounts").unwrap();
+        assert!(init("bank::ledger", "init_accounts")
+            .unwrap()
+            .try_lock()
+            .is_ok());
+
+        let held = token.try_lock().ok().unwrap();
+        initialized("bank", "init_accounts");
+        drop(held);
+
+        assert!(init("bank", "init_accounts").is_none());
+        assert!(init("shop", "init_accounts").is_some());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::tokens::Tokens;
+use std::ops::{Deref, DerefMut};
+
+static INTENTS: Tokens = Tokens::new();
+
+/// The intent token of a lock of `intent: [..]`, held along with its read guard.
+pub fn intent(module: &'static str, id: &'static str) -> futures_locks::Mutex<()> {
+    INTENTS.get(module, id)
+}
+
+/// A read guard holding the intent of the lock, the only one allowed to upgrade to write, for
+/// `intent: [..]`. The upgrade is made by `upgrade_<field>()` on the struct, which keeps the
+/// intent while the locks are released and resolved again in order.
+///
+/// ```
+/// use futures::Future;
+/// use futures_locks::RwLock;
+/// use lock_derive_runtime::{Intent, Upgraded};
+///
+/// let lock = RwLock::new(10);
+/// let token = lock_derive_runtime::intent::intent(module_path!(), "accounts");
+///
+/// let mut read = Intent::new(lock.read().wait().unwrap(), token.lock().wait().unwrap());
+/// let intent = read.take_token();
+/// drop(read);
+///
+/// let mut write = Upgraded::new(lock.write().wait().unwrap());
+/// write.keep_token(intent);
+/// *write += 1;
+/// drop(write);
+///
+/// assert_eq!(11, *lock.read().wait().unwrap());
+/// ```
+pub struct Intent<R> {
+    guard: R,
+    token: Option<futures_locks::MutexGuard<()>>,
+}
+
+impl<R> Intent<R> {
+    pub fn new(guard: R, token: futures_locks::MutexGuard<()>) -> Self {
+        Self {
+            guard,
+            token: Some(token),
+        }
+    }
+
+    /// Takes the intent out of the guard, to be kept by the upgraded guard while the locks are
+    /// resolved again. Panics when taken twice.
+    pub fn take_token(&mut self) -> futures_locks::MutexGuard<()> {
+        self.token.take().expect("intent taken")
+    }
+}
+
+impl<R: Deref> Deref for Intent<R> {
+    type Target = R::Target;
+
+    fn deref(&self) -> &R::Target {
+        &self.guard
+    }
+}
+
+/// The write guard of the lock of an `Intent` once upgraded, keeping the intent until dropped.
+pub struct Upgraded<W> {
+    guard: W,
+    #[allow(dead_code)]
+    token: Option<futures_locks::MutexGuard<()>>,
+}
+
+impl<W> Upgraded<W> {
+    /// The write guard resolved by the chain, given the intent by `keep_token()` right after.
+    pub fn new(guard: W) -> Self {
+        Self { guard, token: None }
+    }
+
+    pub fn keep_token(&mut self, token: futures_locks::MutexGuard<()>) {
+        self.token = Some(token);
+    }
+}
+
+impl<W: Deref> Deref for Upgraded<W> {
+    type Target = W::Target;
+
+    fn deref(&self) -> &W::Target {
+        &self.guard
+    }
+}
+
+impl<W: DerefMut> DerefMut for Upgraded<W> {
+    fn deref_mut(&mut self) -> &mut W::Target {
+        &mut self.guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::Future;
+
+    #[test]
+    fn the_upgrade_keeps_the_intent() {
+        let lock = futures_locks::RwLock::new(10);
+        let token = intent("bank", "intent_accounts");
+
+        let mut read = Intent::new(lock.read().wait().unwrap(), token.lock().wait().unwrap());
+        let held = read.take_token();
+        drop(read);
+
+        let mut upgraded = Upgraded::new(lock.write().wait().unwrap());
+        upgraded.keep_token(held);
+
+        assert_eq!(10, *upgraded);
+        assert!(intent("bank::ledger", "intent_accounts")
+            .try_lock()
+            .is_err());
+
+        drop(upgraded);
+        assert!(token.try_lock().is_ok());
+    }
+}
@@ -0,0 +1,30 @@
+/// Sorts `order`, the indexes of `keys`, by key, for the keys of a lock or the instances of a
+/// lock requested by the same invocation, acquired in that order by `locks!` and `locks_sync!`.
+/// Two equal keys are an error, `false`.
+///
+/// ```
+/// let mut order = [0, 1, 2];
+/// assert!(lock_derive_runtime::keyed::order(&[&3, &1, &2], &mut order));
+/// assert_eq!([1, 2, 0], order);
+///
+/// assert!(!lock_derive_runtime::keyed::order(&[&1, &1], &mut [0, 1]));
+/// ```
+pub fn order<K: Ord + ?Sized>(keys: &[&K], order: &mut [usize]) -> bool {
+    order.sort_unstable_by(|a, b| keys[*a].cmp(keys[*b]));
+    !order.windows(2).any(|w| keys[w[0]] == keys[w[1]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_keys_are_sorted_whatever_their_type() {
+        let mut indexes = [0, 1, 2];
+        assert!(order(&["users", "accounts", "audit"], &mut indexes));
+        assert_eq!([1, 2, 0], indexes);
+
+        let mut indexes = [0, 1, 2];
+        assert!(!order(&[&(1, 2), &(0, 1), &(1, 2)], &mut indexes));
+    }
+}
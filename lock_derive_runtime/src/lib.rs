@@ -0,0 +1,174 @@
+/*!
+The support library of `lock_derive`, the machinery shared by the code the macros generate, which
+calls it rather than writing it out in each expansion. A proc macro crate cannot export anything
+but its macros, so the calling crate depends on `lock_derive_runtime` along with `lock_derive`.
+
+The items are reached through the macros: the public ones are re-exported at the root of the
+calling crate by `lock_derive::runtime!()`, the others are hidden. The state shared by the
+invocations, such as the turnstiles of `policy: write_preferring` or the waiters of `condvar;`,
+is kept here for all the crates of the program, the locks being told apart by crate and by the
+full path of their recipe. The events of the `telemetry`, `diagnostics` and `audit` features and
+the reports of `report_hold` are logged from here as well, so the calling crate does not depend
+on `log`. Without the default `std` feature of `lock_derive`, the calling crate turns off the
+default `std` feature, leaving what only needs `core`.
+
+The timeouts, the builder and the supervisor wait on the timer of a `RuntimeAdapter`: `Tokio`
+with the default `tokio` feature, or `AsyncStd` with the `async-std` feature, used by the
+invocations unless they name their own with `adapter: Type`.
+*/
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+mod adapter;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod audit;
+#[cfg(feature = "std")]
+mod block_on;
+#[cfg(feature = "std")]
+mod bridge;
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+mod cancel;
+#[cfg(feature = "std")]
+mod compat;
+#[cfg(feature = "std")]
+mod detach;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod diagnostics;
+mod erased;
+#[cfg(feature = "graph")]
+#[doc(hidden)]
+pub mod graph;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod held;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod hold;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod init;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod intent;
+#[doc(hidden)]
+pub mod keyed;
+#[cfg(feature = "std")]
+mod lock_set;
+#[cfg(feature = "std")]
+mod manager;
+#[cfg(feature = "std")]
+mod model;
+#[cfg(feature = "std")]
+mod order;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod owners;
+mod projection;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod recorder;
+#[cfg(feature = "std")]
+mod source;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod supervisor;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod telemetry;
+#[cfg(feature = "std")]
+mod timeout;
+#[cfg(feature = "std")]
+mod tokens;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod turnstile;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod version;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod waiters;
+
+#[cfg(feature = "async-std")]
+pub use self::adapter::AsyncStd;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub use self::adapter::DefaultAdapter;
+#[cfg(feature = "tokio")]
+pub use self::adapter::Tokio;
+#[cfg(feature = "std")]
+pub use self::adapter::{interval, Delay, RuntimeAdapter};
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use self::block_on::block_on;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use self::bridge::{
+    Async, AsyncInfallible, AsyncResolve, InfallibleResolve, Legacy, LegacyInfallible, Resolve,
+};
+#[cfg(feature = "std")]
+pub use self::builder::{LocksBuilder, LocksFuture, Observer};
+#[cfg(feature = "std")]
+pub use self::cancel::Cancelled;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use self::compat::Compat;
+#[cfg(feature = "std")]
+pub use self::detach::{detach, Detached};
+pub use self::erased::ErasedLocks;
+#[cfg(feature = "graph")]
+pub use self::graph::{lock_graph_dot, lock_graph_json, lock_sites, LockSite, LockUse};
+#[cfg(feature = "std")]
+pub use self::hold::set_hold_observer;
+#[cfg(feature = "std")]
+pub use self::intent::{Intent, Upgraded};
+#[cfg(feature = "std")]
+pub use self::lock_set::{LockRegistry, LockSet, LockSetGuards};
+#[cfg(feature = "std")]
+pub use self::manager::{LockFactory, LockManager, ManagedLocks};
+#[cfg(feature = "std")]
+pub use self::order::acquisition_order;
+#[cfg(feature = "std")]
+pub use self::owners::name_task;
+#[doc(hidden)]
+pub use self::projection::Projection;
+#[cfg(feature = "std")]
+pub use self::recorder::{Acquisition, Recorded, Recorder};
+#[cfg(feature = "std")]
+pub use self::source::{LockFuture, LockSource};
+#[cfg(feature = "std")]
+pub use self::supervisor::Supervisor;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use self::timeout::timeout;
+#[cfg(feature = "std")]
+pub use self::version::{Versioned, Written};
+
+#[cfg(feature = "graph")]
+#[doc(hidden)]
+pub use inventory;
+
+/// Registers a call site of the `graph` feature of `lock_derive` with `inventory`.
+#[cfg(feature = "graph")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! submit_site {
+    ($site:expr) => {
+        $crate::inventory::submit! { $site }
+    };
+}
+
+#[cfg(not(feature = "graph"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! submit_site {
+    ($site:expr) => {
+        compile_error!(concat!(
+            "the `graph` feature of `lock_derive` needs ",
+            "the `graph` feature of `lock_derive_runtime`"
+        ));
+    };
+}
@@ -0,0 +1,98 @@
+use crate::order::acquisition_order;
+use futures::{Future, Stream};
+use std::any::Any;
+use std::marker::PhantomData;
+
+type Guard = Box<dyn Any>;
+type Fut<T> = Box<dyn Future<Item = T, Error = failure::Error>>;
+
+/// The recipes reachable from a `LockSet`, implemented by `lock_set!`.
+pub trait LockRegistry {
+    /// Acquires the lock `name` for the access given, `read` or `write`, yielding its
+    /// type-erased guard, or `None` when the registry does not have it.
+    fn resolve(name: &'static str, access: &'static str) -> Option<Fut<Guard>>;
+}
+
+/// A set of locks chosen at runtime among the recipes of the registry, acquired in the same
+/// order as `locks!`.
+pub struct LockSet<R> {
+    locks: Vec<(&'static str, &'static str)>,
+    registry: PhantomData<R>,
+}
+
+impl<R> Default for LockSet<R> {
+    fn default() -> Self {
+        Self {
+            locks: Vec::new(),
+            registry: PhantomData,
+        }
+    }
+}
+
+impl<R: LockRegistry> LockSet<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(mut self, name: &'static str) -> Self {
+        self.locks.push((name, "read"));
+        self
+    }
+
+    pub fn write(mut self, name: &'static str) -> Self {
+        self.locks.push((name, "write"));
+        self
+    }
+
+    pub fn resolve(mut self) -> impl Future<Item = LockSetGuards, Error = failure::Error> {
+        if let Err(e) = acquisition_order(&mut self.locks) {
+            return futures::future::Either::A(futures::future::err(e));
+        }
+
+        let locks = futures::stream::iter_ok(self.locks);
+        let guards = LockSetGuards { guards: Vec::new() };
+
+        futures::future::Either::B(locks.fold(guards, |mut guards, (name, access)| {
+            let resolve = R::resolve(name, access).unwrap_or_else(|| {
+                Box::new(futures::future::err(failure::format_err!(
+                    "lock `{}` ({}) is not in the lock set registry",
+                    name,
+                    access
+                )))
+            });
+
+            resolve.map(move |guard| {
+                guards.guards.push((name, guard));
+                guards
+            })
+        }))
+    }
+}
+
+/// The guards of a `LockSet`, by lock name.
+pub struct LockSetGuards {
+    guards: Vec<(&'static str, Guard)>,
+}
+
+impl LockSetGuards {
+    /// The guard of the lock, if held and of type `G`.
+    pub fn get<G: 'static>(&self, name: &str) -> Option<&G> {
+        self.guards
+            .iter()
+            .find(|t| t.0 == name)
+            .and_then(|t| t.1.downcast_ref())
+    }
+
+    /// The guard of the lock, if held and of type `G`.
+    pub fn get_mut<G: 'static>(&mut self, name: &str) -> Option<&mut G> {
+        self.guards
+            .iter_mut()
+            .find(|t| t.0 == name)
+            .and_then(|t| t.1.downcast_mut())
+    }
+
+    /// The names of the locks held, in acquisition order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.guards.iter().map(|t| t.0)
+    }
+}
@@ -0,0 +1,153 @@
+use futures::{Future, Stream};
+use std::any::Any;
+use std::sync::{Mutex, MutexGuard};
+
+/// Acquires a lock registered in the `LockManager`, yielding its type-erased guard.
+pub type LockFactory = fn() -> Box<dyn Future<Item = Box<dyn Any>, Error = failure::Error>>;
+
+static FACTORIES: Mutex<Vec<(String, &'static str, LockFactory)>> = Mutex::new(Vec::new());
+
+/// The locks registered at runtime under a name, acquired in the same order as `locks!` when
+/// registered under the name of their recipe. The locks are registered for all the crates of
+/// the program.
+///
+/// ```
+/// use futures::Future;
+/// use lock_derive_runtime::LockManager;
+///
+/// LockManager::register("accounts", "write", || Box::new(futures::future::ok(Box::new(10) as _)));
+///
+/// let locks = LockManager::acquire(&["accounts:w"]).wait().unwrap();
+/// assert_eq!(Some(&10), locks.get::<i32>("accounts"));
+/// assert!(LockManager::acquire(&["accounts:r"]).wait().is_err());
+/// ```
+pub struct LockManager;
+
+impl LockManager {
+    /// Registers the lock `name` for the access given, `read` or `write`, replacing the lock
+    /// already registered under that name and access.
+    pub fn register(name: impl Into<String>, access: &'static str, factory: LockFactory) {
+        let name = name.into();
+        let mut factories = factories();
+
+        factories.retain(|t| t.0 != name || t.1 != access);
+        factories.push((name, access, factory));
+    }
+
+    /// Acquires the locks given as `name:r` for reading or `name:w` for writing, sorted by name.
+    pub fn acquire(locks: &[&str]) -> impl Future<Item = ManagedLocks, Error = failure::Error> {
+        match Self::factories(locks) {
+            Ok(factories) => futures::future::Either::A(futures::stream::iter_ok(factories).fold(
+                ManagedLocks { guards: Vec::new() },
+                |mut guards, (name, factory)| {
+                    factory().map(move |guard| {
+                        guards.guards.push((name, guard));
+                        guards
+                    })
+                },
+            )),
+            Err(e) => futures::future::Either::B(futures::future::err(e)),
+        }
+    }
+
+    /// The factories of the locks, in acquisition order.
+    fn factories(locks: &[&str]) -> Result<Vec<(String, LockFactory)>, failure::Error> {
+        let mut locks = locks
+            .iter()
+            .map(|lock| match lock.rsplit_once(':') {
+                Some((name, "r")) => Ok((name, "read")),
+                Some((name, "w")) => Ok((name, "write")),
+                _ => Err(failure::format_err!(
+                    "lock `{}` is expected as `name:r` or `name:w`",
+                    lock
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        crate::acquisition_order(&mut locks)?;
+
+        let factories = factories();
+
+        locks
+            .into_iter()
+            .map(|(name, access)| {
+                factories
+                    .iter()
+                    .find(|t| t.0 == name && t.1 == access)
+                    .map(|t| (t.0.clone(), t.2))
+                    .ok_or_else(|| {
+                        failure::format_err!(
+                            "lock `{}` ({}) is not registered in the lock manager",
+                            name,
+                            access
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+fn factories() -> MutexGuard<'static, Vec<(String, &'static str, LockFactory)>> {
+    FACTORIES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The guards of the `LockManager`, by lock name.
+pub struct ManagedLocks {
+    guards: Vec<(String, Box<dyn Any>)>,
+}
+
+impl ManagedLocks {
+    /// The guard of the lock, if held and of type `G`.
+    pub fn get<G: 'static>(&self, name: &str) -> Option<&G> {
+        self.guards
+            .iter()
+            .find(|t| t.0 == name)
+            .and_then(|t| t.1.downcast_ref())
+    }
+
+    /// The guard of the lock, if held and of type `G`.
+    pub fn get_mut<G: 'static>(&mut self, name: &str) -> Option<&mut G> {
+        self.guards
+            .iter_mut()
+            .find(|t| t.0 == name)
+            .and_then(|t| t.1.downcast_mut())
+    }
+
+    /// The names of the locks held, in acquisition order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.guards.iter().map(|t| t.0.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard() -> Box<dyn Future<Item = Box<dyn Any>, Error = failure::Error>> {
+        Box::new(futures::future::ok(Box::new(1) as Box<dyn Any>))
+    }
+
+    #[test]
+    fn the_locks_are_acquired_in_order() {
+        LockManager::register("manager_accounts", "read", guard);
+        LockManager::register("manager_users", "write", guard);
+
+        let locks = LockManager::acquire(&["manager_accounts:r", "manager_users:w"])
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            vec!["manager_users", "manager_accounts"],
+            locks.names().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(&1), locks.get::<i32>("manager_users"));
+    }
+
+    #[test]
+    fn the_locks_must_be_registered_with_their_access() {
+        LockManager::register("manager_audit", "read", guard);
+
+        assert!(LockManager::acquire(&["manager_audit:w"]).wait().is_err());
+        assert!(LockManager::acquire(&["manager_audit"]).wait().is_err());
+    }
+}
@@ -0,0 +1,6 @@
+// the `thread` module of the registry of the held locks, the one of `loom` when the program is
+// built with `--cfg loom` for model-checked tests.
+#[cfg(loom)]
+pub use loom::thread;
+#[cfg(not(loom))]
+pub use std::thread;
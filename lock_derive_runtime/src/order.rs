@@ -0,0 +1,27 @@
+/// Sorts the locks given by name and access in the order of their acquisition, the order of
+/// `locks!` whose chain acquires the last sorted lock first, so the locks chosen at runtime can
+/// be mixed with the invocations without deadlocks. A lock found more than once is an error.
+///
+/// ```
+/// let mut locks = vec![("accounts", "read"), ("users", "write")];
+/// lock_derive_runtime::acquisition_order(&mut locks).unwrap();
+/// assert_eq!(locks, [("users", "write"), ("accounts", "read")]);
+///
+/// let mut locks = vec![("users", "read"), ("users", "write")];
+/// assert!(lock_derive_runtime::acquisition_order(&mut locks).is_err());
+/// ```
+pub fn acquisition_order<N>(locks: &mut [(N, &'static str)]) -> Result<(), failure::Error>
+where
+    N: AsRef<str> + Ord,
+{
+    locks.sort();
+    locks.reverse();
+
+    match locks.windows(2).find(|w| w[0].0 == w[1].0) {
+        Some(w) => Err(failure::format_err!(
+            "lock `{}` found multiple times",
+            w[0].0.as_ref()
+        )),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,126 @@
+use crate::model::thread;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+static OWNERS: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+static TASKS: AtomicUsize = AtomicUsize::new(0);
+
+futures::task_local! {
+    static TASK: RefCell<Option<String>> = RefCell::new(None)
+}
+
+/// Names the task polling the chain in the errors of the timeouts of the `owners` feature of
+/// `lock_derive`, rather than numbering it on its first acquisition. Panics outside of a task.
+pub fn name_task<S: Into<String>>(name: S) {
+    TASK.with(|t| *t.borrow_mut() = Some(name.into()));
+}
+
+/// The task polling the chain, named by `name_task()` or numbered.
+pub fn task() -> String {
+    TASK.with(|t| {
+        let mut name = t.borrow_mut();
+        let name = name.get_or_insert_with(|| TASKS.fetch_add(1, Ordering::Relaxed).to_string());
+
+        format!("task `{}`", name)
+    })
+}
+
+pub fn thread() -> String {
+    let thread = thread::current();
+
+    match thread.name() {
+        Some(name) => format!("thread `{}`", name),
+        None => format!("thread `{:?}`", thread.id()),
+    }
+}
+
+/// A lock held by an invocation. The locks are told apart by crate, the names of the recipes
+/// being unique to a crate.
+struct Entry {
+    held: usize,
+    krate: &'static str,
+    id: &'static str,
+    write: bool,
+    owner: String,
+    site: &'static str,
+    since: Instant,
+}
+
+fn owners() -> MutexGuard<'static, Vec<Entry>> {
+    OWNERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The crate of the `module_path!()` of an invocation.
+fn krate(module: &'static str) -> &'static str {
+    module.split("::").next().unwrap_or(module)
+}
+
+/// Removes the locks from the registry when the struct is dropped.
+pub struct Owned(usize);
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        owners().retain(|t| t.held != self.0);
+    }
+}
+
+/// Registers the locks, `(id, access, write)`, as held by the holder until the struct is
+/// dropped.
+pub fn acquired(
+    module: &'static str,
+    site: &'static str,
+    locks: &[(&'static str, &'static str, bool)],
+    owner: String,
+) -> Owned {
+    let held = NEXT.fetch_add(1, Ordering::Relaxed);
+    let krate = krate(module);
+    let since = Instant::now();
+    let mut owners = owners();
+
+    for t in locks {
+        owners.push(Entry {
+            held,
+            krate,
+            id: t.0,
+            write: t.2,
+            owner: owner.clone(),
+            site,
+            since,
+        });
+    }
+
+    Owned(held)
+}
+
+/// The holder keeping the lock from being acquired in the access given, the one holding it the
+/// longest, as held by task `name` acquired at src/lib.rs:10 for 4.2s.
+pub fn holder(module: &'static str, id: &str, write: bool) -> Option<String> {
+    let krate = krate(module);
+
+    owners()
+        .iter()
+        .filter(|t| t.krate == krate && t.id == id && (write || t.write))
+        .min_by_key(|t| t.since)
+        .map(|t| {
+            format!(
+                "held by {} acquired at {} for {:.1}s",
+                t.owner,
+                t.site,
+                t.since.elapsed().as_secs_f64()
+            )
+        })
+}
+
+/// The first of the locks held in a conflicting access, in the order of their acquisition,
+/// along with its holder.
+pub fn blocked(
+    module: &'static str,
+    locks: &[(&'static str, &'static str, bool)],
+) -> Option<String> {
+    locks.iter().find_map(|t| {
+        holder(module, t.0, t.2).map(|h| format!("waiting for `{}` ({}), {}", t.0, t.1, h))
+    })
+}
@@ -0,0 +1,22 @@
+use core::ops::{Deref, DerefMut};
+
+/// A guard dereferencing to one of the inner fields of its value, for `name.field`.
+pub struct Projection<G: Deref, U: ?Sized> {
+    pub guard: G,
+    pub get: fn(&G::Target) -> &U,
+    pub get_mut: fn(&mut G::Target) -> &mut U,
+}
+
+impl<G: Deref, U: ?Sized> Deref for Projection<G, U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        (self.get)(&self.guard)
+    }
+}
+
+impl<G: DerefMut, U: ?Sized> DerefMut for Projection<G, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        (self.get_mut)(&mut self.guard)
+    }
+}
@@ -0,0 +1,159 @@
+use futures::{Future, Poll};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// A lock acquired while a recorder was current.
+#[derive(Clone, Debug)]
+pub struct Acquisition {
+    /// The identity of the lock, named after its recipe.
+    pub lock: &'static str,
+    /// `read`, `write`, ...
+    pub access: &'static str,
+    /// When the guard was resolved.
+    pub acquired: Instant,
+    /// When the struct holding the guard was dropped, if it was.
+    pub released: Option<Instant>,
+}
+
+type Events = Arc<Mutex<Vec<Acquisition>>>;
+
+thread_local! {
+    static CURRENT: RefCell<Vec<Events>> = const { RefCell::new(Vec::new()) };
+}
+
+fn current() -> Option<Events> {
+    CURRENT.with(|c| c.borrow().last().cloned())
+}
+
+/// Records the acquisition of a lock in the current recorder, if any.
+pub fn acquired(lock: &'static str, access: &'static str) {
+    if let Some(events) = current() {
+        events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Acquisition {
+                lock,
+                access,
+                acquired: Instant::now(),
+                released: None,
+            });
+    }
+}
+
+/// Marks the locks of a struct as released when it is dropped.
+pub struct Hold(Option<Events>, &'static [&'static str]);
+
+pub fn hold(locks: &'static [&'static str]) -> Hold {
+    Hold(current(), locks)
+}
+
+impl Drop for Hold {
+    fn drop(&mut self) {
+        if let Some(events) = &self.0 {
+            let now = Instant::now();
+            let mut events = events.lock().unwrap_or_else(|e| e.into_inner());
+
+            for t in events.iter_mut() {
+                if t.released.is_none() && self.1.contains(&t.lock) {
+                    t.released = Some(now);
+                }
+            }
+        }
+    }
+}
+
+/// Records the locks acquired by the futures it wraps, or by the closures it runs for
+/// `locks_sync!`, with the `test-util` feature of `lock_derive`.
+#[derive(Clone, Default)]
+pub struct Recorder(Events);
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the locks acquired while `future` is polled.
+    pub fn record<F: Future>(&self, future: F) -> Recorded<F> {
+        Recorded(self.clone(), future)
+    }
+
+    /// Records the locks acquired while `f` runs.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Exit;
+
+        impl Drop for Exit {
+            fn drop(&mut self) {
+                CURRENT.with(|c| c.borrow_mut().pop());
+            }
+        }
+
+        CURRENT.with(|c| c.borrow_mut().push(self.0.clone()));
+        let _exit = Exit;
+        f()
+    }
+
+    /// The locks acquired so far, in order.
+    pub fn acquisitions(&self) -> Vec<Acquisition> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The identities of the locks acquired so far, in order.
+    pub fn locks(&self) -> Vec<&'static str> {
+        self.acquisitions().iter().map(|t| t.lock).collect()
+    }
+
+    /// Panics unless the first acquisitions of the locks given are in that order.
+    #[track_caller]
+    pub fn assert_order(&self, locks: &[&str]) {
+        let recorded = self.locks();
+        let positions = locks
+            .iter()
+            .map(|lock| recorded.iter().position(|t| t == lock))
+            .collect::<Vec<_>>();
+
+        let ordered =
+            positions.iter().all(Option::is_some) && positions.windows(2).all(|w| w[0] < w[1]);
+
+        assert!(
+            ordered,
+            "expected the locks to be acquired in the order {:?}, recorded {:?}",
+            locks, recorded
+        );
+    }
+
+    /// Whether the locks `a` and `b` were held at the same time, a lock not released yet being
+    /// still held.
+    pub fn held_together(&self, a: &str, b: &str) -> bool {
+        let acquisitions = self.acquisitions();
+        let holds = |lock: &str| {
+            acquisitions
+                .iter()
+                .filter(|t| t.lock == lock)
+                .map(|t| (t.acquired, t.released))
+                .collect::<Vec<_>>()
+        };
+
+        let (a, b) = (holds(a), holds(b));
+
+        a.iter().any(|a| {
+            b.iter().any(|b| {
+                a.1.is_none_or(|released| b.0 < released)
+                    && b.1.is_none_or(|released| a.0 < released)
+            })
+        })
+    }
+}
+
+/// A future whose acquisitions are recorded, see `Recorder::record()`.
+pub struct Recorded<F>(Recorder, F);
+
+impl<F: Future> Future for Recorded<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<F::Item, F::Error> {
+        let Recorded(recorder, future) = self;
+        recorder.scope(|| future.poll())
+    }
+}
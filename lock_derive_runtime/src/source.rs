@@ -0,0 +1,50 @@
+use futures::Future;
+
+/// The future of a guard returned by a `LockSource`.
+pub type LockFuture<G> = Box<dyn Future<Item = G, Error = failure::Error> + Send>;
+
+/// A lock usable by `locks!` without a recipe, `name: Type = expr`.
+pub trait LockSource {
+    type ReadGuard;
+    type WriteGuard;
+
+    fn read(&self) -> LockFuture<Self::ReadGuard>;
+    fn write(&self) -> LockFuture<Self::WriteGuard>;
+
+    /// Tries to acquire the lock for reading without waiting, for `optimistic;`.
+    fn try_read(&self) -> Option<Self::ReadGuard> {
+        None
+    }
+
+    /// Tries to acquire the lock for writing without waiting, for `optimistic;`.
+    fn try_write(&self) -> Option<Self::WriteGuard> {
+        None
+    }
+}
+
+impl<T: Send + Sync + 'static> LockSource for futures_locks::RwLock<T> {
+    type ReadGuard = futures_locks::RwLockReadGuard<T>;
+    type WriteGuard = futures_locks::RwLockWriteGuard<T>;
+
+    fn read(&self) -> LockFuture<Self::ReadGuard> {
+        Box::new(
+            self.read()
+                .map_err(|_| failure::format_err!("RwLock error")),
+        )
+    }
+
+    fn write(&self) -> LockFuture<Self::WriteGuard> {
+        Box::new(
+            self.write()
+                .map_err(|_| failure::format_err!("RwLock error")),
+        )
+    }
+
+    fn try_read(&self) -> Option<Self::ReadGuard> {
+        self.try_read().ok()
+    }
+
+    fn try_write(&self) -> Option<Self::WriteGuard> {
+        self.try_write().ok()
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A supervisor of a lock, given by the `(supervisor)` arm of its recipe, which marks the lock as
+/// invalidated for the `supervised;` invocations of the `supervisor` feature of `lock_derive`.
+///
+/// ```
+/// use lock_derive_runtime::Supervisor;
+/// use std::time::Duration;
+///
+/// static ACCOUNTS: Supervisor = Supervisor::new();
+///
+/// ACCOUNTS.invalidate(Duration::from_secs(0));
+/// assert!(ACCOUNTS.grace_elapsed());
+///
+/// ACCOUNTS.restore();
+/// assert!(!ACCOUNTS.is_invalidated());
+/// ```
+#[derive(Debug, Default)]
+pub struct Supervisor(Mutex<Option<(Instant, Duration)>>);
+
+impl Supervisor {
+    pub const fn new() -> Self {
+        Supervisor(Mutex::new(None))
+    }
+
+    /// Invalidates the lock: the structs holding it see `is_invalidated()`, and once `grace`
+    /// has elapsed, the acquisitions waiting on it take their guard from `force_resolve`.
+    pub fn invalidate(&self, grace: Duration) {
+        *self.state() = Some((Instant::now(), grace));
+    }
+
+    /// Ends the invalidation of the lock, once the task wedging it is dealt with.
+    pub fn restore(&self) {
+        *self.state() = None;
+    }
+
+    pub fn is_invalidated(&self) -> bool {
+        self.state().is_some()
+    }
+
+    /// Whether the lock is invalidated and its grace period has elapsed.
+    pub fn grace_elapsed(&self) -> bool {
+        self.state()
+            .is_some_and(|(since, grace)| since.elapsed() >= grace)
+    }
+
+    fn state(&self) -> MutexGuard<'_, Option<(Instant, Duration)>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Logs on the `lock_derive::supervisor` target that the acquisition of `lock` is forced, its
+/// supervisor having invalidated it.
+pub fn forced(lock: &'static str) {
+    log::error!(
+        target: "lock_derive::supervisor",
+        "lock {} was invalidated by its supervisor, forcing its acquisition",
+        lock
+    );
+}
@@ -0,0 +1,77 @@
+use crate::Cancelled;
+
+/// Logs the failure of a lock bundle to resolve on the `lock_derive::telemetry` target, labeled
+/// with its `locks`, `accounts:read,users:write`, and the `cause` found in the error chain.
+pub fn failed(locks: &'static str, e: &failure::Error) {
+    let cause = e.iter_chain().filter_map(cause).next().unwrap_or("backend");
+
+    log::warn!(
+        target: "lock_derive::telemetry",
+        locks = locks,
+        cause = cause;
+        "lock bundle resolve failed: {}",
+        e
+    );
+}
+
+/// The cause of a failure of the chain: a timeout, a cancellation, the shutdown or the overload
+/// of the runtime, `None` for the errors of the recipes.
+fn cause(f: &dyn failure::Fail) -> Option<&'static str> {
+    #[cfg(feature = "tokio")]
+    {
+        if let Some(e) = f.downcast_ref::<tokio::timer::Error>() {
+            return Some(if e.is_shutdown() {
+                "shutdown"
+            } else if e.is_at_capacity() {
+                "overload"
+            } else {
+                "backend"
+            });
+        }
+
+        if let Some(e) = f.downcast_ref::<tokio::executor::SpawnError>() {
+            return Some(if e.is_shutdown() {
+                "shutdown"
+            } else {
+                "overload"
+            });
+        }
+    }
+
+    if f.downcast_ref::<futures::Canceled>().is_some() || f.downcast_ref::<Cancelled>().is_some() {
+        return Some("cancellation");
+    }
+
+    if let Some(e) = f.downcast_ref::<std::io::Error>() {
+        match e.kind() {
+            std::io::ErrorKind::TimedOut => return Some("timeout"),
+            std::io::ErrorKind::Interrupted => return Some("cancellation"),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn cause_of(e: failure::Error) -> &'static str {
+        e.iter_chain().filter_map(cause).next().unwrap_or("backend")
+    }
+
+    #[test]
+    fn the_causes_are_found_in_the_error_chain() {
+        let timed_out = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        let canceled = failure::Error::from(futures::Canceled);
+        let elapsed = crate::timeout::timed_out(failure::err_msg("acquiring write lock `a`"));
+
+        assert_eq!("timeout", cause_of(failure::Error::from(timed_out)));
+        assert_eq!("timeout", cause_of(elapsed));
+        assert_eq!("cancellation", cause_of(canceled));
+        assert_eq!("cancellation", cause_of(failure::Error::from(Cancelled)));
+        assert_eq!("backend", cause_of(failure::err_msg("Lock error")));
+    }
+}
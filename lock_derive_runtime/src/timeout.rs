@@ -0,0 +1,32 @@
+use crate::adapter::RuntimeAdapter;
+use futures::future::Either;
+use futures::Future;
+use std::io;
+use std::time::Duration;
+
+/// The error of a timeout, a timed out `std::io::Error` carrying the message, which telemetry
+/// labels as a `timeout`.
+pub(crate) fn timed_out(e: failure::Error) -> failure::Error {
+    io::Error::new(io::ErrorKind::TimedOut, e.to_string()).into()
+}
+
+/// Fails the acquisition of a lock once `duration` elapses on the timer of the adapter, with the
+/// error given by `elapsed` naming the lock.
+pub fn timeout<A, F, E>(
+    future: F,
+    duration: Duration,
+    elapsed: E,
+) -> impl Future<Item = F::Item, Error = failure::Error>
+where
+    A: RuntimeAdapter,
+    F: Future<Error = failure::Error>,
+    E: FnOnce() -> failure::Error,
+{
+    future
+        .select2(A::delay(duration))
+        .then(move |result| match result {
+            Ok(Either::A((value, _))) => Ok(value),
+            Ok(Either::B(_)) => Err(timed_out(elapsed())),
+            Err(Either::A((e, _))) | Err(Either::B((e, _))) => Err(e),
+        })
+}
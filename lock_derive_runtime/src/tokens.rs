@@ -0,0 +1,53 @@
+use std::sync::{Mutex, MutexGuard};
+
+/// The tokens of the locks, a `futures_locks::Mutex` per lock told apart by crate and by the
+/// full path of its recipe.
+pub(crate) struct Tokens(Mutex<Vec<(&'static str, &'static str, futures_locks::Mutex<()>)>>);
+
+impl Tokens {
+    pub(crate) const fn new() -> Self {
+        Tokens(Mutex::new(Vec::new()))
+    }
+
+    pub(crate) fn get(&self, module: &'static str, id: &'static str) -> futures_locks::Mutex<()> {
+        let krate = krate(module);
+        let mut tokens = self.lock();
+
+        match tokens.iter().find(|t| t.0 == krate && t.1 == id) {
+            Some(t) => t.2.clone(),
+            None => {
+                let token = futures_locks::Mutex::new(());
+                tokens.push((krate, id, token.clone()));
+                token
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<(&'static str, &'static str, futures_locks::Mutex<()>)>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The crate of the invocation, the first segment of its `module_path!()`.
+pub(crate) fn krate(module: &'static str) -> &'static str {
+    module.split("::").next().unwrap_or(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_locks_are_told_apart_by_crate() {
+        let tokens = Tokens::new();
+        let _held = tokens
+            .get("bank::ledger", "accounts")
+            .try_lock()
+            .ok()
+            .unwrap();
+
+        assert!(tokens.get("bank", "accounts").try_lock().is_err());
+        assert!(tokens.get("bank", "users").try_lock().is_ok());
+        assert!(tokens.get("shop", "accounts").try_lock().is_ok());
+    }
+}
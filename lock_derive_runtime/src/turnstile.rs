@@ -0,0 +1,10 @@
+use crate::tokens::Tokens;
+
+static TURNSTILES: Tokens = Tokens::new();
+
+/// The turnstile of a lock of `policy: write_preferring`, held by a writer while waiting for the
+/// lock and passed through by the readers, so the readers coming after a waiting writer queue
+/// behind it.
+pub fn turnstile(module: &'static str, id: &'static str) -> futures_locks::Mutex<()> {
+    TURNSTILES.get(module, id)
+}
@@ -0,0 +1,102 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+static VERSIONS: Mutex<Vec<(&'static str, &'static str, &'static AtomicU64)>> =
+    Mutex::new(Vec::new());
+
+/// The version of a lock, bumped each time the lock is written. The locks are told apart by
+/// crate and by the full path of their recipe.
+pub fn version(module: &'static str, id: &'static str) -> &'static AtomicU64 {
+    let krate = module.split("::").next().unwrap_or(module);
+    let mut versions = VERSIONS.lock().unwrap_or_else(|e| e.into_inner());
+
+    match versions.iter().find(|t| t.0 == krate && t.1 == id) {
+        Some(t) => t.2,
+        None => {
+            let version = Box::leak(Box::new(AtomicU64::new(0)));
+            versions.push((krate, id, version));
+            version
+        }
+    }
+}
+
+/// Bumps the versions of the locks written, their guards still held.
+pub fn commit(module: &'static str, ids: &[&'static str]) {
+    for id in ids {
+        version(module, id).fetch_add(1, Ordering::Release);
+    }
+}
+
+/// Bumps the versions of the write locks of a struct when it is dropped, before its guards are
+/// released. `into_parts()` and `tuple;` give it along with the guards, to be dropped once the
+/// writes are made.
+pub struct Written(Vec<&'static AtomicU64>);
+
+pub fn written(module: &'static str, ids: &[&'static str]) -> Written {
+    Written(ids.iter().map(|id| version(module, id)).collect())
+}
+
+impl Drop for Written {
+    fn drop(&mut self) {
+        for version in &self.0 {
+            version.fetch_add(1, Ordering::Release);
+        }
+    }
+}
+
+/// The value of a lock of `versioned: [..]`, cloned under its read lock along with the version
+/// of the lock, the read lock being released at once. The value is current as long as the lock
+/// was not written since.
+///
+/// ```
+/// use lock_derive_runtime::Versioned;
+///
+/// let accounts = Versioned::new(10, module_path!(), "accounts");
+/// assert!(accounts.is_current());
+///
+/// drop(lock_derive_runtime::version::written(module_path!(), &["accounts"]));
+/// assert!(!accounts.is_current());
+/// assert_eq!(10, *accounts);
+/// ```
+pub struct Versioned<T> {
+    value: T,
+    version: u64,
+    current: &'static AtomicU64,
+}
+
+impl<T> Versioned<T> {
+    /// Captures the version of the lock `id` in the crate of `module`, to be called with the read
+    /// lock held.
+    pub fn new(value: T, module: &'static str, id: &'static str) -> Self {
+        let current = version(module, id);
+
+        Self {
+            value,
+            version: current.load(Ordering::Acquire),
+            current,
+        }
+    }
+
+    /// The version of the lock when the value was read.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Whether the lock was not written since the value was read.
+    pub fn is_current(&self) -> bool {
+        self.current.load(Ordering::Acquire) == self.version
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Versioned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
@@ -0,0 +1,63 @@
+use crate::tokens::krate;
+use futures::sync::oneshot::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+type Waiters = Vec<(&'static str, &'static str, Vec<Sender<()>>)>;
+
+static WAITERS: Mutex<Waiters> = Mutex::new(Vec::new());
+
+/// Registers a waiter of `condvar;` on the lock, notified by the next `notify()` on it.
+pub fn wait(module: &'static str, id: &'static str) -> Receiver<()> {
+    let krate = krate(module);
+    let (sender, receiver) = oneshot::channel();
+    let mut waiters = WAITERS.lock().unwrap_or_else(|e| e.into_inner());
+
+    let waiters = match waiters.iter().position(|t| t.0 == krate && t.1 == id) {
+        Some(i) => &mut waiters[i].2,
+        None => {
+            waiters.push((krate, id, Vec::new()));
+            &mut waiters.last_mut().expect("waiters").2
+        }
+    };
+
+    // the waiters notified through another lock are gone.
+    waiters.retain(|s| !s.is_canceled());
+    waiters.push(sender);
+    receiver
+}
+
+/// Wakes the waiters of the lock.
+pub fn notify(module: &'static str, id: &'static str) {
+    let krate = krate(module);
+    let mut waiters = WAITERS.lock().unwrap_or_else(|e| e.into_inner());
+
+    if let Some(t) = waiters.iter_mut().find(|t| t.0 == krate && t.1 == id) {
+        for waiter in t.2.drain(..) {
+            let _ = waiter.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{Async, Future};
+
+    #[test]
+    fn the_waiters_of_the_lock_are_notified() {
+        let mut accounts = wait("bank", "waiters_accounts");
+        let mut users = wait("bank", "waiters_users");
+        let mut other = wait("shop", "waiters_accounts");
+
+        notify("bank::ledger", "waiters_accounts");
+
+        futures::future::lazy(|| {
+            assert_eq!(Ok(Async::Ready(())), accounts.poll());
+            assert_eq!(Ok(Async::NotReady), users.poll());
+            assert_eq!(Ok(Async::NotReady), other.poll());
+            Ok::<_, ()>(())
+        })
+        .wait()
+        .unwrap();
+    }
+}
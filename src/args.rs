@@ -0,0 +1,1529 @@
+use crate::poison::Poison;
+use crate::timeout::Timeout;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use std::collections::HashMap;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{
+    bracketed, parenthesized, parse_quote, token, AngleBracketedGenericArguments, Error, Expr,
+    Ident, LitInt, LitStr, Member, Path, Token, Type, TypeParamBound,
+};
+
+const EXPECTED_FLAGS: &str = "Expected `blocking`, `owned`, `optimistic`, `no_traits`, \
+                              `builder`, `hierarchy`, `transact`, `condvar`, `release`, \
+                              `tuple`, `erased`, `infallible`, `supervised` or `impl Trait`.";
+
+#[derive(Clone)]
+pub struct Args {
+    /// `blocking;` also generates `resolve_blocking()` and resolves the locks on the spot.
+    pub blocking: bool,
+    /// `owned;` takes the lock instances by value so the struct and the future are `'static`.
+    pub owned: bool,
+    /// `optimistic;` tries all the locks at once before falling back to the ordered chain.
+    pub optimistic: bool,
+    /// `builder;` evaluates to a `LocksBuilder` setting the timeout, the retries and the
+    /// observers of the acquisition at runtime.
+    pub builder: bool,
+    /// `hierarchy;` checks at compile time that the locks are acquired by increasing level.
+    pub hierarchy: bool,
+    /// `transact;` generates `transact()`, applying the staged values of the write locks only
+    /// when the closure succeeds.
+    pub transact: bool,
+    /// `condvar;` generates `wait_until()` and `notify()`, waiting on the locks of the struct
+    /// without holding them.
+    pub condvar: bool,
+    /// `release;` generates `release()`, giving the guards back to the `release` arm of their
+    /// recipe.
+    pub release: bool,
+    /// `tuple;` evaluates to a tuple of the guards, in the order of the sections, without
+    /// generating a struct.
+    pub tuple: bool,
+    /// `erased;` generates `get::<T>()`, giving the value of a guard by name, and implements
+    /// `ErasedLocks`.
+    pub erased: bool,
+    /// `infallible;` declares recipes that cannot fail, the invocation evaluating to a
+    /// `std::future::Future` of the struct rather than of a `Result`.
+    pub infallible: bool,
+    /// `no_traits;` skips the `traits` arm of the recipes, when two of them implement the same
+    /// trait.
+    pub no_traits: bool,
+    /// `supervised;` races the acquisitions against the supervisor of the locks and generates
+    /// `is_invalidated()`, with the `supervisor` feature.
+    #[cfg_attr(not(feature = "supervisor"), allow(dead_code))]
+    pub supervised: bool,
+    /// `impl Trait;` implements the trait on the struct, with the methods given by the `impl`
+    /// arm of the recipes.
+    pub impls: Vec<Path>,
+    /// `policy: write_preferring` queues the readers behind the waiting writers.
+    pub write_preferring: bool,
+    /// `poison: propagate`, `clear` or `ignore`, the handling of the poisoned `std::sync` locks.
+    pub poison: Option<Poison>,
+    /// The groups of `include: [..]` not yet expanded, each one appending its own sections.
+    pub includes: Vec<Ident>,
+    /// The flags and the sections of the invocation, less the includes, given back to the
+    /// groups to expand.
+    pub tokens: TokenStream,
+    /// The closure running with the guards, released as soon as it completes.
+    pub closure: Option<TokenStream>,
+    /// `cancel: token` abandons the acquisition once the token is cancelled.
+    pub cancel: Option<Expr>,
+    /// `assert: Send + 'static` checks at compile time that the guards and the value of the
+    /// invocation satisfy the bounds.
+    pub assert: Option<TokenStream>,
+    /// `report_hold: "label"` reports how long the locks were held when the struct is dropped.
+    pub report_hold: Option<LitStr>,
+    /// `drop_order: [..]`, the fields released first when the struct is dropped.
+    pub drop_order: Vec<Ident>,
+    /// `adapter: Type`, the `RuntimeAdapter` of the timeouts and of the builder.
+    pub adapter: Option<Type>,
+    /// The name of the struct given by `locks_struct!`, defined at module level rather than in
+    /// the expression of the call site.
+    pub ident: Option<Ident>,
+    /// The field written by the struct resolved by `upgrade_<field>()`, read by the struct
+    /// upgraded.
+    pub upgraded: Option<Ident>,
+    /// The number of replicas of `quorum: 2 of [..] @ 1s` to acquire, and the deadline of each
+    /// replica.
+    pub quorum: Option<(usize, Timeout)>,
+    /// `diagnostics: [acquire @ 100ms, hold @ 1s]`, the thresholds of the `diagnostics` feature.
+    #[cfg_attr(not(feature = "diagnostics"), allow(dead_code))]
+    pub thresholds: Thresholds,
+    pub items: Vec<Item>,
+}
+
+impl Args {
+    /// The generated struct takes a lifetime as soon as a lock is borrowed from an instance.
+    pub fn has_lifetime(&self) -> bool {
+        self.items.iter().any(Item::is_borrowed)
+    }
+
+    /// Locks borrowed or keyed from the call site can only be resolved inline at the call site.
+    pub fn is_inline(&self) -> bool {
+        self.items.iter().any(|item| {
+            item.lock.is_some()
+                || item.key.is_some()
+                || item.keys.is_some()
+                || item.count.is_some()
+                || item.source.is_some()
+        })
+    }
+
+    /// The items in the order of their release: the fields of `drop_order: [..]` first, then
+    /// the others in the reverse order of their acquisition, which is the order of the items
+    /// since the chain acquires the last item first.
+    pub fn release_order(&self) -> Vec<&Item> {
+        let first = self
+            .drop_order
+            .iter()
+            .filter_map(|field| self.items.iter().find(|t| t.field == *field));
+
+        let rest = self
+            .items
+            .iter()
+            .filter(|t| !self.drop_order.contains(&t.field));
+
+        first.chain(rest).collect()
+    }
+
+    /// Splits the items in acquisition steps. Keyed items sharing the same lock form a single
+    /// step, ordered by key at runtime, and so do the borrowed instances of the same lock,
+    /// ordered by address. The replicas of `quorum` form a single step as well.
+    pub fn steps(&self) -> Vec<Vec<(usize, &Item)>> {
+        let mut steps: Vec<Vec<(usize, &Item)>> = Vec::new();
+
+        for (i, item) in self.items.iter().enumerate() {
+            match steps.last_mut() {
+                Some(step) if item.quorum && step[0].1.quorum => step.push((i, item)),
+                Some(step)
+                    if (item.key.is_some() || item.is_borrowed())
+                        && step[0].1.name == item.name =>
+                {
+                    step.push((i, item))
+                }
+                _ => steps.push(vec![(i, item)]),
+            }
+        }
+
+        steps
+    }
+}
+
+#[derive(Clone)]
+pub struct Item {
+    /// The canonical identity of the lock, used to detect duplicates and to sort the locks: the
+    /// full path of its recipe.
+    pub id: String,
+    /// The last segment of the path, naming the field.
+    pub name: Ident,
+    /// The path of the recipe, `storage::accounts`.
+    pub path: Path,
+    /// The type arguments given to the arms of the recipe, `cache::<User>`.
+    pub generics: Option<AngleBracketedGenericArguments>,
+    /// The field of the generated struct holding the guard.
+    pub field: Ident,
+    pub access: Access,
+    pub lock: Option<Expr>,
+    pub key: Option<Expr>,
+    /// The keys of a batch acquisition, `name <- keys`.
+    pub keys: Option<Expr>,
+    /// The number of permits to acquire, `name(count)`.
+    pub count: Option<Expr>,
+    /// The lock instance is owned by the recipe rather than borrowed.
+    pub owned: bool,
+    /// The lock implements `LockSource` rather than having a recipe, `name: Type = expr`.
+    pub source: Option<Source>,
+    /// The time given to the lock to be acquired, `name @ 200ms`.
+    pub timeout: Option<Timeout>,
+    /// The guard is projected to an inner field, `name => .field: Type`.
+    pub projection: Option<Projection>,
+    /// `policy: write_preferring` queues the readers behind the waiting writers.
+    pub write_preferring: bool,
+    /// `poison: ..`, the `resolve` arm of the blocking recipe returning a `LockResult`.
+    pub poison: Option<Poison>,
+    /// `infallible;` takes a `std::future::Future` of the `resolve` arm as resolving to the
+    /// guard itself, without a `Result`.
+    pub infallible: bool,
+    /// `adapter: Type`, the `RuntimeAdapter` of the timeout and of the supervisor.
+    pub adapter: Option<Type>,
+    /// The position of the lock in the sections of the invocation, the position of its guard
+    /// for `tuple;`.
+    pub index: usize,
+    /// `reentrant: [..]` borrows the guard already held by the holder rather than acquiring the
+    /// lock again.
+    pub reentrant: bool,
+    /// `upgrade: [..]` generates `upgrade_<field>()`, acquiring the read lock again for writing.
+    pub upgradable: bool,
+    /// `escalate: [..]` writes the read lock once `escalate()` finds that a write is needed.
+    pub escalated: bool,
+    /// The lock of `intent: [..]` written by the struct resolved by `upgrade_<field>()`, its
+    /// field keeping the intent.
+    pub upgraded: bool,
+    /// A replica of `quorum: 2 of [..]`, its field holding the guard only when it was acquired.
+    pub quorum: bool,
+    /// `supervised;` races the acquisition against the supervisor of the lock.
+    #[cfg_attr(not(feature = "supervisor"), allow(dead_code))]
+    pub supervised: bool,
+}
+
+/// A lock implementing `LockSource`, `: Type = expr`.
+#[derive(Clone)]
+pub struct Source {
+    pub ty: Type,
+    pub expr: Expr,
+}
+
+impl Parse for Source {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let _: Token![:] = stream.parse()?;
+        let ty = stream.parse()?;
+        let _: Token![=] = stream.parse()?;
+
+        Ok(Self {
+            ty,
+            expr: stream.parse()?,
+        })
+    }
+}
+
+/// A projection of the guard to an inner field, `=> .field: Type`.
+#[derive(Clone)]
+pub struct Projection {
+    pub members: Vec<Member>,
+    pub ty: Type,
+}
+
+impl Parse for Projection {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let _: Token![=>] = stream.parse()?;
+        let mut members = Vec::new();
+
+        while stream.peek(Token![.]) {
+            let _: Token![.] = stream.parse()?;
+            members.push(stream.parse()?);
+        }
+
+        if members.is_empty() {
+            return Err(stream.error("Expected `.field`."));
+        }
+
+        let _: Token![:] = stream.parse()?;
+
+        Ok(Self {
+            members,
+            ty: stream.parse()?,
+        })
+    }
+}
+
+/// The thresholds over which the `diagnostics` feature logs a warning, `acquire` for the time
+/// taken to acquire all the locks and `hold` for the time the struct is held.
+#[derive(Clone)]
+pub struct Thresholds {
+    pub acquire: Timeout,
+    pub hold: Timeout,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            acquire: Timeout {
+                millis: 100,
+                text: "100ms".to_string(),
+            },
+            hold: Timeout {
+                millis: 1000,
+                text: "1s".to_string(),
+            },
+        }
+    }
+}
+
+impl Parse for Thresholds {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let mut thresholds = Self::default();
+
+        for (name, timeout) in
+            <Punctuated<(Ident, Timeout), Token![,]>>::parse_terminated_with(stream, |stream| {
+                Ok((stream.parse()?, stream.parse()?))
+            })?
+        {
+            match name.to_string().as_str() {
+                "acquire" => thresholds.acquire = timeout,
+                "hold" => thresholds.hold = timeout,
+                _ => return Err(Error::new(name.span(), "Expected `acquire` or `hold`.")),
+            }
+        }
+
+        Ok(thresholds)
+    }
+}
+
+impl Item {
+    /// Whether the guard borrows from a lock instance of the call site.
+    pub fn is_borrowed(&self) -> bool {
+        self.lock.is_some() && !self.owned
+    }
+
+    /// The name of the access, as given to the recipe.
+    pub fn access_name(&self) -> &'static str {
+        self.access.name()
+    }
+
+    /// Whether the guard may be lent to a nested invocation with `reentrant: [..]`, a `read` or
+    /// `write` lock of a recipe dereferencing to the value of the lock.
+    pub fn is_lendable(&self) -> bool {
+        self.lock.is_none()
+            && self.key.is_none()
+            && self.keys.is_none()
+            && self.source.is_none()
+            && self.projection.is_none()
+            && !self.quorum
+            && matches!(
+                self.access,
+                Access::Read | Access::Write | Access::BlockingRead | Access::BlockingWrite
+            )
+    }
+}
+
+/// A lock entry, either `name` for a lock known to the recipe or `name(expr)` for a lock
+/// instance borrowed from the call site, `name::<Type>` giving type arguments to the recipe,
+/// optionally followed by `[key]` for keyed locks or by `<- keys` for a batch of keys, `[..]` for
+/// all the locks of a collection, `as alias` to name the field, then by `@ 200ms` to time out the
+/// acquisition and by `=> .field: Type` to project the guard. `name: Type = expr` is a lock
+/// implementing `LockSource`, which needs no recipe.
+struct Entry {
+    path: Path,
+    generics: Option<AngleBracketedGenericArguments>,
+    name: Ident,
+    source: Option<Source>,
+    lock: Option<Expr>,
+    key: Option<Expr>,
+    keys: Option<Expr>,
+    /// `name[..]`, the batch of all the indexes of the collection.
+    all: bool,
+    /// `name as alias`, the field of the lock.
+    alias: Option<Ident>,
+    timeout: Option<Timeout>,
+    projection: Option<Projection>,
+}
+
+impl Parse for Entry {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let path = parse_recipe_path(stream)?;
+        let name = match path.segments.last() {
+            Some(segment) => segment.value().ident.clone(),
+            None => return Err(stream.error("Expected the name of a lock.")),
+        };
+
+        // the generated code names its own items with a leading `__`.
+        if name.to_string().starts_with("__") {
+            return Err(Error::new(
+                name.span(),
+                "Lock names starting with `__` are reserved.",
+            ));
+        }
+
+        // the arguments follow the access in the arms of the recipe, `(ty read <User>)`.
+        let generics = if stream.peek(Token![::]) && stream.peek3(Token![<]) {
+            let mut generics: AngleBracketedGenericArguments = stream.parse()?;
+            generics.colon2_token = None;
+            Some(generics)
+        } else {
+            None
+        };
+
+        // a lock source is a single lock, neither borrowed by the recipe nor keyed.
+        let source = if stream.peek(Token![:]) && !stream.peek(Token![::]) {
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+
+        if let (Some(generics), Some(_)) = (&generics, &source) {
+            return Err(Error::new_spanned(
+                generics,
+                "A lock source has no recipe to give type arguments to.",
+            ));
+        }
+
+        let lock = if source.is_none() && stream.peek(token::Paren) {
+            let content;
+            parenthesized!(content in stream);
+            Some(content.parse()?)
+        } else {
+            None
+        };
+
+        let mut all = false;
+
+        let key = if source.is_none() && stream.peek(token::Bracket) {
+            let content;
+            bracketed!(content in stream);
+
+            let fork = content.fork();
+            all = fork.parse::<Token![..]>().is_ok() && fork.is_empty();
+
+            if all {
+                let _: Token![..] = content.parse()?;
+                None
+            } else {
+                Some(content.parse()?)
+            }
+        } else {
+            None
+        };
+
+        let alias = if stream.peek(Token![as]) {
+            let _: Token![as] = stream.parse()?;
+            let alias: Ident = stream.parse()?;
+
+            if alias.to_string().starts_with("__") {
+                return Err(Error::new(
+                    alias.span(),
+                    "Field names starting with `__` are reserved.",
+                ));
+            }
+
+            Some(alias)
+        } else {
+            None
+        };
+
+        // the indexes of the collection, from the lock instance or from the `len` arm of the
+        // recipe.
+        let keys = if all {
+            Some(match &lock {
+                Some(lock) => parse_quote! { 0..(#lock).len() },
+                None => parse_quote! { 0..#path!(len #generics) },
+            })
+        } else if source.is_none() && key.is_none() && stream.peek(Token![<-]) {
+            let _: Token![<-] = stream.parse()?;
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+
+        let timeout = if stream.peek(Token![@]) {
+            let span = stream.cursor().span();
+            let timeout = stream.parse()?;
+
+            // the keys of a lock are acquired in their own order, in a single step.
+            if key.is_some() || keys.is_some() {
+                return Err(Error::new(
+                    span,
+                    "Timeouts are not supported on keyed locks.",
+                ));
+            }
+
+            Some(timeout)
+        } else {
+            None
+        };
+
+        let projection = if stream.peek(Token![=>]) {
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            path,
+            generics,
+            name,
+            source,
+            lock,
+            key,
+            keys,
+            all,
+            alias,
+            timeout,
+            projection,
+        })
+    }
+}
+
+/// Parses the path of a recipe, stopping before the type arguments, which are given to its arms
+/// rather than to the macro.
+fn parse_recipe_path(stream: ParseStream) -> Result<Path> {
+    let mut path = Path {
+        leading_colon: stream.parse()?,
+        segments: Punctuated::new(),
+    };
+
+    loop {
+        path.segments
+            .push_value(stream.call(Ident::parse_any)?.into());
+
+        if !stream.peek(Token![::]) || stream.peek3(Token![<]) {
+            return Ok(path);
+        }
+
+        path.segments.push_punct(stream.parse()?);
+    }
+}
+
+impl Entry {
+    /// The canonical identity of the entry: the full path of its recipe, followed by its type
+    /// arguments, instance and keys. Two entries spelled differently but referring to the same
+    /// lock share the same identity.
+    fn id(&self) -> String {
+        let mut id = self.recipe();
+
+        if let Some(generics) = &self.generics {
+            id = format!("{}{}", id, canonical(generics.into_token_stream()));
+        }
+
+        if let Some(lock) = &self.lock {
+            id = format!("{}({})", id, canonical(lock.into_token_stream()));
+        }
+
+        if let Some(key) = &self.key {
+            id = format!("{}[{}]", id, canonical(key.into_token_stream()));
+        }
+
+        if let Some(keys) = &self.keys {
+            id = format!("{}<-{}", id, canonical(keys.into_token_stream()));
+        }
+
+        id
+    }
+
+    /// The full path of the recipe, `storage::accounts`, telling apart the recipes sharing the
+    /// last segment of their path.
+    fn recipe(&self) -> String {
+        canonical(self.path.clone().into_token_stream())
+    }
+}
+
+/// Normalizes the spelling of a token stream by removing whitespaces.
+fn canonical(tokens: TokenStream) -> String {
+    tokens
+        .to_string()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+impl Parse for Args {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let mut blocking = false;
+        let mut owned = false;
+        let mut optimistic = false;
+        let mut no_traits = false;
+        let mut hierarchy = false;
+        let mut transact = false;
+        let mut condvar = false;
+        let mut release = false;
+        let mut tuple = false;
+        let mut erased = false;
+        let mut infallible = None;
+        let mut builder = None;
+        let mut supervised = None;
+        let mut impls = Vec::<Path>::new();
+        // `self =>` takes the locks without an instance from the fields of `self`.
+        let mut receiver = None;
+        let mut tokens = TokenStream::new();
+
+        if stream.peek(Token![self]) && stream.peek2(Token![=>]) {
+            let token: Token![self] = stream.parse()?;
+            let _: Token![=>] = stream.parse()?;
+            tokens.extend(quote! { #token => });
+            receiver = Some(token);
+        }
+
+        loop {
+            if stream.peek(Token![impl]) {
+                let token: Token![impl] = stream.parse()?;
+                let path: Path = stream.parse()?;
+                let _: Token![;] = stream.parse()?;
+                let name = canonical(path.clone().into_token_stream());
+
+                if impls
+                    .iter()
+                    .any(|t| canonical(t.clone().into_token_stream()) == name)
+                {
+                    return Err(Error::new(
+                        token.span,
+                        format!("`impl {}` found more than once.", name),
+                    ));
+                }
+
+                tokens.extend(quote! { #token #path; });
+                impls.push(path);
+                continue;
+            }
+
+            if !stream.peek(Ident) || !stream.peek2(Token![;]) {
+                break;
+            }
+
+            let flag: Ident = stream.parse()?;
+            let _: Token![;] = stream.parse()?;
+
+            let old = match flag.to_string().as_str() {
+                "blocking" => std::mem::replace(&mut blocking, true),
+                "owned" => std::mem::replace(&mut owned, true),
+                "optimistic" => std::mem::replace(&mut optimistic, true),
+                "no_traits" => std::mem::replace(&mut no_traits, true),
+                "builder" => builder.replace(flag.span()).is_some(),
+                "hierarchy" => std::mem::replace(&mut hierarchy, true),
+                "transact" => std::mem::replace(&mut transact, true),
+                "condvar" => std::mem::replace(&mut condvar, true),
+                "release" => std::mem::replace(&mut release, true),
+                "tuple" => std::mem::replace(&mut tuple, true),
+                "erased" => std::mem::replace(&mut erased, true),
+                "infallible" => infallible.replace(flag.span()).is_some(),
+                "supervised" => supervised.replace(flag.span()).is_some(),
+                _ => return Err(Error::new(flag.span(), EXPECTED_FLAGS)),
+            };
+
+            if old {
+                return Err(Error::new(
+                    flag.span(),
+                    format!("`{}` found more than once.", flag),
+                ));
+            }
+
+            tokens.extend(quote! { #flag; });
+        }
+
+        if let Some(span) = supervised {
+            if !cfg!(feature = "supervisor") {
+                return Err(Error::new(
+                    span,
+                    "`supervised` races the acquisitions against the supervisors of the \
+                     `supervisor` feature.",
+                ));
+            }
+        }
+
+        // the builder resolves the chain again on each attempt.
+        if let Some(span) = builder {
+            if blocking || owned {
+                return Err(Error::new(
+                    span,
+                    "`builder` cannot be combined with `blocking` or `owned`.",
+                ));
+            }
+        }
+
+        let mut includes = Vec::new();
+        let mut reentrant = Vec::new();
+        let mut upgrade = Vec::new();
+        let mut escalate = Vec::new();
+        let mut drop_order = Vec::<Ident>::new();
+        let mut quorum = None::<(Ident, usize, Vec<String>, Timeout)>;
+        let mut write_preferring = false;
+        let mut poison = None;
+        let mut closure = None;
+        let mut cancel = None;
+        let mut assert = None;
+        let mut report_hold = None;
+        let mut adapter = None;
+        let mut thresholds = None;
+        let mut entries = Vec::<(Entry, Access)>::new();
+
+        while !stream.is_empty() {
+            if stream.peek(Token![|]) || stream.peek(Token![||]) || stream.peek(Token![move]) {
+                let span = stream.cursor().span();
+                let tokens_closure = parse_closure(stream)?;
+
+                if closure.is_some() {
+                    return Err(Error::new(span, "Closure found more than once."));
+                }
+
+                if blocking || builder.is_some() {
+                    return Err(Error::new(
+                        span,
+                        "`blocking` and `builder` cannot be combined with a closure.",
+                    ));
+                }
+
+                tokens.extend(quote! { #tokens_closure, });
+                closure = Some(tokens_closure);
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            let unsafety: Option<Token![unsafe]> = stream.parse()?;
+            let name: Ident = stream.parse()?;
+            let _: Token![:] = stream.parse()?;
+            let s = name.to_string();
+            let s = s.as_str();
+
+            if unsafety.is_some() && s != "reentrant" {
+                return Err(Error::new(name.span(), "Only `reentrant` is `unsafe`."));
+            }
+
+            if s == "policy" {
+                let policy: Ident = stream.parse()?;
+
+                if policy != "write_preferring" {
+                    return Err(Error::new(policy.span(), "Expected `write_preferring`."));
+                }
+
+                if std::mem::replace(&mut write_preferring, true) {
+                    return Err(Error::new(name.span(), "`policy` found more than once."));
+                }
+
+                tokens.extend(quote! { #name: #policy, });
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "poison" {
+                let policy: Poison = stream.parse()?;
+
+                if poison.replace(policy).is_some() {
+                    return Err(Error::new(name.span(), "`poison` found more than once."));
+                }
+
+                let policy = Ident::new(policy.name(), name.span());
+                tokens.extend(quote! { #name: #policy, });
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "cancel" {
+                if builder.is_some() {
+                    return Err(Error::new(
+                        name.span(),
+                        "`builder` cannot be combined with `cancel`, the builder resolves the \
+                         locks again on each attempt.",
+                    ));
+                }
+
+                let token: Expr = stream.parse()?;
+                tokens.extend(quote! { #name: #token, });
+
+                if cancel.replace(token).is_some() {
+                    return Err(Error::new(name.span(), "`cancel` found more than once."));
+                }
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "assert" {
+                let bounds =
+                    <Punctuated<TypeParamBound, Token![+]>>::parse_separated_nonempty(stream)?;
+                tokens.extend(quote! { #name: #bounds, });
+
+                if assert.replace(quote! { #bounds }).is_some() {
+                    return Err(Error::new(name.span(), "`assert` found more than once."));
+                }
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "report_hold" {
+                let label: LitStr = stream.parse()?;
+                tokens.extend(quote! { #name: #label, });
+
+                if report_hold.replace(label).is_some() {
+                    return Err(Error::new(
+                        name.span(),
+                        "`report_hold` found more than once.",
+                    ));
+                }
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "adapter" {
+                let ty: Type = stream.parse()?;
+                tokens.extend(quote! { #name: #ty, });
+
+                if adapter.replace(ty).is_some() {
+                    return Err(Error::new(name.span(), "`adapter` found more than once."));
+                }
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "diagnostics" {
+                let content;
+                bracketed!(content in stream);
+
+                let section: TokenStream = content.fork().parse()?;
+                tokens.extend(quote! { #name: [#section], });
+
+                if thresholds.replace(content.parse()?).is_some() {
+                    return Err(Error::new(
+                        name.span(),
+                        "`diagnostics` found more than once.",
+                    ));
+                }
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "reentrant" {
+                // the borrow of the outer guard is unchecked, the caller vouches for it.
+                if unsafety.is_none() {
+                    return Err(Error::new(
+                        name.span(),
+                        "`reentrant` borrows the guards of the outer invocation unchecked, write \
+                         `unsafe reentrant: [..]` once the outer struct outlives the inner one and \
+                         is not used meanwhile.",
+                    ));
+                }
+
+                let content;
+                bracketed!(content in stream);
+
+                let names = <Punctuated<Ident, Token![,]>>::parse_terminated(&content)?;
+                tokens.extend(quote! { unsafe #name: [#names], });
+                reentrant.extend(names);
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "upgrade" {
+                let content;
+                bracketed!(content in stream);
+
+                let names = <Punctuated<Ident, Token![,]>>::parse_terminated(&content)?;
+                tokens.extend(quote! { #name: [#names], });
+                upgrade.extend(names);
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "escalate" {
+                let content;
+                bracketed!(content in stream);
+
+                let names = <Punctuated<Ident, Token![,]>>::parse_terminated(&content)?;
+                tokens.extend(quote! { #name: [#names], });
+                escalate.extend(names);
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "drop_order" {
+                let content;
+                bracketed!(content in stream);
+
+                let fields = <Punctuated<Ident, Token![,]>>::parse_terminated(&content)?;
+                tokens.extend(quote! { #name: [#fields], });
+
+                for field in fields {
+                    if drop_order.contains(&field) {
+                        return Err(Error::new(
+                            field.span(),
+                            "Found multiple times in `drop_order`.",
+                        ));
+                    }
+
+                    drop_order.push(field);
+                }
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            if s == "include" {
+                let content;
+                bracketed!(content in stream);
+                includes.extend(<Punctuated<Ident, Token![,]>>::parse_terminated(&content)?);
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            // `group: billing`, the group of `lock_group!`, is `include: [billing]`.
+            if s == "group" {
+                includes.push(stream.parse()?);
+
+                if !stream.is_empty() {
+                    let _: Token![,] = stream.parse()?;
+                }
+
+                continue;
+            }
+
+            let (access, many) = match s {
+                "read" => (Access::Read, false),
+                "write" => (Access::Write, false),
+                "read_many" => (Access::Read, true),
+                "write_many" => (Access::Write, true),
+                "intent" => (Access::Intent, false),
+                "permits" => (Access::Permits, false),
+                "checkout" => (Access::Checkout, false),
+                "blocking_read" => (Access::BlockingRead, false),
+                "blocking_write" => (Access::BlockingWrite, false),
+                "freeze_bytes" => (Access::FreezeBytes, false),
+                "snapshot" => (Access::Snapshot, false),
+                "versioned" => (Access::Versioned, false),
+                "init" => (Access::Init, false),
+                "quorum" => (Access::Write, false),
+                _ => {
+                    return Err(Error::new(
+                        name.span(),
+                        "Expected `include`, `group`, `policy`, `poison`, `diagnostics`, \
+                         `cancel`, `reentrant`, `upgrade`, `escalate`, `drop_order`, `adapter`, \
+                         `assert`, `report_hold`, `quorum`, `read`, `write`, `read_many`, \
+                         `write_many`, `intent`, `permits`, `checkout`, `blocking_read`, \
+                         `blocking_write`, `freeze_bytes`, `snapshot`, `versioned` or `init`.",
+                    ))
+                }
+            };
+
+            // `quorum: 2 of [..]` writes the replicas, resolving once the threshold is met.
+            let threshold = if s == "quorum" {
+                let threshold: LitInt = stream.parse()?;
+                let of: Ident = stream.parse()?;
+
+                if of != "of" {
+                    return Err(Error::new(of.span(), "Expected `of`."));
+                }
+
+                if quorum.is_some() {
+                    return Err(Error::new(name.span(), "`quorum` found more than once."));
+                }
+
+                let deadline = Timeout {
+                    millis: 1000,
+                    text: "1s".to_string(),
+                };
+
+                quorum = Some((
+                    name.clone(),
+                    threshold.value() as usize,
+                    Vec::new(),
+                    deadline,
+                ));
+                Some(quote! { #threshold of })
+            } else {
+                None
+            };
+
+            let content;
+            bracketed!(content in stream);
+
+            let section: TokenStream = content.fork().parse()?;
+
+            // `quorum: 2 of [..] @ 200ms` gives each replica its deadline.
+            let deadline = match &mut quorum {
+                Some(quorum) if threshold.is_some() && stream.peek(Token![@]) => {
+                    let spelled = stream.fork();
+                    let _: Token![@] = spelled.parse()?;
+                    let lit: proc_macro2::Literal = spelled.parse()?;
+
+                    quorum.3 = stream.parse()?;
+                    quote! { @ #lit }
+                }
+                _ => quote! {},
+            };
+
+            tokens.extend(quote! { #name: #threshold [#section] #deadline, });
+
+            let punctuated = <Punctuated<Entry, Token![,]>>::parse_terminated(&content)?;
+
+            for entry in punctuated {
+                if let (Some(_), Access::Permits)
+                | (Some(_), Access::Checkout)
+                | (Some(_), Access::FreezeBytes)
+                | (Some(_), Access::Snapshot)
+                | (Some(_), Access::Versioned) = (&entry.projection, access)
+                {
+                    return Err(Error::new(
+                        entry.name.span(),
+                        format!("Projections are not supported in `{}`.", s),
+                    ));
+                }
+
+                if let (Some(_), Access::Permits)
+                | (Some(_), Access::Checkout)
+                | (Some(_), Access::FreezeBytes)
+                | (Some(_), Access::Snapshot)
+                | (Some(_), Access::Versioned) = (&entry.source, access)
+                {
+                    return Err(Error::new(
+                        entry.name.span(),
+                        format!("Lock sources are not supported in `{}`.", s),
+                    ));
+                }
+
+                // the fields of `self` are borrowed, or cloned when owned, in place of the lock.
+                let mut entry = entry;
+
+                if let (Some(receiver), None, None) = (&receiver, &entry.lock, &entry.source) {
+                    let name = &entry.name;
+
+                    entry.lock = match access {
+                        Access::Permits => None,
+                        _ if owned => Some(parse_quote! { #receiver.#name.clone() }),
+                        _ => Some(parse_quote! { &#receiver.#name }),
+                    };
+                }
+
+                // the intent, the initialization and the version are shared by lock identity,
+                // which borrowed and keyed locks lack.
+                if let Access::Intent | Access::Init | Access::Versioned = access {
+                    if entry.lock.is_some()
+                        || entry.key.is_some()
+                        || entry.keys.is_some()
+                        || entry.source.is_some()
+                        || entry.projection.is_some()
+                    {
+                        return Err(Error::new(
+                            entry.name.span(),
+                            format!(
+                                "`{}` only supports the locks of a recipe, without a lock, a key \
+                                 or a projection.",
+                                s
+                            ),
+                        ));
+                    }
+                }
+
+                // the blocking locks are acquired one at a time, outside of the keyed steps.
+                if let Access::BlockingRead | Access::BlockingWrite = access {
+                    if entry.key.is_some() || entry.keys.is_some() || entry.source.is_some() {
+                        return Err(Error::new(
+                            entry.name.span(),
+                            format!("Keys and lock sources are not supported in `{}`.", s),
+                        ));
+                    }
+                }
+
+                if many && entry.keys.is_none() {
+                    return Err(Error::new(entry.name.span(), "Expected `<- keys`."));
+                }
+
+                if !many && entry.keys.is_some() && !entry.all {
+                    return Err(Error::new(
+                        entry.name.span(),
+                        format!("Keys are only supported in `{}_many`.", s),
+                    ));
+                }
+
+                // a section may be repeated when merging groups, the same lock listed twice with
+                // the same access is acquired once.
+                let id = entry.id();
+                let listed = entries
+                    .iter()
+                    .any(|e| e.0.id() == id && e.1.name() == access.name());
+
+                // a replica is acquired by the quorum alone.
+                let replicas = quorum.as_mut().map(|t| &mut t.2);
+
+                if listed && (threshold.is_some() || replicas.iter().any(|t| t.contains(&id))) {
+                    return Err(Error::new(
+                        entry.name.span(),
+                        "The replicas of `quorum` cannot be listed in another section.",
+                    ));
+                }
+
+                if let (Some(replicas), Some(_)) = (replicas, &threshold) {
+                    replicas.push(id);
+                }
+
+                if !listed {
+                    entries.push((entry, access));
+                }
+            }
+
+            if !stream.is_empty() {
+                let _: Token![,] = stream.parse()?;
+            }
+        }
+
+        let mut counts = HashMap::new();
+
+        for (entry, _) in &entries {
+            let count = counts.entry(entry.recipe()).or_insert(0);
+            *count += 1;
+
+            // only keyed entries may share a lock, each one with its own key, borrowed entries,
+            // each one with its own instance, or instantiated entries, each one with its own
+            // type arguments. The recipes sharing the last segment of their path are distinct
+            // locks, their fields being renamed with `as`.
+            let mut same = entries.iter().filter(|e| e.0.recipe() == entry.recipe());
+            let keyed = same.clone().all(|e| e.0.key.is_some());
+            let instantiated = same.clone().all(|e| e.0.generics.is_some());
+            let addressed = same.all(|e| {
+                e.0.lock.is_some() && e.0.key.is_none() && !owned && !matches!(e.1, Access::Permits)
+            });
+
+            if *count > 1 && !keyed && !addressed && !instantiated {
+                return Err(Error::new(entry.name.span(), "Found multiple times."));
+            }
+        }
+
+        let mut set = HashMap::new();
+        let mut indexes = HashMap::new();
+        let mut fields = HashMap::new();
+
+        for (index, (entry, access)) in entries.into_iter().enumerate() {
+            let span = entry.name.span();
+            let name = entry.name.to_string();
+
+            let field = if let Some(alias) = &entry.alias {
+                alias.clone()
+            } else if counts[&entry.recipe()] > 1 {
+                let index = indexes.entry(name.clone()).or_insert(0);
+                *index += 1;
+                Ident::new(&format!("{}_{}", name, *index - 1), span)
+            } else {
+                entry.name.clone()
+            };
+
+            if let Some(old) = fields.insert(field.to_string(), entry.id()) {
+                return Err(Error::new(
+                    field.span(),
+                    format!("The field `{}` is already taken by `{}`.", field, old),
+                ));
+            }
+
+            let id = entry.id();
+            let replica = quorum.iter().any(|t| t.2.contains(&id));
+
+            // the parenthesis of a permit hold the number of permits, not a lock
+            let (lock, count) = match access {
+                Access::Permits => (None, Some(entry.lock.unwrap_or_else(|| parse_quote!(1)))),
+                _ => (entry.lock, None),
+            };
+
+            let item = Item {
+                id,
+                name: entry.name,
+                path: entry.path,
+                generics: entry.generics,
+                field,
+                access,
+                lock,
+                key: entry.key,
+                keys: entry.keys,
+                count,
+                owned,
+                source: entry.source,
+                timeout: entry.timeout,
+                projection: entry.projection,
+                write_preferring,
+                poison,
+                infallible: infallible.is_some(),
+                adapter: adapter.clone(),
+                index,
+                reentrant: false,
+                upgradable: false,
+                escalated: false,
+                upgraded: false,
+                quorum: replica,
+                supervised: supervised.is_some(),
+            };
+
+            if let Some(old) = set.insert(item.id.clone(), item) {
+                return Err(Error::new(
+                    span,
+                    format!("Found multiple times, same lock as `{}`.", old.field),
+                ));
+            }
+        }
+
+        let mut items = set.into_iter().map(|t| t.1).collect::<Vec<_>>();
+        items.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+
+        // the guard held is found by the identity of the lock, in the registry of the holder.
+        for name in reentrant {
+            if !cfg!(feature = "reentrancy") {
+                return Err(Error::new(
+                    name.span(),
+                    "`reentrant` finds the locks held in the registry of the `reentrancy` feature.",
+                ));
+            }
+
+            if optimistic || transact || release {
+                return Err(Error::new(
+                    name.span(),
+                    "`reentrant` cannot be combined with `optimistic`, `transact` or `release`, \
+                     which need the guards of their recipe.",
+                ));
+            }
+
+            let item = items
+                .iter_mut()
+                .find(|t| t.name == name)
+                .ok_or_else(|| Error::new(name.span(), "Expected a lock of the invocation."))?;
+
+            if !item.is_lendable() {
+                return Err(Error::new(
+                    name.span(),
+                    "`reentrant` only supports the `read` and `write` locks of a recipe, without \
+                     a lock, a key or a projection.",
+                ));
+            }
+
+            item.reentrant = true;
+        }
+
+        for name in upgrade {
+            if tuple || closure.is_some() {
+                return Err(Error::new(
+                    name.span(),
+                    "`upgrade` generates a method of the struct, it cannot be combined with \
+                     `tuple` or a closure.",
+                ));
+            }
+
+            let item = items
+                .iter_mut()
+                .find(|t| t.name == name)
+                .ok_or_else(|| Error::new(name.span(), "Expected a lock of the invocation."))?;
+
+            if !matches!(item.access, Access::Read) || !item.is_lendable() || item.reentrant {
+                return Err(Error::new(
+                    name.span(),
+                    "`upgrade` only supports the `read` locks of a recipe, without a lock, a key, \
+                     a projection or `reentrant`.",
+                ));
+            }
+
+            item.upgradable = true;
+        }
+
+        for name in escalate {
+            if tuple || closure.is_some() {
+                return Err(Error::new(
+                    name.span(),
+                    "`escalate` generates a method of the struct, it cannot be combined with \
+                     `tuple` or a closure.",
+                ));
+            }
+
+            let item = items
+                .iter_mut()
+                .find(|t| t.name == name)
+                .ok_or_else(|| Error::new(name.span(), "Expected a lock of the invocation."))?;
+
+            if !matches!(item.access, Access::Read) || !item.is_lendable() || item.reentrant {
+                return Err(Error::new(
+                    name.span(),
+                    "`escalate` only supports the `read` locks of a recipe, without a lock, a \
+                     key, a projection or `reentrant`.",
+                ));
+            }
+
+            item.escalated = true;
+        }
+
+        // the tuple has no struct to order, its guards are dropped in the order of the sections.
+        for field in &drop_order {
+            if tuple {
+                return Err(Error::new(
+                    field.span(),
+                    "`drop_order` orders the fields of the struct, which `tuple` does not have.",
+                ));
+            }
+
+            if !items.iter().any(|t| t.field == *field) {
+                return Err(Error::new(field.span(), "Expected a field of the struct."));
+            }
+        }
+
+        if let Some((name, threshold, replicas, _)) = &quorum {
+            if *threshold == 0 || *threshold > replicas.len() {
+                return Err(Error::new(
+                    name.span(),
+                    format!(
+                        "Expected a threshold between 1 and the {} replicas of `quorum`.",
+                        replicas.len()
+                    ),
+                ));
+            }
+
+            if optimistic || transact || release || erased {
+                return Err(Error::new(
+                    name.span(),
+                    "`quorum` cannot be combined with `optimistic`, `transact`, `release` or \
+                     `erased`, which need all the guards.",
+                ));
+            }
+
+            if let Some(item) = items.iter().find(|t| {
+                t.quorum
+                    && (t.lock.is_some()
+                        || t.key.is_some()
+                        || t.keys.is_some()
+                        || t.source.is_some()
+                        || t.projection.is_some()
+                        || t.timeout.is_some())
+            }) {
+                return Err(Error::new(
+                    item.name.span(),
+                    "`quorum` only supports the locks of a recipe, without a lock, a key, a \
+                     projection or a timeout.",
+                ));
+            }
+
+            // the replicas are acquired as a single step of the chain.
+            let first = items.iter().position(|t| t.quorum).unwrap_or_default();
+
+            if let Some(item) = items
+                .iter()
+                .skip(first)
+                .take(replicas.len())
+                .find(|t| !t.quorum)
+            {
+                return Err(Error::new(
+                    item.name.span(),
+                    format!(
+                        "The lock `{}` is ordered between the replicas of `quorum`, acquire it in \
+                         another invocation.",
+                        item.id
+                    ),
+                ));
+            }
+        }
+
+        // nothing is left to fail once the recipes cannot, but the features waiting on a clock,
+        // a token or a caller.
+        if let Some(span) = infallible {
+            if blocking
+                || builder.is_some()
+                || closure.is_some()
+                || cancel.is_some()
+                || items.iter().any(|t| t.timeout.is_some())
+            {
+                return Err(Error::new(
+                    span,
+                    "`infallible` cannot be combined with `blocking`, `builder`, a closure, \
+                     `cancel` or a timeout, which can all fail.",
+                ));
+            }
+        }
+
+        Ok(Self {
+            blocking,
+            owned,
+            optimistic,
+            builder: builder.is_some(),
+            hierarchy,
+            transact,
+            condvar,
+            release,
+            tuple,
+            erased,
+            infallible: infallible.is_some(),
+            no_traits,
+            supervised: supervised.is_some(),
+            impls,
+            write_preferring,
+            poison,
+            includes,
+            tokens,
+            closure,
+            cancel,
+            assert,
+            report_hold,
+            drop_order,
+            adapter,
+            ident: None,
+            upgraded: None,
+            quorum: quorum.map(|t| (t.1, t.3)),
+            thresholds: thresholds.unwrap_or_default(),
+            items,
+        })
+    }
+}
+
+/// Takes the tokens of the closure, up to the next comma outside of a group.
+pub fn parse_closure(stream: ParseStream) -> Result<TokenStream> {
+    stream.step(|cursor| {
+        let mut rest = *cursor;
+        let mut tokens = TokenStream::new();
+
+        while let Some((tt, next)) = rest.token_tree() {
+            match &tt {
+                TokenTree::Punct(punct) if punct.as_char() == ',' => break,
+                _ => tokens.extend(Some(tt)),
+            }
+
+            rest = next;
+        }
+
+        Ok((tokens, rest))
+    })
+}
+
+#[derive(Clone, Copy)]
+pub enum Access {
+    Read,
+    Write,
+    Permits,
+    /// A connection checked out of an async pool, such as the pools of `bb8` or `deadpool`.
+    Checkout,
+    /// A read guard of a blocking lock, such as `parking_lot::RwLock`, acquired off the
+    /// executor.
+    BlockingRead,
+    /// A write guard of a blocking lock, acquired off the executor.
+    BlockingWrite,
+    /// A read guard holding the intent of the lock, the only one allowed to upgrade to write.
+    Intent,
+    /// Freezes the content of a `BytesMut` under a short write lock.
+    FreezeBytes,
+    /// Clones the `Arc<T>` behind a read guard, released as soon as it is cloned.
+    Snapshot,
+    /// Clones the value behind a read guard along with the version of the lock, the guard being
+    /// released as soon as it is cloned.
+    Versioned,
+    /// A lazily initialized resource, such as a `OnceCell`, initialized by a single task.
+    Init,
+}
+
+impl Access {
+    pub fn ident(self) -> Ident {
+        Ident::new(self.name(), Span::call_site())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Permits => "permits",
+            Access::Checkout => "checkout",
+            Access::BlockingRead => "blocking_read",
+            Access::BlockingWrite => "blocking_write",
+            Access::Intent => "intent",
+            Access::FreezeBytes => "freeze_bytes",
+            Access::Snapshot => "snapshot",
+            Access::Versioned => "versioned",
+            Access::Init => "init",
+        }
+    }
+
+    /// The access given to the `resolve` arm of the recipe.
+    pub fn resolve_ident(self) -> Ident {
+        match self {
+            Access::FreezeBytes | Access::BlockingWrite => Access::Write.ident(),
+            Access::Intent | Access::BlockingRead | Access::Snapshot | Access::Versioned => {
+                Access::Read.ident()
+            }
+            _ => self.ident(),
+        }
+    }
+
+    /// The access given to the `ty` and `traits` arms of the recipe. The blocking locks share
+    /// the arms of `locks_sync!`.
+    pub fn guard_ident(self) -> Ident {
+        match self {
+            Access::BlockingRead | Access::BlockingWrite => self.resolve_ident(),
+            _ => self.ident(),
+        }
+    }
+
+    /// Whether the guard is acquired by blocking the thread, for `blocking_read` and
+    /// `blocking_write`.
+    pub fn is_blocking(self) -> bool {
+        matches!(self, Access::BlockingRead | Access::BlockingWrite)
+    }
+
+    /// Whether the guard is released as soon as the value is taken from it, for `freeze_bytes`,
+    /// `snapshot` and `versioned`.
+    #[cfg_attr(not(feature = "reentrancy"), allow(dead_code))]
+    pub fn is_released(self) -> bool {
+        matches!(
+            self,
+            Access::FreezeBytes | Access::Snapshot | Access::Versioned
+        )
+    }
+
+    /// Whether the guard is the only one of its lock, for `write` and `blocking_write`.
+    #[cfg_attr(not(feature = "reentrancy"), allow(dead_code))]
+    pub fn is_exclusive(self) -> bool {
+        matches!(self, Access::Write | Access::BlockingWrite)
+    }
+}
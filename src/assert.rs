@@ -0,0 +1,42 @@
+use crate::args::{Args, Item};
+use crate::write_item_ty;
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::Ident;
+
+/// Checks the bounds of `assert: Send + 'static` at compile time, on the guard of each lock,
+/// through a function named after it so the error names the offending lock, then on the value
+/// of the invocation, the future or the struct resolved on the spot. The guards borrowing their
+/// lock only name their type with the lifetime of the struct, the value of the invocation
+/// covers them.
+pub fn write_assert(args: &Args, all: TokenStream) -> TokenStream {
+    let bounds = match &args.assert {
+        Some(bounds) => bounds,
+        None => return all,
+    };
+
+    let guards = args.items.iter().filter(|t| !t.is_borrowed()).map(|item| {
+        // the projection is a field of the struct, the guard is what the recipe resolves to.
+        let ty = write_item_ty(&Item {
+            projection: None,
+            ..item.clone()
+        });
+
+        // the error names the function, so the function names the lock.
+        let check = Ident::new(&format!("__assert_{}", item.field), item.name.span());
+
+        quote_spanned! { item.name.span() =>
+            fn #check<T: #bounds>() {}
+            #check::<#ty>();
+        }
+    });
+
+    quote! {{
+        fn __assert<T: #bounds>(value: T) -> T {
+            value
+        }
+
+        #(#guards)*
+        __assert(#all)
+    }}
+}
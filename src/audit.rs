@@ -0,0 +1,29 @@
+use crate::args::Args;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The marker logging the acquisition of the locks, and their release when the struct is
+/// dropped, with the call site, the locks and their access, and the task or the thread holding
+/// them. `sync` tells whether the struct is built by `locks_sync!`, outside of a task.
+pub fn write_audit(args: &Args, sync: bool) -> TokenStream {
+    let locks = args
+        .items
+        .iter()
+        .map(|t| format!("{}:{}", t.id, t.access_name()))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let owner = if sync {
+        quote! { lock_derive_runtime::audit::thread() }
+    } else {
+        quote! { lock_derive_runtime::audit::task() }
+    };
+
+    quote! {
+        __audit: lock_derive_runtime::audit::acquired(
+            concat!(file!(), ":", line!()),
+            #locks,
+            #owner,
+        ),
+    }
+}
@@ -0,0 +1,19 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Acquires a blocking lock for `blocking_read` and `blocking_write`, where the `resolve` arm of
+/// the recipe returns a `Result` as with `locks_sync!`. On a `tokio` threadpool, the lock is
+/// acquired in `tokio_threadpool::blocking`, which hands the other tasks of the worker to another
+/// thread while it waits. Elsewhere, such as in `current_thread`, it is acquired inline, blocking
+/// the executor.
+pub fn write_blocking(resolve: TokenStream) -> TokenStream {
+    quote! {
+        futures::future::poll_fn(move || -> futures::Poll<_, failure::Error> {
+            match tokio_threadpool::blocking(|| #resolve) {
+                Ok(futures::Async::Ready(r)) => r.map(futures::Async::Ready),
+                Ok(futures::Async::NotReady) => Ok(futures::Async::NotReady),
+                Err(_) => (#resolve).map(futures::Async::Ready),
+            }
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Bridges the future of a `resolve` arm to the futures 0.1 chain. A futures 0.1 future is taken
+/// as is, a `std::future::Future` such as an `async` block or an `async fn` call is polled from
+/// the task polling the chain.
+pub fn write_bridged(resolve: TokenStream) -> TokenStream {
+    // the task of a `std::future::Future` is woken through `std`.
+    if cfg!(not(feature = "std")) {
+        return resolve;
+    }
+
+    quote! { (&mut __Resolve(Some(#resolve))).__bridge() }
+}
+
+/// Bridges the future of a `resolve` arm under `infallible;`, a `std::future::Future` resolving
+/// to the guard itself, such as `tokio::sync::RwLock::write()`, rather than to a `Result`.
+pub fn write_infallible(resolve: TokenStream) -> TokenStream {
+    if cfg!(not(feature = "std")) {
+        return resolve;
+    }
+
+    quote! { (&mut __Resolve(Some(#resolve))).__bridge_infallible() }
+}
+
+/// Brings `__Resolve` of the runtime crate into scope, telling the futures of the recipes apart
+/// by autoref specialization: the `__Legacy` impl is found first for the futures 0.1, `__Async`
+/// otherwise.
+pub fn write_bridge() -> TokenStream {
+    if cfg!(not(feature = "std")) {
+        return quote! {};
+    }
+
+    quote! {
+        #[allow(unused_imports)]
+        use lock_derive_runtime::{
+            Async as __Async, AsyncInfallible as __AsyncInfallible, Legacy as __Legacy,
+            LegacyInfallible as __LegacyInfallible, Resolve as __Resolve,
+        };
+    }
+}
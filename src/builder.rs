@@ -0,0 +1,10 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Brings `LocksBuilder` of the runtime crate into scope, resolving the chain with the timeout,
+/// the retries and the observers set at runtime.
+pub fn write_builder() -> TokenStream {
+    quote! {
+        use lock_derive_runtime::LocksBuilder;
+    }
+}
@@ -0,0 +1,33 @@
+use crate::bridge::write_bridged;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Expr;
+
+/// Races the chain against the `cancelled()` future of the token, for `cancel: token`. Once the
+/// token is cancelled, the chain is dropped along with the guards already acquired and the
+/// future fails with `Cancelled`.
+pub fn write_cancel(token: &Expr, resolve: TokenStream) -> TokenStream {
+    let cancelled = write_bridged(quote! {
+        async move {
+            __token.cancelled().await;
+            Ok::<(), failure::Error>(())
+        }
+    });
+
+    quote! {{
+        use futures::Future;
+
+        let __token = #token;
+        let __cancelled = #cancelled;
+
+        (#resolve).select2(__cancelled).then(|r| match r {
+            Ok(futures::future::Either::A((locks, _))) => Ok(locks),
+            Ok(futures::future::Either::B(_)) => Err(failure::Error::from(
+                lock_derive_runtime::Cancelled,
+            )),
+            Err(futures::future::Either::A((e, _))) | Err(futures::future::Either::B((e, _))) => {
+                Err(e)
+            }
+        })
+    }}
+}
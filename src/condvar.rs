@@ -0,0 +1,54 @@
+use crate::args::Args;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `wait_until()` and `notify()` for `condvar;`. The waiter registers on the locks of the
+/// struct while holding them, so a notification given under any of its write locks cannot be
+/// missed, then releases all the guards and resolves the chain again once notified.
+pub fn write_condvar(args: &Args) -> TokenStream {
+    let ids = args.items.iter().map(|t| &t.id).collect::<Vec<_>>();
+    let ids = &ids;
+    let ident = crate::struct_ident(args);
+    let vis = crate::struct_vis(args);
+
+    quote! {
+        #[allow(dead_code)]
+        impl #ident {
+            /// Releases the locks until `notify()` is called on any of them, then acquires
+            /// them again in the same order, until `pred` holds.
+            #vis fn wait_until<P>(
+                self,
+                pred: P,
+            ) -> impl futures::Future<Item = Self, Error = failure::Error>
+            where
+                P: FnMut(&Self) -> bool,
+            {
+                use futures::Future;
+
+                futures::future::loop_fn((self, pred), |(locks, mut pred)| {
+                    if pred(&locks) {
+                        return futures::future::Either::A(futures::future::ok(
+                            futures::future::Loop::Break(locks),
+                        ));
+                    }
+
+                    let waiters = vec![
+                        #(lock_derive_runtime::waiters::wait(module_path!(), #ids),)*
+                    ];
+                    drop(locks);
+
+                    futures::future::Either::B(
+                        futures::future::select_all(waiters)
+                            .then(|_| Self::resolve())
+                            .map(move |locks| futures::future::Loop::Continue((locks, pred))),
+                    )
+                })
+            }
+
+            /// Wakes the tasks waiting on any of the locks of the struct.
+            #vis fn notify(&self) {
+                #(lock_derive_runtime::waiters::notify(module_path!(), #ids);)*
+            }
+        }
+    }
+}
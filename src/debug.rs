@@ -0,0 +1,82 @@
+use crate::args::{Access, Args};
+use crate::struct_generics;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes the `Debug` impl of the struct, listing each lock with its access and its value when
+/// the value is `Debug`, `_` otherwise.
+pub fn write_debug(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+
+    let fields = args.items.iter().map(|t| {
+        let field = &t.field;
+        let name = field.to_string();
+        let access = t.access_name();
+
+        let value = match (t.access, &t.keys) {
+            (Access::Permits, _) | (Access::Checkout, _) => quote! { &format_args!(#access) },
+            _ if t.quorum => quote! {
+                &format_args!(
+                    "{} {:?}",
+                    #access,
+                    self.#field.as_ref().map(|g| __Value((&__Show(&**g)).show()))
+                )
+            },
+            (_, Some(_)) => quote! {
+                &format_args!(
+                    "{} {:?}",
+                    #access,
+                    self.#field.iter().map(|g| __Value((&__Show(&**g)).show())).collect::<Vec<_>>()
+                )
+            },
+            (_, None) => quote! {
+                &format_args!("{} {:?}", #access, __Value((&__Show(&*self.#field)).show()))
+            },
+        };
+
+        quote! { .field(#name, #value) }
+    });
+
+    quote! {
+        impl #impl_generics core::fmt::Debug for #ty {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                use core::fmt::Debug;
+
+                // picks the `Debug` impl of the value when there is one, by autoref.
+                struct __Show<'x, T: ?Sized>(&'x T);
+
+                trait __ShowDebug {
+                    fn show(&self) -> Option<String>;
+                }
+
+                impl<T: Debug + ?Sized> __ShowDebug for __Show<'_, T> {
+                    fn show(&self) -> Option<String> {
+                        Some(format!("{:?}", self.0))
+                    }
+                }
+
+                trait __ShowOpaque {
+                    fn show(&self) -> Option<String>;
+                }
+
+                impl<T: ?Sized> __ShowOpaque for &__Show<'_, T> {
+                    fn show(&self) -> Option<String> {
+                        None
+                    }
+                }
+
+                struct __Value(Option<String>);
+
+                impl Debug for __Value {
+                    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                        f.write_str(self.0.as_ref().map_or("_", String::as_str))
+                    }
+                }
+
+                f.debug_struct("Locks")
+                    #(#fields)*
+                    .finish()
+            }
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use proc_macro2::{Punct, Spacing, Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Parser, Result};
+use syn::{
+    parse_quote, Error, Expr, GenericArgument, Ident, Item, ItemMacro, LitInt, PathArguments,
+    Token, Type, Visibility,
+};
+
+/// `name: RwLock<T> = expr`, a lock known to the recipe by its expression, or `name: RwLock<T>`
+/// for a lock always given at the call site, optionally followed by `, level = 1` for
+/// `hierarchy;`.
+pub struct Definition {
+    name: Ident,
+    value: Type,
+    lock: Option<Expr>,
+    level: Option<LitInt>,
+}
+
+impl Parse for Definition {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let name = stream.parse()?;
+        let _: Token![:] = stream.parse()?;
+        let ty: Type = stream.parse()?;
+        let value = type_argument(&ty, "RwLock")
+            .ok_or_else(|| Error::new_spanned(&ty, "Expected `RwLock<T>`."))?;
+
+        let lock = if stream.peek(Token![=]) {
+            let _: Token![=] = stream.parse()?;
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+
+        let level = if stream.is_empty() {
+            None
+        } else {
+            let _: Token![,] = stream.parse()?;
+            let key: Ident = stream.parse()?;
+
+            if key != "level" {
+                return Err(Error::new(key.span(), "Expected `level`."));
+            }
+
+            let _: Token![=] = stream.parse()?;
+            Some(stream.parse()?)
+        };
+
+        Ok(Self {
+            name,
+            value,
+            lock,
+            level,
+        })
+    }
+}
+
+/// The arguments of `#[lock_recipe]`, the name of the recipe when it is not the name of the
+/// static in lowercase, optionally followed by `level = 1` for `hierarchy;`.
+pub struct Attribute {
+    name: Option<Ident>,
+    level: Option<LitInt>,
+}
+
+impl Parse for Attribute {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let mut name = None;
+        let mut level = None;
+
+        while !stream.is_empty() {
+            let ident: Ident = stream.parse()?;
+
+            if stream.peek(Token![=]) {
+                if ident != "level" {
+                    return Err(Error::new(ident.span(), "Expected `level`."));
+                }
+
+                let _: Token![=] = stream.parse()?;
+                level = Some(stream.parse()?);
+            } else if name.replace(ident.clone()).is_some() {
+                return Err(Error::new(
+                    ident.span(),
+                    "The name of the recipe is already given.",
+                ));
+            }
+
+            if !stream.is_empty() {
+                let _: Token![,] = stream.parse()?;
+            }
+        }
+
+        Ok(Self { name, level })
+    }
+}
+
+/// A static of a `lazy_static!` invocation, `static ref NAME: Type = expr;`.
+struct LazyStatic {
+    ident: Ident,
+    ty: Type,
+}
+
+impl Parse for LazyStatic {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let _ = stream.call(syn::Attribute::parse_outer)?;
+        let _: Visibility = stream.parse()?;
+        let _: Token![static] = stream.parse()?;
+        let _: Token![ref] = stream.parse()?;
+        let ident = stream.parse()?;
+        let _: Token![:] = stream.parse()?;
+        let ty = stream.parse()?;
+        let _: Token![=] = stream.parse()?;
+        let _: Expr = stream.parse()?;
+        let _: Token![;] = stream.parse()?;
+
+        Ok(Self { ident, ty })
+    }
+}
+
+/// Writes the recipes of the statics of the item given to `#[lock_recipe]`, a `static` or a
+/// `lazy_static!` invocation, the item being written back as is. A static holds a `RwLock<T>`,
+/// possibly behind a `Lazy<..>` dereferencing to it.
+pub fn write_lock_recipe(attr: &Attribute, item: TokenStream) -> Result<TokenStream> {
+    let statics = match syn::parse2(item.clone())? {
+        Item::Static(item) => vec![(item.ident, *item.ty)],
+        Item::Macro(ItemMacro { mac, .. })
+            if mac
+                .path
+                .segments
+                .last()
+                .map(|t| t.value().ident == "lazy_static")
+                == Some(true) =>
+        {
+            let parse = |stream: ParseStream| {
+                let mut statics = Vec::new();
+
+                while !stream.is_empty() {
+                    let t: LazyStatic = stream.parse()?;
+                    statics.push((t.ident, t.ty));
+                }
+
+                Ok(statics)
+            };
+
+            parse.parse2(mac.tts)?
+        }
+        _ => {
+            return Err(Error::new_spanned(
+                item,
+                "`#[lock_recipe]` expects a `static` or a `lazy_static!` invocation.",
+            ))
+        }
+    };
+
+    if attr.name.is_some() && statics.len() > 1 {
+        return Err(Error::new(
+            Span::call_site(),
+            "`#[lock_recipe]` names the recipe of a single static.",
+        ));
+    }
+
+    let recipes = statics
+        .into_iter()
+        .map(|(ident, ty)| {
+            let lock = type_argument(&ty, "Lazy").unwrap_or_else(|| ty.clone());
+            let value = type_argument(&lock, "RwLock").ok_or_else(|| {
+                Error::new_spanned(&ty, "Expected `RwLock<T>` or `Lazy<RwLock<T>>`.")
+            })?;
+
+            let name = attr
+                .name
+                .clone()
+                .unwrap_or_else(|| Ident::new(&ident.to_string().to_lowercase(), ident.span()));
+
+            Ok(write_definition(&Definition {
+                name,
+                value,
+                lock: Some(parse_quote!(#ident)),
+                level: attr.level.clone(),
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #item
+        #(#recipes)*
+    })
+}
+
+/// The type argument of `Name<T>`, whatever the path leading to `Name`.
+fn type_argument(ty: &Type, name: &str) -> Option<Type> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().and_then(|segment| {
+            let segment = segment.value();
+
+            match &segment.arguments {
+                PathArguments::AngleBracketed(args) if segment.ident == name => {
+                    match args.args.iter().collect::<Vec<_>>().as_slice() {
+                        [GenericArgument::Type(value)] => Some(value.clone()),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// Writes the recipe macro of a `futures_locks::RwLock`. Its guard cannot be taken from a holder
+/// wedging the lock, so the recipe has neither `supervisor` nor `force_resolve` arm and
+/// `supervised;` fails to compile on it.
+pub fn write_definition(def: &Definition) -> TokenStream {
+    let name = &def.name;
+    let value = &def.value;
+    let d = Punct::new('$', Spacing::Alone);
+    let err = quote! { |_| failure::format_err!(concat!("Lock `", stringify!(#name), "` error")) };
+
+    // the acquisitions given up by a timeout or a cancellation are kept alive, a waiter of a
+    // `futures_locks::RwLock` cannot drop out of its queue.
+    let detach = quote! { lock_derive_runtime::detach };
+
+    let level = match &def.level {
+        Some(level) => quote! { (level) => { #level }; },
+        None => quote! {
+            (level) => {
+                compile_error!(concat!(
+                    "The lock `",
+                    stringify!(#name),
+                    "` has no level, add `, level = N` to its `define_lock!`.",
+                ))
+            };
+        },
+    };
+
+    let unforced = quote! {
+        compile_error!(concat!(
+            "The lock `",
+            stringify!(#name),
+            "` of `define_lock!` cannot be forced, `supervised;` needs recipes with `supervisor` ",
+            "and `force_resolve` arms.",
+        ))
+    };
+
+    let statics = def.lock.as_ref().map(|lock| {
+        quote! {
+            (resolve read) => { futures::Future::map_err(#detach(#lock.read()), #err) };
+            (resolve write) => { futures::Future::map_err(#detach(#lock.write()), #err) };
+            (try_resolve read) => { #lock.try_read().ok() };
+            (try_resolve write) => { #lock.try_write().ok() };
+        }
+    });
+
+    quote! {
+        macro_rules! #name {
+            (ty read) => { futures_locks::RwLockReadGuard<#value> };
+            (ty write) => { futures_locks::RwLockWriteGuard<#value> };
+            (ty read #d a:lifetime) => { futures_locks::RwLockReadGuard<#value> };
+            (ty write #d a:lifetime) => { futures_locks::RwLockWriteGuard<#value> };
+            (ty owned read) => { futures_locks::RwLockReadGuard<#value> };
+            (ty owned write) => { futures_locks::RwLockWriteGuard<#value> };
+            #statics
+            #level
+            (resolve read #d lock:expr) => {
+                futures::Future::map_err(#detach(#d lock.read()), #err)
+            };
+            (resolve write #d lock:expr) => {
+                futures::Future::map_err(#detach(#d lock.write()), #err)
+            };
+            (resolve owned read #d lock:expr) => {
+                futures::Future::map_err(#detach(#d lock.read()), #err)
+            };
+            (resolve owned write #d lock:expr) => {
+                futures::Future::map_err(#detach(#d lock.write()), #err)
+            };
+            (try_resolve read #d lock:expr) => { #d lock.try_read().ok() };
+            (try_resolve write #d lock:expr) => { #d lock.try_write().ok() };
+            (try_resolve owned read #d lock:expr) => { #d lock.try_read().ok() };
+            (try_resolve owned write #d lock:expr) => { #d lock.try_write().ok() };
+            (supervisor) => { #unforced };
+            (force_resolve #d (#d t:tt)*) => { #unforced };
+            (traits read #d struct:ty) => {
+                impl AsRef<#value> for #d struct {
+                    fn as_ref(&self) -> &#value {
+                        &self.#name
+                    }
+                }
+            };
+            (traits write #d struct:ty) => {
+                impl AsRef<#value> for #d struct {
+                    fn as_ref(&self) -> &#value {
+                        &self.#name
+                    }
+                }
+
+                impl AsMut<#value> for #d struct {
+                    fn as_mut(&mut self) -> &mut #value {
+                        &mut self.#name
+                    }
+                }
+            };
+            (traits #d access:ident #d struct:ty) => {};
+            (#d (#d t:tt)*) => {
+                compile_error!(concat!(
+                    "The lock `",
+                    stringify!(#name),
+                    "` has no `",
+                    stringify!(#d (#d t)*),
+                    "` arm, only `read` and `write` locks are defined.",
+                ))
+            };
+        }
+    }
+}
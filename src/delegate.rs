@@ -0,0 +1,39 @@
+use crate::args::Args;
+use crate::{respan, struct_generics};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Implements the traits of `impl Trait;` on the struct. The `impl` arm of each recipe gives the
+/// methods it maps to its guard, reached through the field given along with the access, so a
+/// trait is implemented by the locks of the invocation together. A lock source has no recipe,
+/// and a projection, a batch of keys or a replica of a quorum does not hold the guard of the
+/// recipe.
+pub fn write_delegates(args: &Args) -> TokenStream {
+    let (generics, ty) = struct_generics(args);
+
+    let items = args
+        .items
+        .iter()
+        .filter(|t| t.projection.is_none() && t.source.is_none() && t.keys.is_none() && !t.quorum);
+    let items = items.collect::<Vec<_>>();
+
+    let impls = args.impls.iter().map(|path| {
+        let methods = items.iter().map(|t| {
+            let recipe = &t.path;
+            let generics = &t.generics;
+            let access = t.access.guard_ident();
+            let field = &t.field;
+            let arm = respan(quote! { impl #path #access #field }, t.name.span());
+
+            quote! { #recipe!{ #arm #generics } }
+        });
+
+        quote! {
+            impl #generics #path for #ty {
+                #(#methods)*
+            }
+        }
+    });
+
+    quote! { #(#impls)* }
+}
@@ -0,0 +1,72 @@
+use crate::args::Args;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The locks of the invocation as written in the warnings, `accounts:read,users:write`.
+fn locks(args: &Args) -> String {
+    args.items
+        .iter()
+        .map(|t| format!("{}:{}", t.field, t.access.ident()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Wraps the resolve future to log a warning when acquiring all the locks is slow. The clock
+/// starts when the chain is first polled.
+pub fn write_slow(args: &Args, resolve: TokenStream) -> TokenStream {
+    let locks = locks(args);
+    let millis = args.thresholds.acquire.millis;
+    let capture = if args.owned {
+        quote! { move }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        futures::future::lazy(#capture || {
+            let __start = std::time::Instant::now();
+
+            (#resolve).map(move |__locks| {
+                lock_derive_runtime::diagnostics::check(
+                    "acquiring",
+                    #locks,
+                    __start,
+                    std::time::Duration::from_millis(#millis),
+                );
+                __locks
+            })
+        })
+    }
+}
+
+/// Logs a warning when acquiring all the locks is slow, for `locks_sync!`.
+pub fn write_slow_sync(args: &Args, resolve: TokenStream) -> TokenStream {
+    let locks = locks(args);
+    let millis = args.thresholds.acquire.millis;
+
+    quote! {{
+        let __start = std::time::Instant::now();
+        let __locks = #resolve;
+
+        lock_derive_runtime::diagnostics::check(
+            "acquiring",
+            #locks,
+            __start,
+            std::time::Duration::from_millis(#millis),
+        );
+        __locks
+    }}
+}
+
+/// The hidden field of the struct logging a warning when the struct is held for too long.
+pub fn write_hold(args: &Args) -> TokenStream {
+    let locks = locks(args);
+    let millis = args.thresholds.hold.millis;
+
+    quote! {
+        __hold: lock_derive_runtime::diagnostics::Hold::start(
+            #locks,
+            std::time::Duration::from_millis(#millis),
+        ),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::args::{Access, Args};
+use crate::{struct_generics, struct_vis};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `get_any()` and `get::<T>()` for `erased;`, giving the value of a guard by the name of
+/// its field, and implements `ErasedLocks` so the struct is reachable as a `&dyn ErasedLocks`.
+/// Permits and batches of keys have no single value and are left out.
+pub fn write_erased(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+
+    let items = args
+        .items
+        .iter()
+        .filter(|t| t.keys.is_none() && !matches!(t.access, Access::Permits))
+        .collect::<Vec<_>>();
+
+    let names = items
+        .iter()
+        .map(|t| t.field.to_string())
+        .collect::<Vec<_>>();
+    let names = &names;
+
+    let values = items.iter().map(|t| {
+        let field = &t.field;
+
+        match t.access {
+            // the bytes are unsized once dereferenced.
+            Access::FreezeBytes => quote! { &self.#field },
+            _ => quote! { &*self.#field },
+        }
+    });
+
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            /// The value of the guard in the field named `name`, if any.
+            #vis fn get_any(&self, name: &str) -> Option<&dyn core::any::Any> {
+                match name {
+                    #(#names => Some(#values),)*
+                    _ => None,
+                }
+            }
+
+            /// The value of the guard in the field named `name`, if any and of type `T`.
+            #vis fn get<T: core::any::Any>(&self, name: &str) -> Option<&T> {
+                self.get_any(name).and_then(|v| v.downcast_ref())
+            }
+        }
+
+        impl #impl_generics lock_derive_runtime::ErasedLocks for #ty {
+            fn names(&self) -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn get_any(&self, name: &str) -> Option<&dyn core::any::Any> {
+                Self::get_any(self, name)
+            }
+        }
+    }
+}
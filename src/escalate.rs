@@ -0,0 +1,110 @@
+use crate::args::{Access, Args};
+use crate::{error_ty, struct_ident, struct_vis, write_chain, write_field_ty, write_struct_items};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::Ident;
+
+/// The struct holding the locks once escalated, the same locks with those of `escalate: [..]`
+/// written.
+pub fn escalated_args(args: &Args) -> Args {
+    let mut escalated = args.clone();
+
+    for t in &mut escalated.items {
+        t.upgradable = false;
+
+        if std::mem::replace(&mut t.escalated, false) {
+            t.access = Access::Write;
+        }
+    }
+
+    // a struct of its own, named after the struct escalated as the structs of the upgrades.
+    escalated.ident = args
+        .ident
+        .as_ref()
+        .map(|ident| Ident::new(&format!("{}EscalateWrite", ident), ident.span()));
+
+    let tokens = &args.tokens;
+    escalated.tokens = quote! { #tokens escalate };
+    escalated.upgraded = Some(Ident::new("escalate", Span::call_site()));
+    escalated
+}
+
+/// The struct of the values the `check` of `escalate()` is given, the same for the locks read
+/// and written.
+pub fn view_ident(args: &Args) -> Ident {
+    let ident = struct_ident(args);
+    Ident::new(&format!("{}View", ident), ident.span())
+}
+
+/// Writes `escalate(check, mutate)` for the read locks of `escalate: [..]`, with the struct it
+/// resolves to. `check` tells from the values read whether a write is needed; only then are the
+/// locks released and acquired again in order, those of `escalate: [..]` being written. Since
+/// another task may write in between, `check` runs again on the values written before `mutate`.
+pub fn write_escalate(args: &Args) -> TokenStream {
+    if !args.items.iter().any(|t| t.escalated) {
+        return quote! {};
+    }
+
+    let ident = struct_ident(args);
+    let vis = struct_vis(args);
+    let error = error_ty();
+    let escalated = escalated_args(args);
+    let escalated_ident = struct_ident(&escalated);
+    let items = write_struct_items(&escalated, write_chain(&escalated));
+    let view = view_ident(args);
+
+    let fields = args.items.iter().map(|t| &t.field).collect::<Vec<_>>();
+    let fields = &fields;
+    let values = fields;
+    let view_fields = args.items.iter().map(|t| {
+        let field = &t.field;
+        let ty = write_field_ty(t);
+
+        quote! { #vis #field: &'v <#ty as core::ops::Deref>::Target }
+    });
+
+    quote! {
+        #items
+
+        /// The values of the locks, given to the `check` of `escalate()`.
+        #vis struct #view<'v> {
+            #(#view_fields,)*
+        }
+
+        #[allow(dead_code)]
+        impl #ident {
+            /// Runs `check` on the values read and, only when it tells that a write is needed,
+            /// releases the locks and acquires them again in order, those of `escalate: [..]`
+            /// being written, then runs `check` again and `mutate` if a write is still needed.
+            /// Resolves to the output of `mutate`, if it ran, once the locks are released.
+            #vis fn escalate<C, M, R>(
+                self,
+                check: C,
+                mutate: M,
+            ) -> impl futures::Future<Item = Option<R>, Error = #error>
+            where
+                C: Fn(&#view) -> bool,
+                M: FnOnce(&mut #escalated_ident) -> R,
+            {
+                use futures::Future;
+
+                if !check(&#view { #(#fields: &*self.#values,)* }) {
+                    return futures::future::Either::A(futures::future::ok(None));
+                }
+
+                futures::future::Either::B(
+                    self.unlock_all()
+                        .and_then(|_| #escalated_ident::resolve())
+                        .map(move |mut locks| {
+                            // another task may have written in between.
+                            if check(&#view { #(#fields: &*locks.#values,)* }) {
+                                Some(mutate(&mut locks))
+                            } else {
+                                None
+                            }
+                        }),
+                )
+            }
+        }
+    }
+}
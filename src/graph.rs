@@ -0,0 +1,28 @@
+use crate::args::Args;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Registers the call site and its locks, in acquisition order, with the `inventory` of the runtime
+/// crate, so the lock graph of the whole program can be rendered at runtime.
+pub fn write_site(args: &Args) -> TokenStream {
+    // the futures chain acquires the last item first
+    let locks = args.items.iter().rev().map(|t| {
+        let path = &t.path;
+        let generics = &t.generics;
+        let name = quote!(#path #generics).to_string().replace(' ', "");
+        let access = t.access_name();
+
+        quote! { lock_derive_runtime::LockUse { name: #name, access: #access } }
+    });
+
+    quote! {
+        lock_derive_runtime::submit_site! {
+            lock_derive_runtime::LockSite {
+                file: file!(),
+                line: line!(),
+                column: column!(),
+                locks: &[#(#locks,)*],
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::args::Args;
+use proc_macro2::{Punct, Spacing, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{Error, Ident, Token};
+
+/// The arguments of `lock_group!`, the name of the group followed by its sections,
+/// `billing = read: [rates], write: [accounts, ledger]`.
+pub struct Group {
+    name: Ident,
+    sections: TokenStream,
+}
+
+impl Parse for Group {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let name: Ident = stream.parse()?;
+        let _: Token![=] = stream.parse()?;
+
+        // the sections are appended after those of the invocation, where no flag can follow.
+        if stream.peek(Ident) && stream.peek2(Token![;]) {
+            return Err(stream.error("A group only holds sections, its flags would come last."));
+        }
+
+        let sections: TokenStream = stream.parse()?;
+        let args: Args = syn::parse2(sections.clone())?;
+
+        if args.closure.is_some() || args.cancel.is_some() || args.quorum.is_some() {
+            return Err(Error::new(
+                name.span(),
+                "A group only holds the sections of its locks, without a closure, `cancel` or \
+                 `quorum`.",
+            ));
+        }
+
+        Ok(Self { name, sections })
+    }
+}
+
+/// Writes the group macro, whose `include` arm appends the sections of the group to the
+/// invocation, the same macro as one written by hand for `include: [..]`.
+pub fn write_group(group: &Group) -> TokenStream {
+    let Group { name, sections } = group;
+    let d = Punct::new('$', Spacing::Alone);
+
+    quote! {
+        macro_rules! #name {
+            (include #d(#d mac:ident)::+ { #d(#d args:tt)* }) => {
+                #d(#d mac)::+!(#d(#d args)* #sections)
+            };
+        }
+    }
+}
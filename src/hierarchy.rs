@@ -0,0 +1,40 @@
+use crate::args::Args;
+use crate::respan;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Checks at compile time that the locks are acquired by increasing `(level)` of their recipe,
+/// for `hierarchy;`. Every invocation acquires its locks in the same order, so checking the
+/// locks acquired one after the other is enough to keep the hierarchy between invocations.
+pub fn write_hierarchy(args: &Args) -> TokenStream {
+    // the futures chain acquires the last item first, the keys of a lock share its level.
+    let mut items = args
+        .items
+        .iter()
+        .rev()
+        .filter(|t| t.source.is_none())
+        .collect::<Vec<_>>();
+
+    items.dedup_by(|a, b| a.name == b.name);
+
+    let checks = items.windows(2).map(|w| {
+        let (a, b) = (w[0], w[1]);
+        let (path_a, path_b) = (&a.path, &b.path);
+        let (generics_a, generics_b) = (&a.generics, &b.generics);
+        let level_a = respan(quote! { level }, a.name.span());
+        let level_b = respan(quote! { level }, b.name.span());
+        let message = format!(
+            "lock `{}` is acquired after lock `{}` but has a lower level",
+            b.name, a.name
+        );
+
+        quote! {
+            const _: () = assert!(
+                #path_a!(#level_a #generics_a) <= #path_b!(#level_b #generics_b),
+                #message
+            );
+        }
+    });
+
+    quote! { #(#checks)* }
+}
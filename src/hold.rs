@@ -0,0 +1,16 @@
+use crate::args::Args;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The hidden field of the struct for `report_hold: "label"`, reporting how long the locks
+/// were held, from their acquisition to the drop of the struct, under the label of the call
+/// site. The report goes to the observer of `lock_derive_runtime`, or to `log` when there is
+/// none.
+pub fn write_hold_timer(args: &Args) -> TokenStream {
+    match &args.report_hold {
+        Some(label) => quote! {
+            __hold_timer: lock_derive_runtime::hold::Timer::start(#label),
+        },
+        None => quote! {},
+    }
+}
@@ -0,0 +1,21 @@
+use crate::args::Args;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Hands the invocation to the `include` arm of the first group, which appends its own sections
+/// and invokes `mac` again, until all the groups are expanded and the locks can be sorted
+/// globally.
+pub fn write_include(args: &Args, mac: TokenStream) -> Option<TokenStream> {
+    let (group, rest) = args.includes.split_first()?;
+    let tokens = &args.tokens;
+
+    let rest = if rest.is_empty() {
+        quote! {}
+    } else {
+        quote! { include: [#(#rest),*], }
+    };
+
+    Some(quote! {
+        #group!(include #mac { #tokens #rest })
+    })
+}
@@ -0,0 +1,27 @@
+use crate::args::Item;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Resolves the value of `init: [..]` under the initialization token of the lock until a value
+/// is first resolved, so a single task runs the initialization of the recipe, in its turn in
+/// the order of the chain, while the others wait on the token. Once initialized, the recipe is
+/// resolved without the token.
+pub fn write_init(item: &Item, resolve: TokenStream) -> TokenStream {
+    let id = &item.id;
+
+    quote! {
+        match lock_derive_runtime::init::init(module_path!(), #id) {
+            None => futures::future::Either::A(#resolve),
+            Some(__init) => futures::future::Either::B(
+                __init
+                    .lock()
+                    .map_err(|_| failure::format_err!("initialization of lock `{}` error", #id))
+                    .and_then(|__token| #resolve.map(move |__value| {
+                        lock_derive_runtime::init::initialized(module_path!(), #id);
+                        drop(__token);
+                        __value
+                    })),
+            ),
+        }
+    }
+}
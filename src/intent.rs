@@ -0,0 +1,37 @@
+use crate::args::Item;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Takes the intent token of the lock before its read guard, for `intent: [..]`. The upgrade is
+/// left to `upgrade_<field>()` on the struct, which resolves the locks again in order.
+pub fn write_intent(item: &Item, resolve: TokenStream) -> TokenStream {
+    let id = &item.id;
+
+    quote! {
+        lock_derive_runtime::intent::intent(module_path!(), #id)
+            .lock()
+            .map_err(|_| failure::format_err!("intent of lock `{}` error", #id))
+            .and_then(|__token| #resolve.map(move |__guard| {
+                lock_derive_runtime::Intent::new(__guard, __token)
+            }))
+    }
+}
+
+/// Tries to take the intent token and the read guard of the lock without waiting, for
+/// `optimistic;`.
+pub fn write_try_intent(item: &Item, try_resolve: TokenStream) -> TokenStream {
+    let id = &item.id;
+
+    quote! {{
+        let __token = lock_derive_runtime::intent::intent(module_path!(), #id)
+            .try_lock()
+            .ok()?;
+        #try_resolve.map(move |__guard| lock_derive_runtime::Intent::new(__guard, __token))
+    }}
+}
+
+/// The write guard of the lock of `intent: [..]` resolved by the struct upgraded, given the
+/// intent by `upgrade_<field>()` once resolved.
+pub fn write_upgraded(resolve: TokenStream) -> TokenStream {
+    quote! { #resolve.map(lock_derive_runtime::Upgraded::new) }
+}
@@ -2,11 +2,11 @@
 A derive proc macro allowing to locks simultaneously severals locks (based on futures) and prevents
 deadlocks by always sorting the locks in the same order.
 
+The macros expand to an expression and build on stable Rust.
+
 # Example
 
 ```
-#![feature(proc_macro_hygiene)]
-
 use failure::format_err;
 use futures_locks::{RwLock, RwLockReadGuard};
 use tokio::executor::current_thread::block_on_all;
@@ -37,172 +37,4205 @@ fn main() {
     assert_eq!(10, *locks.as_ref());
 }
 ```
-!*/
 
-extern crate proc_macro;
-extern crate proc_macro2;
+The code generated by the macros calls the machinery shared by all the invocations, such as the
+bridge of the `async` recipes, the builder or the lock sets, from the `lock_derive_runtime` crate
+rather than writing it out in each expansion. A proc macro crate cannot re-export it, so the
+calling crate depends on `lock_derive_runtime` along with `lock_derive`.
 
-use proc_macro2::{Span, TokenStream};
-use quote::quote;
-use std::collections::HashMap;
-use syn::parse::{Parse, ParseStream, Result};
-use syn::punctuated::Punctuated;
-use syn::{bracketed, parse_macro_input, Error, Ident, Token};
+The invocation defines the struct of the guards, named `Locks` in this documentation, under a name
+unique to the invocation, so several invocations may live in the same scope and the type never
+leaks to the call site. The struct is only reached through the value it resolves to.
 
-#[proc_macro]
-pub fn locks(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let args = parse_macro_input!(item as Args);
-    write_all(&args).into()
+The locks are acquired one after the other, by a chain of `and_then`. From 5 locks, the chain is a
+flat state machine instead, with a state per lock, so the type of the future and the code polling
+it do not grow with each lock; only the locks with a timeout keep the chain.
+
+The locks are sorted by the full path of their recipe, so all the invocations naming the recipes
+by the same paths acquire the locks in the same order. A recipe is named by the same path
+throughout the crate: reached through an import under another path, such as
+`use accounts as ledger;`, it would be sorted by each spelling in turn.
+
+# Defining recipes
+
+For a `futures_locks::RwLock`, `define_lock!(name: RwLock<T> = expr)` writes the recipe: the
+`read` and `write` arms of the static, borrowed and owned locks, the `try_resolve` arms, and a
+`traits` arm implementing `AsRef<T>` for the read locks and `AsRef<T>` and `AsMut<T>` for the
+write locks, since the recipe knows the target type. The `= expr` part may be left out when the
+lock is always given at the call site. The calling crate must depend on `failure`, `futures` and
+`futures_locks`.
+
+Two locks of the same type would implement the same traits on the struct. Starting the
+invocation with `no_traits;` skips the `traits` arm of all the recipes; the guards remain
+reachable through the fields.
+
+```
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<i32> = RwLock::new(1);
 }
 
-struct Args {
-    items: Vec<(Ident, ReadWrite)>,
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<i32> = USERS);
+
+fn main() {
+    let mut locks = block_on_all(lock_derive::locks!(write: [accounts])).unwrap();
+    *locks.as_mut() += 5;
+    assert_eq!(15, *locks.accounts);
+    drop(locks);
+
+    // both locks guard an `i32`, their `AsRef<i32>` impls would conflict.
+    let locks = block_on_all(lock_derive::locks!(no_traits; read: [accounts, users])).unwrap();
+    assert_eq!(16, *locks.accounts + *locks.users);
 }
+```
 
-impl Parse for Args {
-    fn parse(stream: ParseStream) -> Result<Self> {
-        let mut read = None;
-        let mut write = None;
+A recipe defined in another module is named by its path, `storage::accounts`, once exported
+from that module with `pub(crate) use accounts;`. The last segment of the path names the field,
+while the locks are sorted on the full path. Two recipes sharing the last segment of their path
+are distinct locks, the field of one of them being renamed with `as`.
 
-        while !stream.is_empty() {
-            let name: Ident = stream.parse()?;
-            let _: Token![:] = stream.parse()?;
-            let s = name.to_string();
-            let s = s.as_str();
+```
+use futures_locks::RwLock;
+use tokio::executor::current_thread::block_on_all;
 
-            let content;
-            bracketed!(content in stream);
+mod storage {
+    use futures_locks::RwLock;
 
-            let punctuated = <Punctuated<Ident, Token![,]>>::parse_terminated(&content)?;
-            let vec = punctuated.into_iter().collect::<Vec<_>>();
+    lazy_static::lazy_static! {
+        pub static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    }
 
-            let old = match s {
-                "read" => read.replace(vec),
-                "write" => write.replace(vec),
-                _ => return Err(Error::new(name.span(), "Expected `read` or `write`.")),
-            };
+    lock_derive::define_lock!(accounts: RwLock<i32> = crate::storage::ACCOUNTS);
+    pub(crate) use accounts;
+}
 
-            if old.is_some() {
-                return Err(Error::new(
-                    name.span(),
-                    format!("`{}` found more than once.", s),
-                ));
-            }
-        }
+mod archive {
+    use futures_locks::RwLock;
 
-        let mut set = HashMap::new();
-        let read = read
-            .unwrap_or_else(Vec::new)
-            .into_iter()
-            .map(|r| (r, ReadWrite::Read));
+    lazy_static::lazy_static! {
+        pub static ref ACCOUNTS: RwLock<i32> = RwLock::new(1);
+    }
 
-        let write = write
-            .unwrap_or_else(Vec::new)
-            .into_iter()
-            .map(|w| (w, ReadWrite::Write));
+    lock_derive::define_lock!(accounts: RwLock<i32> = crate::archive::ACCOUNTS);
+    pub(crate) use accounts;
+}
 
-        let items = read.chain(write);
+fn main() {
+    let future = lock_derive::locks!(
+        no_traits;
+        read: [storage::accounts, archive::accounts as archived],
+    );
+    let locks = block_on_all(future).unwrap();
 
-        for (ident, read_write) in items {
-            let span = ident.span();
+    assert_eq!(11, *locks.accounts + *locks.archived);
+    assert_eq!(
+        [("storage::accounts", "read"), ("archive::accounts", "read")],
+        locks.order()
+    );
+}
+```
 
-            if set.insert(ident, read_write).is_some() {
-                return Err(Error::new(span, "Found multiple times."));
-            }
-        }
+# Recipes of statics
 
-        let mut items = set.into_iter().collect::<Vec<_>>();
-        items.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+`#[lock_recipe]` writes the recipe of the static it is placed on, as `define_lock!` would,
+named after the static in lowercase. Placed on a `lazy_static!` invocation, it writes the recipe
+of each of its statics. The type of a static is a `RwLock<T>`, or a `Lazy<RwLock<T>>` such as the
+one of `once_cell`. The name of the recipe may be given, `#[lock_recipe(accounts)]`, for a single
+static, followed by `level = 1` for [`hierarchy;`](#lock-hierarchy). The recipe refers to the
+static by its name, which must be in scope where the recipe is used.
 
-        Ok(Self { items })
-    }
+```
+use futures_locks::RwLock;
+use lock_derive::lock_recipe;
+use tokio::executor::current_thread::block_on_all;
+
+#[lock_recipe]
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<i32> = RwLock::new(1);
 }
 
-#[derive(Clone, Copy)]
-enum ReadWrite {
-    Read,
-    Write,
+fn main() {
+    let locks = block_on_all(lock_derive::locks!(no_traits; read: [accounts, users])).unwrap();
+    assert_eq!(11, *locks.accounts + *locks.users);
 }
+```
 
-impl ReadWrite {
-    fn ident(self) -> Ident {
-        Ident::new(
-            match self {
-                ReadWrite::Read => "read",
-                ReadWrite::Write => "write",
-            },
-            Span::call_site(),
-        )
-    }
+# Renaming fields
+
+`name as alias` names the field of the lock `alias`, when the name of the lock is long or taken
+by a local variable, or to tell apart the instances of the same lock. The locks are still
+sorted and resolved by their name. The `traits` arm of a renamed lock is not expanded, since it
+refers to the field by the name of the lock.
+
+```
+# use futures_locks::RwLock;
+# use lock_derive::define_lock;
+# use tokio::executor::current_thread::block_on_all;
+# lazy_static::lazy_static! {
+#     static ref CONFIGURATION: RwLock<i32> = RwLock::new(10);
+# }
+define_lock!(service_configuration: RwLock<i32> = CONFIGURATION);
+define_lock!(account: RwLock<i32>);
+
+# fn main() {
+let future = lock_derive::locks!(read: [service_configuration as cfg]);
+assert_eq!(10, *block_on_all(future).unwrap().cfg);
+
+let (a, b) = (RwLock::new(1), RwLock::new(2));
+let future = lock_derive::locks!(write: [account(&a) as from, account(&b) as to]);
+let mut locks = block_on_all(future).unwrap();
+*locks.from -= 1;
+*locks.to += 1;
+# }
+```
+
+# Async recipes
+
+The `resolve` arm of a recipe may also be an `async` block or an `async fn` call, the output
+being a `Result` of the guard. It is polled from the task polling the chain, in the same order
+as the other locks. The `try_resolve` arm and the arms of `locks_sync!` are not futures.
+
+```
+use failure::format_err;
+use futures_locks::{RwLock, RwLockReadGuard};
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
 }
 
-fn write_resolve(args: &Args) -> TokenStream {
-    let fields = args.items.iter().enumerate().map(|(i, t)| {
-        let name = &t.0;
-        let v = Ident::new(&format!("__v{}", i), Span::call_site());
-        quote! { #name: #v }
-    });
+async fn read_accounts() -> Result<RwLockReadGuard<i32>, failure::Error> {
+    ACCOUNTS.try_read().map_err(|_| format_err!("Lock error"))
+}
+
+macro_rules! accounts {
+    (ty read) => { RwLockReadGuard<i32> };
+    (resolve read) => { read_accounts() };
+    (traits $access:ident $struct:ty) => {};
+}
 
-    let mut inner_code = Some(quote! { Ok(Locks { #(#fields,)* }) });
+fn main() {
+    let locks = block_on_all(lock_derive::locks!(read: [accounts])).unwrap();
+    assert_eq!(10, *locks.accounts);
+}
+```
 
-    for (i, t) in args.items.iter().enumerate() {
-        let name = &t.0;
-        let t = t.1.ident();
-        let v = Ident::new(&format!("__v{}", i), Span::call_site());
-        let code = inner_code.take().expect("inner_code");
+# Infallible recipes
+
+The futures of some locks cannot fail, such as `tokio::sync::RwLock::write()`, whose output is
+the guard itself. `infallible;` declares the recipes of the invocation as such: an `async`
+`resolve` arm resolves to the guard rather than to a `Result`, and the invocation evaluates to a
+`std::future::Future` of the struct, without an error to handle. The recipes returning a futures
+0.1 future are taken as is.
+
+Nothing but the recipes must be able to fail, so `infallible;` cannot be combined with
+`blocking`, `builder`, a closure, `cancel` or a timeout. It is not supported by `locks_struct!`,
+`locks_sync!`, `acquire_more!`, `merge_locks!` nor by the arms of `locks_for!`. Under
+`#[with_locks(infallible, ..)]`, the locks are awaited without `?`.
+
+```
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use lock_derive_runtime::block_on;
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# async fn write_accounts() -> RwLockWriteGuard<i32> {
+#     ACCOUNTS.try_write().ok().expect("uncontended")
+# }
+macro_rules! accounts {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => { write_accounts() };
+    (traits $access:ident $struct:ty) => {};
+}
+
+# fn main() {
+let mut locks = block_on(lock_derive::locks!(infallible; write: [accounts]));
+*locks.accounts += 1;
+assert_eq!(11, *locks.accounts);
+# }
+```
+
+# Lock sources
+
+A lock implementing the `LockSource` trait of the [runtime](#runtime) needs no recipe: it is
+written `name: Type = expr`, where `expr` is the lock, of type `Type`. The guards are the
+`ReadGuard` and `WriteGuard` of the trait, and its `try_read` and `try_write` methods are used by
+`optimistic;`. The trait is implemented for `futures_locks::RwLock`. A lock source is always
+resolved at the call site; it cannot be keyed nor used for permits, by `locks_sync!` or with a
+supervisor, and has no `traits` arm.
+
+```
+use futures_locks::RwLock;
+use tokio::executor::current_thread::block_on_all;
+
+lock_derive::runtime!();
+
+struct Config(std::sync::Arc<String>);
+
+impl LockSource for Config {
+    type ReadGuard = std::sync::Arc<String>;
+    type WriteGuard = std::sync::Arc<String>;
 
-        inner_code = Some(quote! { #name!(resolve #t).and_then(|#v| #code) });
+    fn read(&self) -> LockFuture<Self::ReadGuard> {
+        Box::new(futures::future::ok(self.0.clone()))
     }
 
-    let code = inner_code.expect("inner_code");
+    fn write(&self) -> LockFuture<Self::WriteGuard> {
+        Box::new(futures::future::ok(self.0.clone()))
+    }
+}
 
-    quote! {
-        impl Locks {
-            fn resolve() -> impl futures::Future<Item = Self, Error = failure::Error> {
-                use futures::Future;
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+}
 
-                #code
-            }
-        }
+fn main() {
+    let config = Config(std::sync::Arc::new("billing".to_string()));
+    let future = lock_derive::locks!(
+        read: [config: Config = config],
+        write: [accounts: RwLock<i32> = ACCOUNTS],
+    );
+
+    let mut locks = block_on_all(future).unwrap();
+    *locks.accounts += 1;
+    assert_eq!("billing", locks.config.as_str());
+}
+```
+
+# Borrowed locks
+
+A lock does not have to live in a static. Writing `name(expr)` passes the lock instance to the
+recipe, which receives it as an extra argument of the `resolve` arm. The struct then becomes
+`Locks<'a>` and the `ty` arm receives the lifetime so guards borrowing from the lock can be
+expressed.
+
+```
+use failure::format_err;
+use futures_locks::{RwLock, RwLockReadGuard};
+use tokio::executor::current_thread::block_on_all;
+
+macro_rules! accounts {
+    (ty read $a:lifetime) => { RwLockReadGuard<i32> };
+    (resolve read $lock:expr) => { $lock.read().map_err(|_| format_err!("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+struct Bank {
+    accounts: RwLock<i32>,
+}
+
+fn main() {
+    let bank = Bank { accounts: RwLock::new(10) };
+    let future = lock_derive::locks!(read: [accounts(&bank.accounts)]);
+    let locks = block_on_all(future).unwrap();
+    assert_eq!(10, *locks.accounts);
+}
+```
+
+The same lock may be borrowed from several instances chosen at runtime, such as the two
+accounts of a transfer, where ordering by name is not enough. The instances are then ordered by
+address before being acquired, so two transfers between the same accounts in opposite
+directions cannot deadlock. They must be given as references, the fields are numbered in the
+order of the invocation, `accounts_0`, `accounts_1`, and the same instance given twice fails the
+acquisition. Owned instances have no stable address and cannot be repeated.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! account {
+#     (ty write $a:lifetime) => { RwLockWriteGuard<i32> };
+#     (resolve write $lock:expr) => { $lock.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+fn transfer(from: &RwLock<i32>, to: &RwLock<i32>, amount: i32) -> Result<(), failure::Error> {
+    let mut locks = block_on_all(lock_derive::locks!(write: [account(from), account(to)]))?;
+    *locks.account_0 -= amount;
+    *locks.account_1 += amount;
+    Ok(())
+}
+
+# fn main() {
+let (a, b) = (RwLock::new(10), RwLock::new(5));
+transfer(&a, &b, 3).unwrap();
+transfer(&b, &a, 1).unwrap();
+
+assert_eq!(8, *a.try_read().unwrap());
+assert!(transfer(&a, &a, 1).is_err());
+# }
+```
+
+# Owned locks
+
+Borrowed guards tie the struct to the lifetime of the locks. Starting the invocation with
+`owned;` takes the lock instances by value instead, typically a clone of an `Arc` backed lock,
+and moves them along the acquisition. The recipe is then expanded with the `(ty owned read)` and
+`(resolve owned read $lock:expr)` arms, the struct has no lifetime and the future is `'static`,
+so it can be handed to another task.
+
+```
+use failure::format_err;
+use futures::Future;
+use futures_locks::{RwLock, RwLockReadGuard};
+
+macro_rules! accounts {
+    (ty owned read) => { RwLockReadGuard<i32> };
+    (resolve owned read $lock:expr) => { $lock.read().map_err(|_| format_err!("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+fn main() {
+    let lock = RwLock::new(10);
+    let future = lock_derive::locks!(owned; read: [accounts(lock.clone())]);
+
+    tokio::run(
+        future
+            .map(|locks| assert_eq!(10, *locks.accounts))
+            .map_err(|e| panic!("{}", e)),
+    );
+}
+```
+
+# Asserting the bounds
+
+A guard that is not `Send` is otherwise found when the task holding the future fails to spawn,
+far from the invocation. `assert: Send + 'static` checks the bounds at compile time, on the guard
+of each lock, then on the value of the invocation: the future, the struct with `blocking;` or the
+`Result` of `locks_sync!`. The check of a guard is a function named after its field, so a guard
+missing a bound fails with ``required by a bound in `__assert_accounts` ``. Borrowed guards are
+only checked through the value of the invocation.
+
+```
+# use failure::format_err;
+# use futures::Future;
+# use futures_locks::{RwLock, RwLockReadGuard};
+# macro_rules! accounts {
+#     (ty owned read) => { RwLockReadGuard<i32> };
+#     (resolve owned read $lock:expr) => { $lock.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# fn main() {
+let lock = RwLock::new(10);
+let future = lock_derive::locks!(owned; assert: Send + 'static, read: [accounts(lock.clone())]);
+# tokio::run(
+#     future
+#         .map(|locks| assert_eq!(10, *locks.accounts))
+#         .map_err(|e| panic!("{}", e)),
+# );
+# }
+```
+
+# Fields of self
+
+Inside a method, `self =>` at the start of the invocation takes the locks from the fields of
+`self`: a lock written without an instance is borrowed from the field of the same name, as if
+written `name(&self.name)`, or cloned with `owned;`, as if written `name(self.name.clone())`. The
+locks are sorted by name as usual, so the methods of a struct lock its fields in the same order.
+
+```
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+use tokio::executor::current_thread::block_on_all;
+
+define_lock!(cache: RwLock<i32>);
+define_lock!(sessions: RwLock<Vec<i32>>);
+
+struct Server {
+    cache: RwLock<i32>,
+    sessions: RwLock<Vec<i32>>,
+}
+
+impl Server {
+    fn open_session(&self) -> usize {
+        let future = lock_derive::locks!(self => read: [cache], write: [sessions]);
+        let mut locks = block_on_all(future).unwrap();
+        locks.sessions.push(*locks.cache);
+        locks.sessions.len()
     }
 }
 
-fn write_struct(args: &Args) -> TokenStream {
-    let fields = args.items.iter().map(|t| {
-        let n = &t.0;
-        let ident = &t.1.ident();
+fn main() {
+    let server = Server { cache: RwLock::new(10), sessions: RwLock::new(Vec::new()) };
+    assert_eq!(1, server.open_session());
+    assert_eq!(2, server.open_session());
+}
+```
 
-        quote! { #n: #n!(ty #ident) }
-    });
+# Generic recipes
 
-    quote! {
-        struct Locks {
-            #(#fields,)*
-        }
+A recipe may serve several types, `cache::<User>`: the type arguments are given to each arm of
+the recipe after its access, or after its name for the arms without access, such as
+`(ty read <User>)` or `(level <User>)`. Each instantiation is a lock of its own, ordered by the
+name of the recipe then by its arguments, and the fields of the same recipe are numbered as for
+[keyed locks](#keyed-locks) unless renamed with `as`. The struct being an item, the arguments
+cannot be the type parameters of the enclosing function.
+
+```
+use failure::format_err;
+use futures_locks::{RwLock, RwLockWriteGuard};
+use tokio::executor::current_thread::block_on_all;
+
+struct Cache<T>(Vec<T>);
+
+trait Cached: Sized + 'static {
+    fn cache() -> &'static RwLock<Cache<Self>>;
+}
+
+macro_rules! cache {
+    (ty write <$t:ty>) => { RwLockWriteGuard<Cache<$t>> };
+    (resolve write <$t:ty>) => {
+        <$t as Cached>::cache().write().map_err(|_| format_err!("Lock error"))
+    };
+    (traits $access:ident <$t:ty> $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref NAMES: RwLock<Cache<String>> = RwLock::new(Cache(Vec::new()));
+    static ref SCORES: RwLock<Cache<u32>> = RwLock::new(Cache(Vec::new()));
+}
+
+impl Cached for String {
+    fn cache() -> &'static RwLock<Cache<Self>> {
+        &NAMES
     }
 }
 
-fn write_traits(args: &Args) -> TokenStream {
-    let fields = args.items.iter().map(|t| {
-        let n = &t.0;
-        let ident = &t.1.ident();
+impl Cached for u32 {
+    fn cache() -> &'static RwLock<Cache<Self>> {
+        &SCORES
+    }
+}
 
-        quote! { #n!{ traits #ident Locks  } }
-    });
+fn main() {
+    let future = lock_derive::locks!(write: [cache::<String> as names, cache::<u32> as scores]);
+    let mut locks = block_on_all(future).unwrap();
+    locks.names.0.push("jane".to_string());
+    locks.scores.0.push(10);
+    assert_eq!(1, locks.names.0.len() * locks.scores.0.len());
+}
+```
+
+# Keyed locks
+
+Per-entity locks are written `name[key]`; the recipe receives the key between brackets in its
+`resolve` arm. The same lock may be keyed more than once, the fields are then numbered in the
+order they are declared (`accounts_0`, `accounts_1`, ...) and the `traits` arm is not expanded.
+The locks are ordered by name first, then by key at runtime, so two tasks locking the same keys
+in a different order cannot deadlock. Requesting the same key twice resolves to an error.
 
-    quote! { #(#fields)* }
+```
+use failure::format_err;
+use futures_locks::{RwLock, RwLockWriteGuard};
+use std::collections::HashMap;
+use tokio::executor::current_thread::block_on_all;
+
+macro_rules! accounts {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write [$key:expr]) => {
+        ACCOUNTS[&$key].write().map_err(|_| format_err!("Lock error"))
+    };
+    (traits $access:ident $struct:ty) => {};
 }
 
-fn write_all(args: &Args) -> TokenStream {
-    let locks = write_struct(args);
-    let resolve = write_resolve(args);
-    let traits = write_traits(args);
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: HashMap<u32, RwLock<i32>> =
+        vec![(1, RwLock::new(10)), (2, RwLock::new(20))].into_iter().collect();
+}
 
-    quote! {{
-        #locks
-        #resolve
-        #traits
+fn main() {
+    let (from, to) = (2, 1);
+    let future = lock_derive::locks!(write: [accounts[from], accounts[to]]);
+    let mut locks = block_on_all(future).unwrap();
+    *locks.accounts_0 -= 5;
+    *locks.accounts_1 += 5;
+    assert_eq!(15, *locks.accounts_0);
+    assert_eq!(15, *locks.accounts_1);
+}
+```
+
+# Batch of keys
+
+`read_many` and `write_many` take a collection of keys with `name <- keys`. The keys are sorted
+and deduplicated at runtime, the locks are acquired in that order using the keyed `resolve` arm
+and the field holds a `Vec` of guards, in the order of the sorted keys.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use std::collections::HashMap;
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write [$key:expr]) => {
+#         ACCOUNTS[&$key].write().map_err(|_| format_err!("Lock error"))
+#     };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: HashMap<u32, RwLock<i32>> =
+#         vec![(1, RwLock::new(10)), (2, RwLock::new(20))].into_iter().collect();
+# }
+# fn main() {
+let keys = vec![2, 1, 2];
+let future = lock_derive::locks!(write_many: [accounts <- keys]);
+let mut locks = block_on_all(future).unwrap();
+
+for account in &mut locks.accounts {
+    **account += 1;
+}
+
+assert_eq!(vec![11, 21], locks.accounts.iter().map(|a| **a).collect::<Vec<_>>());
+# }
+```
+
+For a collection of homogeneous locks such as shards, `name[..]` acquires all of them, in the
+`read` and `write` sections as well: the keys are the indexes from 0 to the length of the
+collection, given by the `(len)` arm of the recipe, or by the `len()` method of the instance with
+`name(expr)[..]`. The locks are acquired in ascending index order and the field holds a `Vec` of
+guards.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+macro_rules! shards {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (len) => { SHARDS.len() };
+    (resolve write [$key:expr]) => {
+        SHARDS[$key].write().map_err(|_| format_err!("Lock error"))
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+# lazy_static::lazy_static! {
+#     static ref SHARDS: Vec<RwLock<i32>> = (0..4).map(RwLock::new).collect();
+# }
+# fn main() {
+let mut locks = block_on_all(lock_derive::locks!(write: [shards[..]])).unwrap();
+
+for shard in &mut locks.shards {
+    **shard += 10;
+}
+
+assert_eq!(vec![10, 11, 12, 13], locks.shards.iter().map(|s| **s).collect::<Vec<_>>());
+# }
+```
+
+# Quorum
+
+`quorum: 2 of [replica_a, replica_b, replica_c]` writes the replicas of a replicated lock, in the
+manner of redlock: the invocation resolves once 2 of them are held. The replicas are acquired
+concurrently, each within a deadline, 1s unless given after the brackets, `@ 200ms`, so two tasks
+racing for the quorum give up rather than wait on each other. A replica failing to resolve or
+timing out counts as failed; once all of them are settled, the invocation fails with the error of
+the first replica failed unless the threshold is met, releasing the replicas acquired. The field of
+each replica holds an `Option` of its guard, `None` when it failed.
+
+No other lock of the invocation may be ordered between the replicas. The replicas are locks of a
+recipe, without a lock, a key, a projection or a timeout of their own, their `traits` arm is not
+expanded, and `quorum` cannot be combined with `optimistic`, `transact`, `release`, `erased` or
+`locks_sync!`.
+
+```
+# use failure::format_err;
+# use futures::Future;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! replica_a {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { REPLICA_A.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! replica_c {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { REPLICA_C.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref REPLICA_A: RwLock<i32> = RwLock::new(1);
+#     static ref REPLICA_C: RwLock<i32> = RwLock::new(3);
+# }
+// a replica out of reach.
+macro_rules! replica_b {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => { futures::future::err(format_err!("Replica down")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+# fn main() {
+let future = lock_derive::locks!(quorum: 2 of [replica_a, replica_b, replica_c] @ 200ms);
+let locks = block_on_all(future).unwrap();
+
+assert_eq!(Some(1), locks.replica_a.as_ref().map(|g| **g));
+assert!(locks.replica_b.is_none());
+assert_eq!(Some(3), locks.replica_c.as_ref().map(|g| **g));
+# }
+```
+
+# Projections
+
+When only a field of the locked value is needed, `name => .field: Type` projects the guard: the
+field of the struct dereferences to the inner field, whose type is given after the colon, while
+the guard is kept alive behind it. Projections are supported in the `read`, `write` and `_many`
+sections, the `traits` arm of a projected lock is not expanded.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! config {
+#     (ty write) => { RwLockWriteGuard<Config> };
+#     (resolve write) => { CONFIG.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+struct Config {
+    name: String,
+    retry: Retry,
+}
+
+struct Retry {
+    attempts: u32,
+}
+
+# lazy_static::lazy_static! {
+#     static ref CONFIG: RwLock<Config> = RwLock::new(Config {
+#         name: "billing".to_string(),
+#         retry: Retry { attempts: 3 },
+#     });
+# }
+# fn main() {
+let future = lock_derive::locks!(write: [config => .retry.attempts: u32]);
+let mut locks = block_on_all(future).unwrap();
+*locks.config += 1;
+assert_eq!(4, *locks.config);
+# assert!(CONFIG.try_read().is_err());
+# }
+```
+
+# Into parts
+
+Once acquired together, the guards can be moved apart with `into_parts()`, which consumes the
+struct into a tuple of its fields, in the order they are sorted, followed by a
+`lock_derive_runtime::Written` when it holds write locks, bumping their
+[versions](#versioned-reads) once dropped, after the writes made through the guards.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+# fn main() {
+let locks = block_on_all(lock_derive::locks!(read: [accounts], write: [users])).unwrap();
+let (accounts, mut users, _written) = locks.into_parts();
+drop(accounts);
+*users += 1;
+assert_eq!(2, *users);
+# }
+```
+
+# Named structs
+
+The struct of `locks!` only lives in the expression of the call site. `locks_struct!` defines it at
+module level under the name and the visibility given, so it can appear in the signature of a
+function, be stored in another struct or be resolved from several call sites with `resolve()`. Its
+fields and methods are public, and its locks must all be resolved from their recipe.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+lock_derive::locks_struct!(pub AccountLocks; read: [accounts], write: [users]);
+
+fn open(locks: &mut AccountLocks) {
+    *locks.users += *locks.accounts;
+}
+
+# fn main() {
+let mut locks = block_on_all(AccountLocks::resolve()).unwrap();
+open(&mut locks);
+assert_eq!(11, *locks.users);
+# }
+```
+
+# Implementing a trait
+
+Starting the invocation with `impl AccountStore;` also implements the trait on the struct, so the
+locks can be handed to domain code taking `&mut impl AccountStore` without it knowing about the
+locks. The methods are given by the recipes: the impl expands the
+`(impl AccountStore $access:ident $field:ident)` arm of each lock, which writes the methods it maps
+to its guard, reached as `self.$field`. A recipe without methods for the trait still needs an
+empty arm. Lock sources, projections, batches of keys and the replicas of a quorum are left out,
+as their field does not hold the guard of the recipe.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+trait AccountStore {
+    fn balance(&self) -> i32;
+    fn deposit(&mut self, amount: i32);
+    fn fee(&self) -> i32;
+}
+
+macro_rules! accounts {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+    (impl AccountStore write $field:ident) => {
+        fn balance(&self) -> i32 {
+            *self.$field
+        }
+
+        fn deposit(&mut self, amount: i32) {
+            *self.$field += amount;
+        }
+    };
+}
+
+macro_rules! fees {
+    (ty read) => { RwLockReadGuard<i32> };
+    (resolve read) => { FEES.read().map_err(|_| format_err!("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+    (impl AccountStore $access:ident $field:ident) => {
+        fn fee(&self) -> i32 {
+            *self.$field
+        }
+    };
+}
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref FEES: RwLock<i32> = RwLock::new(1);
+# }
+
+fn pay(store: &mut impl AccountStore, amount: i32) -> i32 {
+    let fee = store.fee();
+    store.deposit(amount - fee);
+    store.balance()
+}
+
+# fn main() {
+let future = lock_derive::locks!(impl AccountStore; write: [accounts], read: [fees]);
+let mut locks = block_on_all(future).unwrap();
+assert_eq!(14, pay(&mut locks, 5));
+# }
+```
+
+# Acquisition order
+
+The struct lists its locks in the order the macro acquires them, as the identity and the access
+of each lock, in `ORDER` for a struct that can be named and through `order()` otherwise. The
+keyed locks of a same lock are listed in the order of the invocation, they are acquired by key
+at runtime.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+lock_derive::locks_struct!(AccountLocks; read: [accounts], write: [users]);
+
+# fn main() {
+assert_eq!(&[("users", "write"), ("accounts", "read")], AccountLocks::ORDER);
+
+let locks = block_on_all(lock_derive::locks!(read: [accounts], write: [users])).unwrap();
+assert_eq!(AccountLocks::ORDER, locks.order());
+# }
+```
+
+# Lock groups
+
+A recurring combination of locks can be declared once as a group and included with
+`include: [group]`. A group is a macro with an `include` arm appending its sections to the
+invocation; the locks of all the groups and of the invocation are then sorted together. A lock
+listed more than once with the same access is acquired once.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! audit {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { AUDIT.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! invoices {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { INVOICES.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref AUDIT: RwLock<i32> = RwLock::new(0);
+#     static ref INVOICES: RwLock<i32> = RwLock::new(0);
+# }
+macro_rules! billing_group {
+    (include $($mac:ident)::+ { $($args:tt)* }) => {
+        $($mac)::+!($($args)* read: [accounts], write: [invoices])
+    };
+}
+
+# fn main() {
+let future = lock_derive::locks!(include: [billing_group], write: [audit]);
+let mut locks = block_on_all(future).unwrap();
+*locks.invoices += *locks.accounts;
+*locks.audit += 1;
+assert_eq!(10, *locks.invoices);
+# }
+```
+
+`lock_group!(billing = read: [accounts], write: [invoices]);` declares the same group without
+writing the macro, and `group: billing` includes it, as `include: [billing]` does. Keeping the
+locks of a domain invariant in a single group keeps their order the same at all the call sites.
+A group only holds sections, without flags, a closure, `cancel` or `quorum`; it may include
+other groups.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! audit {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { AUDIT.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! invoices {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { INVOICES.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref AUDIT: RwLock<i32> = RwLock::new(0);
+#     static ref INVOICES: RwLock<i32> = RwLock::new(0);
+# }
+lock_derive::lock_group!(billing = read: [accounts], write: [invoices]);
+
+# fn main() {
+let future = lock_derive::locks!(group: billing, read: [audit]);
+let mut locks = block_on_all(future).unwrap();
+*locks.invoices += *locks.accounts + *locks.audit;
+assert_eq!(10, *locks.invoices);
+# }
+```
+
+# Acquiring more locks
+
+Acquiring a lock while holding the struct of an earlier invocation breaks the order of the
+chain, unless the new lock comes later in that order than all the held ones. `acquire_more!`
+takes the held struct and the names of its locks, then the sections of `locks!` for the new
+ones: `acquire_more!(held => accounts, users; write: [orders])`. It fails to compile when a new
+lock would have been acquired before a held one, and when the names do not match the fields of
+the held struct, all of them. The future yields the held struct and the struct of the new locks
+as a pair. Otherwise, the held struct must be dropped and all the locks acquired by a single
+invocation.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { USERS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+# fn main() {
+let held = block_on_all(lock_derive::locks!(read: [users])).unwrap();
+
+// `accounts` comes before `users`, the chain acquires it last.
+let future = lock_derive::acquire_more!(held => users; write: [accounts]);
+let (held, more) = block_on_all(future).unwrap();
+
+assert_eq!(11, *held.users + *more.accounts);
+# }
+```
+
+# Merging locks
+
+Helpers written separately each hold their own struct. `merge_locks!` combines held structs and
+new locks into a single struct, as if they had all been acquired by one invocation. It takes
+each held struct followed by the sections of its invocation, then the sections of the new locks:
+`merge_locks!(held => read: [users]; write: [accounts])`. When the new locks all come later in
+the order of the chain than the held ones, the held guards are moved into the chain in place of
+their acquisition. Otherwise, the held structs are released with `unlock_all()` and all the
+locks are acquired again, in order, so the values may have changed meanwhile; which of the two
+happens is decided at compile time. The held locks must be the `read` and `write` locks of a
+recipe, without a lock, a key or a projection, listed completely as for `acquire_more!`.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { USERS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+# fn main() {
+let held = block_on_all(lock_derive::locks!(read: [users])).unwrap();
+
+let future = lock_derive::merge_locks!(held => read: [users]; write: [accounts]);
+let mut locks = block_on_all(future).unwrap();
+
+*locks.accounts += *locks.users;
+assert_eq!(11, *locks.accounts);
+# }
+```
+
+# Dynamic lock sets
+
+When the locks to take depend on the request, `lock_set!(read: [..], write: [..])` lists the
+recipes and accesses that may be chosen at runtime and defines a `LockSet` builder in the
+module. `LockSet::new().read("accounts").write("users").resolve()` sorts the names at runtime
+and acquires the locks in the same order as `locks!`, so both can be mixed without deadlocks.
+The guards are type-erased and retrieved by name with `get` and `get_mut`.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve read) => { USERS.read().map_err(|_| format_err!("Lock error")) };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+lock_derive::lock_set!(read: [accounts, users], write: [users]);
+
+# fn main() {
+let future = LockSet::new().read("accounts").write("users").resolve();
+let mut guards = block_on_all(future).unwrap();
+
+**guards.get_mut::<RwLockWriteGuard<i32>>("users").unwrap() += 1;
+assert_eq!(2, **guards.get::<RwLockWriteGuard<i32>>("users").unwrap());
+assert_eq!(vec!["users", "accounts"], guards.names().collect::<Vec<_>>());
+# }
+```
+
+# Lock manager
+
+Plugins may add locks which no invocation can name. The `LockManager` of `lock_derive_runtime`,
+imported by the [runtime](#runtime), holds the locks registered under a name at startup, with
+`register_locks!(read: [..], write: [..])` for recipes, registered under the name of the recipe,
+or with `LockManager::register(name, access, factory)`. `LockManager::acquire(&["accounts:w",
+"users:r"])` then sorts the names and acquires the locks in the same order as `locks!` for the
+recipes, the guards being retrieved by name as with `LockSet`. A name requested twice or not
+registered for the access fails the future.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+# }
+# macro_rules! users {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { USERS.read().map_err(|_| format_err!("Lock error")) };
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+lock_derive::runtime!();
+
+# fn main() {
+lock_derive::register_locks!(read: [users], write: [accounts]);
+
+let future = LockManager::acquire(&["accounts:w", "users:r"]);
+let mut guards = block_on_all(future).unwrap();
+
+**guards.get_mut::<RwLockWriteGuard<i32>>("accounts").unwrap() += 1;
+assert_eq!(11, **guards.get::<RwLockWriteGuard<i32>>("accounts").unwrap());
+assert_eq!(vec!["users", "accounts"], guards.names().collect::<Vec<_>>());
+# }
+```
+
+# Write intent
+
+Two readers both upgrading to write deadlock, or one of them acts on a value changed by the
+other. `intent: [accounts]` takes the read guard of the lock along with its intent, a token
+shared by lock identity that a single task holds at a time, so the holder is the only one
+allowed to upgrade. The field derefs to the value, and `upgrade_accounts()` on the struct
+releases all the guards and resolves the locks again in order, as with
+[`upgrade: [..]`](#upgrading-locks), the lock of the intent being written this time. The intent
+is kept meanwhile and until the upgraded struct is dropped, whose field is an `Upgraded` guard of
+`lock_derive_runtime`; a plain `write:` may still take the lock in between. The upgraded struct
+is named `<Struct><Field>Write` for `locks_struct!`. The intent tokens are kept by
+`lock_derive_runtime`, by crate and by lock identity. The intent needs a recipe with both the
+`read` and the `write` arms and a lock without key; it is not supported by `locks_sync!`.
+
+```
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+
+fn main() {
+    let locks = block_on_all(lock_derive::locks!(intent: [accounts])).unwrap();
+
+    if *locks.accounts > 5 {
+        let mut locks = block_on_all(locks.upgrade_accounts()).unwrap();
+        *locks.accounts -= 5;
+    }
+
+    assert_eq!(5, *ACCOUNTS.try_read().unwrap());
+}
+```
+
+# Permits
+
+Bounded resources such as semaphores deadlock with locks in the same way, so they take part in
+the same ordering. `permits: [db_pool(4)]` asks the recipe for 4 permits through its
+`(resolve permits $count:expr)` arm and stores whatever the recipe yields, like the owned
+permits of a semaphore, in the `db_pool` field typed by the `(ty permits)` arm. The count
+defaults to 1 when omitted.
+
+```
+use failure::format_err;
+use futures::future::{lazy, Future};
+use futures_locks::{RwLock, RwLockWriteGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::executor::current_thread::block_on_all;
+
+macro_rules! accounts {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => {
+        lazy(|| {
+            ACQUIRED.lock().unwrap().push("accounts");
+            ACCOUNTS.write().map_err(|_| format_err!("Lock error"))
+        })
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! db_pool {
+    (ty permits) => { Permits };
+    (resolve permits $count:expr) => { acquire_permits($count) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref ACQUIRED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+}
+
+// a semaphore of 8 permits, given back when `Permits` is dropped.
+static AVAILABLE: AtomicUsize = AtomicUsize::new(8);
+
+struct Permits(usize);
+
+impl Drop for Permits {
+    fn drop(&mut self) {
+        AVAILABLE.fetch_add(self.0, Ordering::SeqCst);
+    }
+}
+
+fn acquire_permits(count: usize) -> impl Future<Item = Permits, Error = failure::Error> {
+    lazy(move || {
+        ACQUIRED.lock().unwrap().push("db_pool");
+        AVAILABLE.fetch_sub(count, Ordering::SeqCst);
+        Ok(Permits(count))
+    })
+}
+
+fn main() {
+    let future = lock_derive::locks!(permits: [db_pool(4)], write: [accounts]);
+    let locks = block_on_all(future).unwrap();
+    assert_eq!(4, locks.db_pool.0);
+    assert_eq!(4, AVAILABLE.load(Ordering::SeqCst));
+
+    // the permits are ordered with the locks, by the name of their recipe then their count.
+    assert_eq!([("db_pool(4)", "permits"), ("accounts", "write")], locks.order());
+    assert_eq!(["db_pool", "accounts"], ACQUIRED.lock().unwrap()[..]);
+
+    drop(locks);
+    assert_eq!(8, AVAILABLE.load(Ordering::SeqCst));
+}
+```
+
+# Pool checkouts
+
+A connection taken from an async pool, such as the pools of `bb8` or `deadpool`, is held like a
+lock and deadlocks with the locks in the same way, so it takes part in the same ordering.
+`checkout: [db]` takes the connection through the `(resolve checkout)` arm of the recipe, which
+may be an `async` block, and stores it in the `db` field typed by the `(ty checkout)` arm. The
+connection goes back to the pool when the struct is dropped. A pool given at the call site,
+`checkout: [db(&pool)]`, is passed to the recipe like a borrowed lock.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+use std::sync::Mutex;
+use tokio::executor::current_thread::block_on_all;
+
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+macro_rules! db {
+    (ty checkout) => { Object };
+    (resolve checkout) => { async { DB_POOL.get().await } };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref DB_POOL: Pool = Pool(Mutex::new(vec![1, 2]));
+}
+
+// a pool of connections, numbered here, as the pools of `bb8` or `deadpool`.
+struct Pool(Mutex<Vec<u32>>);
+
+impl Pool {
+    async fn get(&'static self) -> Result<Object, failure::Error> {
+        let connection = self.0.lock().unwrap().pop();
+        let connection = connection.ok_or_else(|| format_err!("Pool exhausted"))?;
+        Ok(Object(connection, self))
+    }
+}
+
+// the connection, going back to its pool when dropped.
+struct Object(u32, &'static Pool);
+
+impl Drop for Object {
+    fn drop(&mut self) {
+        (self.1).0.lock().unwrap().push(self.0);
+    }
+}
+
+fn main() {
+    let future = lock_derive::locks!(checkout: [db], write: [accounts]);
+    let mut locks = block_on_all(future).unwrap();
+    *locks.accounts += locks.db.0 as i32;
+    assert_eq!(12, *locks.accounts);
+    assert_eq!(vec![1], *DB_POOL.0.lock().unwrap());
+
+    drop(locks);
+    assert_eq!(vec![1, 2], *DB_POOL.0.lock().unwrap());
+}
+```
+
+# Freezing bytes
+
+For a lock guarding a `BytesMut`, `freeze_bytes: [inbox]` takes the write lock through the
+`(resolve write)` arm of the recipe, splits off the readable bytes, freezes them and releases the
+write lock right away. The `inbox` field then holds the `bytes::Bytes`.
+
+```
+use bytes::BytesMut;
+use failure::format_err;
+use futures_locks::RwLock;
+use tokio::executor::current_thread::block_on_all;
+
+macro_rules! inbox {
+    (resolve write) => { INBOX.write().map_err(|_| format_err!("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref INBOX: RwLock<BytesMut> = RwLock::new(BytesMut::from(&b"hello"[..]));
+}
+
+fn main() {
+    let locks = block_on_all(lock_derive::locks!(freeze_bytes: [inbox])).unwrap();
+    assert_eq!(&b"hello"[..], &locks.inbox[..]);
+    assert!(INBOX.try_read().unwrap().is_empty());
+}
+```
+
+# Snapshots
+
+Read-mostly data, such as a configuration, is better copied than held. For a lock guarding an
+`Arc<T>`, `snapshot: [config]` takes the read lock through the `(resolve read)` arm of the
+recipe, clones the `Arc` and releases the read lock right away, in its turn in the order of the
+chain. The `config` field then holds the `Arc<T>`, typed from the target of the `(ty read)`
+guard. An `arc_swap::ArcSwap` fits as well, with a `(resolve read)` arm returning
+`futures::future::ok(CONFIG.load())`.
+
+```
+use failure::format_err;
+use futures::Future;
+use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::Arc;
+use tokio::executor::current_thread::block_on_all;
+
+macro_rules! config {
+    (ty read) => { RwLockReadGuard<Arc<String>> };
+    (ty write) => { RwLockWriteGuard<Arc<String>> };
+    (resolve read) => { CONFIG.read().map_err(|_| format_err!("Lock error")) };
+    (resolve write) => { CONFIG.write().map_err(|_| format_err!("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref CONFIG: RwLock<Arc<String>> = RwLock::new(Arc::new("v1".to_string()));
+}
+
+fn main() {
+    let locks = block_on_all(lock_derive::locks!(snapshot: [config])).unwrap();
+
+    // the configuration can be replaced while the snapshot is held.
+    *block_on_all(lock_derive::locks!(write: [config])).unwrap().config = Arc::new("v2".into());
+    assert_eq!("v1", locks.config.as_str());
+}
+```
+
+# Versioned reads
+
+Each lock has a version, kept in `lock_derive_runtime` by crate and by the full path of its recipe,
+as the locks are sorted. `versioned: [accounts]` takes the read lock through the `(resolve read)`
+arm of the recipe, clones the value along with the version of the lock and releases the read lock
+right away, the `accounts` field holding a `lock_derive_runtime::Versioned<T>` of the target of the
+`(ty read)` guard. The structs holding write locks have `commit()`, which bumps their versions right
+away, and bump them anyway when they are dropped, before their guards are released; `into_parts()`
+and `tuple;` give the guards along with a `lock_derive_runtime::Written` bumping the versions once
+dropped. `validate()` tells whether the versioned locks were written since they were read, as does
+`is_current()` on the value itself. The value is a copy: reading it again, or checking that a
+computation made on it is still current, means locking again, the computation being written if
+`validate()` still holds once the write lock is held. Borrowed, keyed and sourced locks have no
+versions.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# fn main() {
+let read = block_on_all(lock_derive::locks!(versioned: [accounts])).unwrap();
+let balance = *read.accounts * 2;
+
+let mut write = block_on_all(lock_derive::locks!(write: [accounts])).unwrap();
+
+if read.validate() {
+    *write.accounts = balance;
+    write.commit();
+}
+
+assert!(!read.validate());
+drop(write);
+assert_eq!(10, *read.accounts);
+# }
+```
+
+# Lazy initialization
+
+A resource created on first use, such as a `OnceCell` filled by an async call, is initialized
+while the tasks acquire their locks, and two tasks initializing it at the same time interleave
+with the chain. `init: [config]` resolves the value through the `(resolve init)` arm of the
+recipe, which may initialize the resource, under an initialization token shared by lock
+identity: the first task holds the token while it initializes, in its turn in the order of the
+chain, and the others wait for it. Once a value is resolved, the token is no longer taken. The
+field is typed by the `(ty init)` arm, e.g. `&'static T` or a guard. The tokens are kept by
+`lock_derive_runtime`. The initialization needs a lock without key; it is not supported by
+`locks_sync!`.
+
+```
+use std::sync::OnceLock;
+use tokio::executor::current_thread::block_on_all;
+
+static CONFIG: OnceLock<String> = OnceLock::new();
+
+macro_rules! config {
+    (ty init) => { &'static String };
+    (resolve init) => {
+        futures::future::lazy(|| match CONFIG.get() {
+            Some(config) => Ok::<_, failure::Error>(config),
+            // e.g. loaded from a service, the first time only.
+            None => Ok(CONFIG.get_or_init(|| "loaded".to_string())),
+        })
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+fn main() {
+    let locks = block_on_all(lock_derive::locks!(init: [config])).unwrap();
+    assert_eq!("loaded", locks.config.as_str());
+}
+```
+
+# Optimistic acquisition
+
+Most acquisitions are uncontended, yet the chain waits for each lock in turn. Starting the
+invocation with `optimistic;` first tries every lock at once with the `try_resolve` arm of the
+recipes, which returns an `Option` of the guard, e.g. `ACCOUNTS.try_read().ok()`. When any of
+them is not available, the guards already taken are released and the locks are acquired with the
+ordered chain. The key expressions are evaluated again on the fallback. As the chain, the attempt
+is only made when the future is first polled, so a future dropped unpolled takes no lock.
+
+```
+use futures_locks::RwLock;
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+}
+
+lock_derive::define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+
+fn main() {
+    let future = lock_derive::locks!(optimistic; write: [accounts]);
+    assert!(ACCOUNTS.try_read().is_ok());
+    drop(future);
+
+    let mut locks = block_on_all(lock_derive::locks!(optimistic; write: [accounts])).unwrap();
+    *locks.accounts += 1;
+    assert!(ACCOUNTS.try_read().is_err());
+    drop(locks);
+
+    assert_eq!(11, *ACCOUNTS.try_read().unwrap());
+}
+```
+
+# Async functions
+
+`#[with_locks(read(accounts), write(users))]` on an `async fn` acquires the locks before its body
+and binds each guard to the name of its field, with no macro in expression position. The
+sections and the flags are those of `locks!`, written `name(locks)` and `flag`. The error of the
+acquisition is returned with `?`, so the function must return a `Result` whose error converts
+from `failure::Error`.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use lock_derive_runtime::block_on;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+use lock_derive::with_locks;
+
+#[with_locks(read(accounts), write(users))]
+async fn add_user() -> Result<i32, failure::Error> {
+    *users += 1;
+    Ok(*accounts + *users)
+}
+
+# fn main() {
+assert_eq!(12, block_on(add_user()).unwrap());
+# }
+```
+
+Within an `async` body, `with_locks_block!(read: [accounts], write: [users] => { .. })` does the
+same for a block, with the sections of `locks!`: the guards are bound as locals of the block and
+dropped at its closing brace, which ends the critical section. The block is the value of the
+macro. The name of `with_locks` being taken by the attribute, the statement form is named
+`with_locks_block!`.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use lock_derive_runtime::block_on;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+use lock_derive::with_locks_block;
+
+async fn add_user() -> Result<i32, failure::Error> {
+    let total = with_locks_block!(read: [accounts], write: [users] => {
+        *users += 1;
+        *accounts + *users
+    });
+
+    // the guards are dropped.
+    assert!(USERS.try_write().is_ok());
+    Ok(total)
+}
+
+# fn main() {
+assert_eq!(12, block_on(add_user()).unwrap());
+# }
+```
+
+# Tuples
+
+For a quick critical section, starting the invocation with `tuple;` evaluates to a future of a
+plain tuple of the guards, in the order of the sections, without generating the struct, its
+methods nor the `traits` arms, followed by a `lock_derive_runtime::Written` when it holds write
+locks, bumping their [versions](#versioned-reads) once dropped. The locks are still acquired in
+the global order. With
+`locks_sync!`, it evaluates to a `Result` of the tuple. `tuple` cannot be combined with the
+options generating methods on the struct, and the `diagnostics` and `reentrancy` features neither
+time the hold nor track the guards of a tuple once it is resolved.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+# fn main() {
+let future = lock_derive::locks!(tuple; write: [users], read: [accounts]);
+let (mut users, accounts, _written) = block_on_all(future).unwrap();
+*users += *accounts;
+assert_eq!(11, *users);
+# }
+```
+
+# Scoped locks
+
+Ending the invocation with a closure, `locks!(read: [a], write: [b], |l| async move { .. })`,
+runs the closure with the locks and evaluates to a future of its output, wrapped in a
+`Result<_, failure::Error>`. The closure borrows the struct, `&mut`, whose guards are dropped as
+soon as its future completes, so they cannot be held by mistake across the `.await` that follows
+the critical section, nor be returned by the closure. With `locks_sync!`, the closure returns its
+output directly.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use lock_derive_runtime::block_on;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+# fn main() {
+let future = lock_derive::locks!(read: [accounts], write: [users], |l| async move {
+    *l.users += 1;
+    *l.accounts + *l.users
+});
+
+assert_eq!(12, block_on(future).unwrap());
+assert!(USERS.try_write().is_ok());
+# }
+```
+
+# Choosing the locks
+
+When the locks depend on the operation, `locks_for!(OpLocks; match op { .. }, |locks| ..)`
+matches the operation with arms whose bodies are the sections of an invocation, resolves the
+locks of the arm taken and runs the closure, as a [scoped invocation](#scoped-locks) would, with
+an enum named `OpLocks`. Each arm resolves to the variant named after the last segment of its
+pattern, `Other` for `_`, holding the struct of the arm; the enum is defined next to the closure,
+the only place it can be named. The arms may have guards, but not `blocking;`, `builder;`,
+groups or a closure of their own.
+
+```
+# use futures_locks::RwLock;
+# use lock_derive::define_lock;
+# use lock_derive_runtime::block_on;
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+# define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+# define_lock!(users: RwLock<i32> = USERS);
+enum Op {
+    Deposit,
+    Report,
+}
+
+async fn handle(op: Op) -> Result<i32, failure::Error> {
+    lock_derive::locks_for!(OpLocks; match op {
+        Op::Deposit => { write: [accounts] },
+        Op::Report => { no_traits; read: [accounts, users] },
+    }, |locks| async move {
+        match locks {
+            OpLocks::Deposit(locks) => {
+                *locks.accounts += 5;
+                *locks.accounts
+            }
+            OpLocks::Report(locks) => *locks.accounts + *locks.users,
+        }
+    })
+    .await
+}
+
+# fn main() {
+assert_eq!(15, block_on(handle(Op::Deposit)).unwrap());
+assert_eq!(16, block_on(handle(Op::Report)).unwrap());
+# }
+```
+
+# Errors
+
+The error of a recipe is wrapped with the lock and its access, so the failing lock can be told
+from the logs.
+
+At compile time, a missing recipe or a missing arm is reported on the lock of the invocation, e.g.
+`no rules expected write` when the recipe has no `(ty write)` arm. A recipe is expected to have a
+`(ty <access>)`, a `(resolve <access>)` and a `(traits $access:ident $struct:ty)` arm for each
+access it is used with; the recipes written by `define_lock!` name the unsupported arm.
+
+```
+# use failure::format_err;
+# use futures_locks::RwLockWriteGuard;
+# use tokio::executor::current_thread::block_on_all;
+macro_rules! accounts {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => { futures::future::err(format_err!("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+# fn main() {
+let e = block_on_all(lock_derive::locks!(write: [accounts])).err().unwrap();
+assert_eq!("acquiring write lock `accounts`: Lock error", e.to_string());
+# }
+```
+
+# Timeouts
+
+A lock followed by `@ 200ms` or `@ 2s` fails fast when it is not acquired in time, each lock with
+its own timeout. The error names the lock and tells how long the locks acquired before it were
+held while it was waited on. The timeouts use the timer of the
+[runtime adapter](#runtime-adapters) and are not supported on keyed locks nor by `locks_sync!`.
+
+The acquisition given up is dropped while it waits in the queue of the lock, and a
+`futures_locks::RwLock` panics on its next release when one of its waiters is gone. The recipes
+of `define_lock!` wrap their acquisitions in `lock_derive_runtime::detach`, which hands an
+acquisition dropped while pending to a background thread releasing the lock as soon as it is
+granted; the recipes written for such locks do the same.
+
+```
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+use tokio::runtime::current_thread::Runtime;
+
+lazy_static::lazy_static! {
+    static ref AUDIT: RwLock<u32> = RwLock::new(1);
+    static ref PAYMENTS: RwLock<i32> = RwLock::new(10);
+}
+
+define_lock!(audit: RwLock<u32> = AUDIT);
+define_lock!(payments: RwLock<i32> = PAYMENTS);
+
+fn main() {
+    // `audit` is held elsewhere.
+    let held = AUDIT.try_write().unwrap();
+
+    let future = lock_derive::locks!(write: [payments @ 2s, audit @ 200ms]);
+    let e = Runtime::new().unwrap().block_on(future).err().unwrap();
+
+    assert!(e.to_string().starts_with(
+        "acquiring write lock `audit`: timed out after 200ms, the earlier locks were held for"
+    ));
+    drop(held);
+}
+```
+
+# Runtime adapters
+
+The timeouts, the builder and the supervisor wait on the timer of a `RuntimeAdapter` of
+`lock_derive_runtime`, which also spawns the futures to run in the background. The adapter is
+`Tokio` with the default `tokio` feature of `lock_derive_runtime`, or `AsyncStd` when only its
+`async-std` feature is on. `adapter: Type` gives the adapter of an invocation, one of those or
+one of the calling crate.
+
+```
+# use futures_locks::RwLockWriteGuard;
+# use lock_derive_runtime::{Delay, RuntimeAdapter};
+# use std::time::Duration;
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! audit {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { futures::future::empty() };
+#     (traits $access:ident $struct:ty) => {};
+# }
+// a timer elapsing right away.
+struct Expired;
+
+impl RuntimeAdapter for Expired {
+    fn delay(_: Duration) -> Delay {
+        Box::new(futures::future::ok(()))
+    }
+
+    fn spawn<F>(future: F)
+    where
+        F: futures::Future<Item = (), Error = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+}
+
+# fn main() {
+let future = lock_derive::locks!(adapter: Expired, write: [audit @ 2s]);
+let e = block_on_all(future).err().unwrap();
+assert_eq!(e.to_string(), "acquiring write lock `audit`: timed out after 2s");
+# }
+```
+
+# Cancellation
+
+`cancel: token` abandons a pending acquisition once the token is cancelled, e.g. on a graceful
+shutdown. The chain is raced against `token.cancelled()`, any value with a `cancelled()` method
+returning a future will do, such as a `tokio_util::sync::CancellationToken`. Once cancelled, the
+guards already acquired are dropped and the future fails with `Cancelled` of
+`lock_derive_runtime`, imported by the [runtime](#runtime). As with the [timeouts](#timeouts),
+the acquisition given up is kept alive by the recipes of `define_lock!` until the lock is
+granted, and `cancel` is not supported by `builder;` nor by `locks_sync!`.
+
+```
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+use lock_derive_runtime::Cancelled;
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref AUDIT: RwLock<u32> = RwLock::new(1);
+    static ref PAYMENTS: RwLock<i32> = RwLock::new(10);
+}
+
+define_lock!(audit: RwLock<u32> = AUDIT);
+define_lock!(payments: RwLock<i32> = PAYMENTS);
+
+// a token cancelled from the start, as on a shutdown.
+struct Shutdown;
+
+impl Shutdown {
+    async fn cancelled(&self) {}
+}
+
+fn main() {
+    // `audit` is held elsewhere: `payments` is acquired first, then the chain waits for `audit`.
+    let held = AUDIT.try_write().unwrap();
+
+    let future = lock_derive::locks!(cancel: Shutdown, write: [audit, payments]);
+    let e = block_on_all(future).err().unwrap();
+
+    assert!(e.downcast_ref::<Cancelled>().is_some());
+    assert!(PAYMENTS.try_write().is_ok());
+    drop(held);
+}
+```
+
+# Lock hierarchy
+
+Starting the invocation with `hierarchy;` checks at compile time that the locks are acquired by
+increasing level, the level of a lock being given by the `(level)` arm of its recipe, or by
+`define_lock!(name: RwLock<T> = expr, level = 1)`. Since every invocation acquires its locks in
+the same order, an invocation acquiring a lock after a lock of a higher level fails to compile,
+which keeps the hierarchy between all the invocations. Lock sources have no level and are not
+checked.
+
+```
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<u32> = RwLock::new(1);
+}
+
+// `users` is acquired first, it must not have a higher level than `accounts`.
+define_lock!(accounts: RwLock<i32> = ACCOUNTS, level = 2);
+define_lock!(users: RwLock<u32> = USERS, level = 1);
+
+fn main() {
+    let future = lock_derive::locks!(hierarchy; read: [accounts, users]);
+    assert_eq!(10, *block_on_all(future).unwrap().accounts);
+}
+```
+
+# Builder
+
+Starting the invocation with `builder;` evaluates to a `LocksBuilder` instead of the future, so the
+acquisition knobs are set at runtime: `with_timeout(d)` fails an attempt not resolved in time,
+`with_retry(n)` makes up to `n` more attempts after a failure and `with_observer(f)` is called
+after each attempt with its error, if any, and its duration. `resolve()` then returns the future
+of the struct. Each attempt resolves the chain again, the guards of a failed attempt being
+released, so `builder;` cannot be combined with `owned;`, `blocking;` or a closure. The timeout
+uses the timer of the [runtime adapter](#runtime-adapters), and as with the
+[timeouts](#timeouts), the acquisition given up by an attempt is kept alive by the recipes of
+`define_lock!` until the lock is granted. The builder also implements `IntoFuture`, so in an
+`async` block `locks!(builder; write: [accounts]).with_retry(3).await?` resolves the struct
+without calling `resolve()`.
+
+```
+# use failure::format_err;
+# use futures::future::{err, Either};
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use std::sync::atomic::{AtomicUsize, Ordering};
+# use tokio::executor::current_thread::block_on_all;
+macro_rules! accounts {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => {
+        // the first attempt fails.
+        match ATTEMPTS.fetch_add(1, Ordering::SeqCst) {
+            0 => Either::A(err(format_err!("Lock error"))),
+            _ => Either::B(ACCOUNTS.write().map_err(|_| format_err!("Lock error"))),
+        }
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+# fn main() {
+let future = lock_derive::locks!(builder; write: [accounts])
+    .with_retry(3)
+    .with_observer(|e, _| println!("attempt failed: {}", e.is_some()))
+    .resolve();
+
+assert_eq!(10, *block_on_all(future).unwrap().accounts);
+assert_eq!(2, ATTEMPTS.load(Ordering::SeqCst));
+# }
+```
+
+# Transactions
+
+Starting the invocation with `transact;` adds a `transact(f)` method to the struct, running `f`
+against a `LocksStaged` holding a clone of the value of each write lock next to the read guards.
+The staged values are written back to the locks only if `f` returns `Ok`, so an error leaves the
+guarded values untouched. The locks are released once `transact` returns, in both cases. The
+values of the write locks must implement `Clone`.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<Vec<i32>> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! limit {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { LIMIT.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<Vec<i32>> = RwLock::new(vec![10, 5]);
+#     static ref LIMIT: RwLock<i32> = RwLock::new(8);
+# }
+fn transfer(amount: i32) -> Result<i32, &'static str> {
+    let future = lock_derive::locks!(transact; write: [accounts], read: [limit]);
+
+    block_on_all(future).unwrap().transact(|t| {
+        t.accounts[0] -= amount;
+        t.accounts[1] += amount;
+
+        if amount > **t.limit {
+            return Err("over limit");
+        }
+
+        Ok(t.accounts[0])
+    })
+}
+
+# fn main() {
+assert_eq!(Ok(7), transfer(3));
+assert_eq!(Err("over limit"), transfer(9));
+assert_eq!(vec![7, 8], *block_on_all(ACCOUNTS.read()).unwrap());
+# }
+```
+
+# Waiting on a condition
+
+Starting the invocation with `condvar;` adds two methods to the struct, in the manner of a
+condition variable. `wait_until(pred)` returns the future of the struct once `pred` holds: until
+then it releases all the guards, waits for `notify()` to be called on any of the locks and
+acquires them again in the same order, so no guard is ever held out of order. The waiter
+registers before releasing the guards, so a `notify()` given under a write lock of the struct
+cannot be missed. The locks are resolved again from their recipe, so they cannot be borrowed,
+keyed or counted. The waiters are kept by `lock_derive_runtime`, by crate and by lock identity.
+
+```
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref QUEUE: RwLock<Vec<i32>> = RwLock::new(Vec::new());
+}
+
+define_lock!(queue: RwLock<Vec<i32>> = QUEUE);
+
+fn main() {
+    let consumer = lock_derive::locks!(condvar; write: [queue])
+        .and_then(|locks| locks.wait_until(|locks| !locks.queue.is_empty()))
+        .map(|mut locks| locks.queue.pop());
+
+    let producer = tokio::timer::Delay::new(Instant::now() + Duration::from_millis(10))
+        .map_err(failure::Error::from)
+        .and_then(|_| lock_derive::locks!(condvar; write: [queue]))
+        .map(|mut locks| {
+            locks.queue.push(4);
+            locks.notify();
+        });
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.spawn(producer.map_err(|_| ()));
+    assert_eq!(Some(4), runtime.block_on(consumer).unwrap());
+}
+```
+
+# Releasing the locks
+
+A distributed lock, on Redis or etcd, is released by an async call rather than by dropping its
+guard. Starting the invocation with `release;` adds `release()` to the struct, giving each guard
+to the `(release $access $guard)` arm of its recipe, which returns a future and may be an `async`
+block. The locks are released one after the other, in the reverse order of their acquisition and
+the last key of a batch first, and the release stops at the first error. Lock sources, `intent`,
+`freeze_bytes` and `snapshot` have no guard to give back and are rejected.
+
+```
+# use futures::Future;
+# use tokio::executor::current_thread::block_on_all;
+pub struct Lease(u64);
+# impl std::ops::Deref for Lease {
+#     type Target = u64;
+#     fn deref(&self) -> &u64 {
+#         &self.0
+#     }
+# }
+# fn acquire_lease() -> impl Future<Item = Lease, Error = failure::Error> {
+#     futures::future::ok(Lease(7))
+# }
+# async fn release_lease(lease: Lease) -> Result<(), failure::Error> {
+#     Ok(())
+# }
+
+macro_rules! leader {
+    (ty write) => { Lease };
+    (resolve write) => { acquire_lease() };
+    (release write $guard:expr) => { release_lease($guard) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+# fn main() {
+let locks = block_on_all(lock_derive::locks!(release; write: [leader])).unwrap();
+assert_eq!(7, *locks.leader);
+block_on_all(locks.release()).unwrap();
+# }
+```
+
+# Unlocking
+
+`unlock_all()` releases the guards explicitly, one by one, in the order of the drop of the
+struct, see [drop order](#drop-order), and the last key of a batch first. With `locks!`, it
+returns a future completing once all the guards are released, through `release()` for
+`release;`. With `locks_sync!`, the guards are dropped before it returns.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(20);
+# }
+# fn main() {
+let locks = block_on_all(lock_derive::locks!(write: [accounts, users])).unwrap();
+block_on_all(locks.unlock_all()).unwrap();
+assert!(ACCOUNTS.try_write().is_ok());
+# }
+```
+
+# Drop order
+
+The guards of the struct are declared in the order of their release, so dropping the struct
+releases them in the reverse order of their acquisition, the first lock of the sections last.
+`drop_order: [..]` names the fields released first, in that order, the others following in the
+reverse order of their acquisition, when a wakeup or a notification downstream must come after a
+given release. `unlock_all()` and `release()` follow the same order; the keys of a batch are
+dropped in the order of the keys, `into_parts()` and the tuple of `tuple;` leave the order to
+the caller.
+
+```
+# use std::sync::Mutex;
+# lazy_static::lazy_static! {
+#     static ref RELEASED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+# }
+# struct Guard(&'static str);
+# impl Drop for Guard {
+#     fn drop(&mut self) {
+#         RELEASED.lock().unwrap().push(self.0);
+#     }
+# }
+# impl std::ops::Deref for Guard {
+#     type Target = str;
+#     fn deref(&self) -> &str {
+#         self.0
+#     }
+# }
+# macro_rules! accounts {
+#     (ty write) => { Guard };
+#     (resolve write) => { futures::future::ok::<_, failure::Error>(Guard("accounts")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { Guard };
+#     (resolve write) => { futures::future::ok::<_, failure::Error>(Guard("users")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! orders {
+#     (ty write) => { Guard };
+#     (resolve write) => { futures::future::ok::<_, failure::Error>(Guard("orders")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# use futures::Future;
+# fn main() {
+let future = lock_derive::locks!(drop_order: [users], write: [accounts, orders, users]);
+drop(future.wait().unwrap());
+assert_eq!(*RELEASED.lock().unwrap(), ["users", "accounts", "orders"]);
+# }
+```
+
+# Yielding the locks
+
+A long critical section starves the other tasks waiting on its locks. `yield_locks()` releases
+the guards with `unlock_all()`, yields to the scheduler and resolves the locks again in the same
+order, returning a fresh struct; `yield_locks_after(future)` awaits `future` in between instead.
+The values may have changed meanwhile. Only the locks resolved from their recipe, without a lock,
+a key or a count, can be resolved again, the methods are not generated otherwise.
+
+```
+# use failure::format_err;
+# use futures::Future;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# fn main() {
+let future = lock_derive::locks!(write: [accounts])
+    .and_then(|mut locks| {
+        *locks.accounts += 1;
+        locks.yield_locks()
+    })
+    .map(|mut locks| {
+        *locks.accounts += 1;
+        *locks.accounts
+    });
+
+assert_eq!(12, block_on_all(future).unwrap());
+# }
+```
+
+# Upgrading locks
+
+A read lock cannot be traded for a write lock in place: two readers upgrading at the same time
+would wait on each other forever. Listing read locks in `upgrade: [..]` adds
+`upgrade_<field>(revalidate)` to the struct, which releases all the guards with `unlock_all()`
+and resolves the locks again in order, that lock being written this time. Another task may have
+written in between, so `revalidate` checks the guards of the upgraded struct and fails the
+future with its error when what was read no longer holds. The upgraded struct is a struct of its
+own, named `<Struct><Field>Write` for `locks_struct!`; the recipe needs a `write` arm, and the
+locks are all resolved from their recipe, without a lock, a key or a count.
+
+```
+# use failure::format_err;
+# use futures::Future;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# fn main() {
+let future = lock_derive::locks!(read: [accounts], upgrade: [accounts])
+    .and_then(|locks| {
+        let balance = *locks.accounts;
+
+        locks.upgrade_accounts(move |locks| match *locks.accounts == balance {
+            true => Ok(()),
+            false => Err(format_err!("The balance has changed.")),
+        })
+    })
+    .map(|mut locks| {
+        *locks.accounts -= 5;
+        *locks.accounts
+    });
+
+assert_eq!(5, block_on_all(future).unwrap());
+# }
+```
+
+# Escalation
+
+Most writes are decided by a read: a balance above a threshold, an entry that is missing.
+Listing read locks in `escalate: [..]` adds `escalate(check, mutate)` to the struct. `check`
+looks at the guards through a view, `<Struct>View` for `locks_struct!`, and the future resolves
+to `None` without writing when it fails. Otherwise the guards are released, the locks are
+resolved again with the listed ones written, and `check` runs once more on the new guards, since
+another task may have written in between; `mutate` only runs when it still holds, and the
+future resolves to its result. The escalated struct is named `<Struct>EscalateWrite`; the
+requirements on the locks are those of `upgrade: [..]`.
+
+```
+# use failure::format_err;
+# use futures::Future;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# fn main() {
+let withdraw = || {
+    lock_derive::locks!(read: [accounts], escalate: [accounts]).and_then(|locks| {
+        locks.escalate(
+            |view| *view.accounts >= 5,
+            |locks| {
+                *locks.accounts -= 5;
+                *locks.accounts
+            },
+        )
+    })
+};
+
+assert_eq!(Some(5), block_on_all(withdraw()).unwrap());
+assert_eq!(Some(0), block_on_all(withdraw()).unwrap());
+assert_eq!(None, block_on_all(withdraw()).unwrap());
+# }
+```
+
+# Writer preference
+
+Readers do not wait on a waiting writer with most read-write locks, so heavy read traffic can
+starve the writers. `policy: write_preferring` emulates a writer-preferring lock: a writer holds
+the turnstile of the lock while it waits for it, and a reader passes through the turnstile
+before reading, so the readers coming after a waiting writer queue behind it. The turnstiles are
+shared by the invocations using the policy, by lock identity; keyed and borrowed locks are
+acquired as usual, and the `optimistic;` attempt is skipped. The policy is not supported by
+`locks_sync!`, where the fairness is decided by the backend.
+
+The turnstiles are kept by `lock_derive_runtime`, by crate and by lock identity.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# fn main() {
+let future = lock_derive::locks!(policy: write_preferring, write: [accounts]);
+*block_on_all(future).unwrap().accounts += 1;
+
+let future = lock_derive::locks!(policy: write_preferring, read: [accounts]);
+assert_eq!(11, *block_on_all(future).unwrap().accounts);
+# }
+```
+
+# Blocking
+
+Starting the invocation with `blocking;` also generates a `resolve_blocking()` function next to
+`resolve()` and resolves the locks on the spot, the macro then evaluates to a
+`Result<Locks, failure::Error>`. The blocking resolver waits on the same future as `resolve()`,
+so both paths acquire the locks in the same order. It blocks the current thread and must not be
+called from within an executor.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard};
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# fn main() {
+let locks = lock_derive::locks!(blocking; read: [accounts]).unwrap();
+assert_eq!(10, *locks.accounts);
+# }
+```
+
+# Blocking locks
+
+`locks_sync!` takes the same arguments as `locks!` for the `std::sync` or `parking_lot` locks.
+The `resolve` arm of the recipes returns a `Result<Guard, failure::Error>`, which is where a
+poisoned lock is converted into an error unless [`poison`](#poisoning) is given, and the macro
+evaluates to a `Result<Locks, failure::Error>`. No futures are involved and the locks are acquired
+in the same order as `locks!`.
+
+```
+use failure::format_err;
+use std::sync::{Mutex, MutexGuard};
+
+macro_rules! accounts {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { ACCOUNTS.lock().map_err(|_| format_err!("Lock poisoned")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: Mutex<i32> = Mutex::new(10);
+}
+
+fn main() {
+    let mut locks = lock_derive::locks_sync!(write: [accounts]).unwrap();
+    *locks.accounts += 1;
+    assert_eq!(11, *locks.accounts);
+}
+```
+
+# Mixing blocking locks
+
+The `blocking_read: [..]` and `blocking_write: [..]` sections of `locks!` take the recipes of
+`locks_sync!`, whose `resolve` arm returns a `Result`, so the state still behind a
+`parking_lot::RwLock` or a `std::sync::Mutex` is acquired in the same order as the futures locks
+while a codebase migrates. On a `tokio` threadpool, the lock is acquired inside
+`tokio_threadpool::blocking`, which hands the other tasks of the worker thread to another one
+while it waits. Elsewhere, such as on `current_thread`, the lock is acquired inline and blocks the
+executor until it is granted. The blocking locks cannot be keyed nor come from a lock source.
+
+The calling crate must depend on `tokio-threadpool`, and the struct must be `Send` to be spawned
+on the threadpool, which the guards of `parking_lot` are with its `send_guard` feature.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+macro_rules! legacy {
+    (ty read) => { parking_lot::RwLockReadGuard<'static, i32> };
+    (resolve read) => { Ok::<_, failure::Error>(LEGACY.read()) };
+    (traits $access:ident $struct:ty) => {};
+}
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+
+lazy_static::lazy_static! {
+    static ref LEGACY: parking_lot::RwLock<i32> = parking_lot::RwLock::new(1);
+#   static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+}
+
+fn main() {
+    let future = lock_derive::locks!(blocking_read: [legacy], write: [accounts]);
+    let mut locks = block_on_all(future).unwrap();
+    *locks.accounts += *locks.legacy;
+    assert_eq!(11, *locks.accounts);
+}
+```
+
+# Poisoning
+
+A `std::sync` lock is poisoned when a thread panics while holding it. Rather than having each
+recipe convert the poisoning, `poison: propagate`, `clear` or `ignore` takes the `resolve` arm of
+the blocking recipes of the invocation, those of `locks_sync!` or of `blocking_read: [..]` and
+`blocking_write: [..]`, as returning the `std::sync::LockResult` of the lock, and handles it.
+`propagate` fails the invocation with an error naming the lock, `clear` recovers the guard with
+`into_inner()` and carries on, and `ignore` leaves the poisoning unhandled, panicking as
+`lock().unwrap()` does.
+
+```
+use std::sync::{Mutex, MutexGuard};
+
+macro_rules! accounts {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { ACCOUNTS.lock() };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: Mutex<i32> = Mutex::new(10);
+}
+
+fn main() {
+    let _ = std::thread::spawn(|| {
+        let _accounts = ACCOUNTS.lock();
+        panic!("poisoning the accounts");
+    })
+    .join();
+
+    let e = lock_derive::locks_sync!(poison: propagate, write: [accounts]).err().unwrap();
+    assert_eq!("acquiring write lock `accounts`: lock poisoned", e.to_string());
+
+    let locks = lock_derive::locks_sync!(poison: clear, write: [accounts]).unwrap();
+    assert_eq!(10, *locks.accounts);
+}
+```
+
+# Resources
+
+Whatever their kind, the guards held by the struct can be walked with `resources()`, which
+returns them in acquisition order as a generated `Resource` enum with one variant per field.
+Generic cleanup or diagnostic code can rely on the `name()` and `access()` of each resource.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! users {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { USERS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref USERS: RwLock<i32> = RwLock::new(1);
+# }
+# fn main() {
+let locks = block_on_all(lock_derive::locks!(read: [accounts], write: [users])).unwrap();
+
+let held = locks
+    .resources()
+    .iter()
+    .map(|r| format!("{} ({})", r.name(), r.access()))
+    .collect::<Vec<_>>();
+
+assert_eq!(vec!["users (write)", "accounts (read)"], held);
+# }
+```
+
+# Type-erased access
+
+Starting the invocation with `erased;` adds `get::<T>(name)` to the struct, returning the value
+of the guard in the field `name` when it is a `T`, and implements the `ErasedLocks` trait of the
+[runtime](#runtime), so middlewares can inspect the guards held through a `&dyn ErasedLocks`
+without knowing the type of the struct. The values are dereferenced from their guards and must be
+`'static`; permits and batches of keys are left out.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+lock_derive::runtime!();
+
+fn audit(locks: &dyn ErasedLocks) -> Vec<String> {
+    locks
+        .names()
+        .iter()
+        .filter_map(|name| locks.get::<i32>(name).map(|v| format!("{} = {}", name, v)))
+        .collect()
+}
+
+fn main() {
+    let locks = block_on_all(lock_derive::locks!(erased; read: [accounts])).unwrap();
+    assert_eq!(Some(&10), locks.get::<i32>("accounts"));
+    assert_eq!(vec!["accounts = 10"], audit(&locks));
+}
+```
+
+# Debug
+
+The struct implements `Debug`, listing each lock with its access and its value when the value
+is `Debug`, `_` otherwise, so it can be dumped in logs and test failures.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty read) => { RwLockReadGuard<i32> };
+#     (resolve read) => { ACCOUNTS.read().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# macro_rules! pool {
+#     (ty write) => { RwLockWriteGuard<Pool> };
+#     (resolve write) => { POOL.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+struct Pool;
+
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+#     static ref POOL: RwLock<Pool> = RwLock::new(Pool);
+# }
+# fn main() {
+let locks = block_on_all(lock_derive::locks!(read: [accounts], write: [pool])).unwrap();
+assert_eq!("Locks { accounts: read 10, pool: write _ }", format!("{:?}", locks));
+# }
+```
+
+# Supervisor
+
+For operational emergencies, the `supervisor` feature lets a supervisor invalidate a lock wedged by
+a stuck task instead of restarting the whole service. The invocations starting with `supervised;`
+race each acquisition against the supervisor of the lock, given by the `(supervisor)` arm of its
+recipe: any value with the `is_invalidated()` and `grace_elapsed()` methods. The recipe also has a
+`force_resolve` arm for each access, taking the same arguments as its `resolve` arm, such as
+`(force_resolve read)` and `(force_resolve write)`, which takes the guard without waiting on the
+stuck lock, e.g. from a replacement lock. The other invocations are left as they are, so the recipes
+they use need neither arm.
+
+The supervisor is checked every 100 ms on the timer of the [runtime adapter](#runtime-adapters):
+once it has invalidated the lock and its grace period has elapsed, the guard comes from
+`force_resolve` and `lock_derive_runtime` logs an error on the `lock_derive::supervisor` target.
+The acquisition waiting on the stuck lock is then dropped while pending, so the recipes of locks
+whose waiters cannot drop out of their queue wrap it in `lock_derive_runtime::detach`, as for the
+[timeouts](#timeouts). The struct gets an `is_invalidated()` method so the current holders can
+notice the invalidation and bail out, and `lock_derive_runtime::Supervisor` keeps the state of a
+supervisor, invalidated with `accounts!(supervisor).invalidate(grace)`.
+
+The guard of a `futures_locks::RwLock` cannot be taken from its holder, so the recipes of
+`define_lock!` have neither arm and `supervised;` fails to compile on them.
+
+```compile_fail
+use futures_locks::RwLock;
+use lock_derive::define_lock;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+
+fn main() {
+    let _ = lock_derive::locks!(supervised; write: [accounts]);
+}
+```
+
+```ignore
+fn settle() -> impl Future<Item = (), Error = failure::Error> {
+    lock_derive::locks!(supervised; write: [accounts]).and_then(|mut locks| {
+        *locks.accounts -= 10;
+
+        // between two steps of the critical section.
+        if locks.is_invalidated() {
+            return Err(format_err!("`accounts` was invalidated"));
+        }
+
+        *locks.accounts += 10;
+        Ok(())
+    })
+}
+
+// from an operational endpoint, once the task holding `accounts` is found stuck.
+accounts!(supervisor).invalidate(Duration::from_secs(5));
+```
+
+# Re-entrancy
+
+Acquiring a lock already held by the same task waits forever on itself, typically from a nested
+helper call. With the `reentrancy` feature, the locks held by each task, or by each thread for
+`locks_sync!`, are registered until their struct is dropped, and acquiring one of them again
+panics with the name of the lock instead of hanging. The locks are known by the name of their
+recipe; keyed locks and batches of keys are not tracked, nor are the locks of `freeze_bytes`,
+`snapshot` and `versioned`, whose guards are released at once, and `into_parts()` ends the
+tracking of its guards. The locks of an `owned;` struct are not registered either, the struct
+being free to move to another task; their acquisition is still checked against the locks held by
+the task polling it.
+
+Each invocation acquires its locks in a global order, which nested invocations break when the
+inner one takes a lock sorting after one held by the outer one: another task taking both in
+the same invocation acquires them the other way around. In debug builds, the same registry
+also panics on such a nested invocation, naming both locks, before it can deadlock.
+
+The registry is kept by `lock_derive_runtime` and needs no [runtime](#runtime) in the crate. It
+is shared by the crates of the program, the locks of each crate being told apart.
+
+# Re-entrant locks
+
+Helpers each declaring the locks they need deadlock when called by one already holding them.
+With the `unsafe reentrant: [..]` option of the `reentrancy` feature, a lock held by the same task,
+or by the same thread for `locks_sync!`, is not acquired again: the field borrows the value of the
+outer guard instead. A lock held for `write` is lent to `read` and `write`, a lock held for `read`
+only to `read`; otherwise, or when it is not held, the lock is acquired as usual.
+
+```ignore
+fn credit(amount: i32) -> impl Future<Item = (), Error = failure::Error> {
+    locks!(write: [accounts], unsafe reentrant: [accounts]).map(move |mut locks| {
+        *locks.accounts += amount;
+    })
+}
+
+locks!(write: [accounts]).and_then(|locks| credit(10).map(move |_| drop(locks)))
+```
+
+The field is a `held::Reentrant` of `lock_derive_runtime`, dereferencing to the value of the lock.
+The borrow is not checked by the compiler, so the section is written `unsafe reentrant: [..]`, the
+caller vouching that the outer struct outlives the inner one and is not used meanwhile. Only the
+`read` and `write` locks of a recipe, without a lock instance, a key or a projection, can be
+borrowed, and `reentrant` cannot be combined with `optimistic`, `transact` or `release`.
+
+# Runtime
+
+The state shared by the invocations, such as the turnstiles of `policy: write_preferring`, the
+tokens of [`intent`](#write-intent) and [`init`](#lazy-initialization), the waiters of
+[`condvar;`](#waiting-on-a-condition) and the locks of the [`LockManager`](#lock-manager), is kept
+by `lock_derive_runtime` for all the crates of the program, the locks being told apart by crate
+and by the full path of their recipe. `lock_derive::runtime!();` at the root
+of the crate imports the items of `lock_derive_runtime` used by the calling code: the
+`LockManager`, the [`LockSource`](#lock-sources) trait, the `Cancelled` error of
+[`cancel`](#cancellation), the `ErasedLocks` trait of [`erased;`](#type-erased-access),
+`set_hold_observer()` of [`report_hold`](#hold-durations) and `name_task()` of the
+[`owners`](#lock-owners) feature.
+
+# Without std
+
+Without the default `std` feature, the code generated by `locks!`, `locks_struct!` and
+`locks_sync!` only uses `core` and `alloc`, so it runs in `no_std` environments such as `embassy`
+with the `futures` 0.1 crate built without its default features. The calling crate depends on
+`lock_derive` and `lock_derive_runtime` without their default features, declares
+`extern crate alloc;` and gives the error of the chain to `lock_derive::runtime!(error: Type)` in
+place of `failure::Error`. The recipes return that error, and it is built from a `&'static str`
+for the errors of the chain itself, such as a key requested twice.
+
+```ignore
+extern crate alloc;
+
+pub struct LockError(&'static str);
+
+impl From<&'static str> for LockError {
+    fn from(message: &'static str) -> Self {
+        LockError(message)
+    }
+}
+
+lock_derive::runtime!(error: LockError);
+
+macro_rules! accounts {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => { ACCOUNTS.write().map_err(|_| LockError("accounts")) };
+    (traits $access:ident $struct:ty) => {};
+}
+```
+
+The recipes must return `futures` 0.1 futures, and what needs `std` to run is rejected: the
+`blocking`, `builder`, `condvar` and `policy` options, closures, timeouts, lock sources,
+`intent` and the blocking locks. The other features of the crate need `std` and turn it on.
+
+# Model checking
+
+When the program is built with `RUSTFLAGS="--cfg loom"`, `lock_derive_runtime` keeps the state of
+`runtime!` in `loom::lazy_static!` statics guarded by the `loom::sync` primitives, and the
+`reentrancy` feature tells the threads apart through `loom::thread`. Together with recipes resolving
+`loom::sync` locks, the acquisitions of `locks_sync!` can be model-checked by `loom::model`, which
+explores every interleaving of the threads and reports a deadlock if the order of the locks allows
+one. The futures of `locks!` are polled through `futures` 0.1 and are outside of the model.
+
+The runtime crate depends on `loom` under `cfg(loom)` only, so no feature is needed; the calling
+crate declares the same dependency for its tests, and the `cfg` to `check-cfg` in its lints.
+
+```ignore
+use loom::sync::{Mutex, MutexGuard};
+
+lock_derive::runtime!();
+
+macro_rules! accounts {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { ACCOUNTS.lock().map_err(|_| failure::err_msg("Lock poisoned")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+// `users` is defined the same way.
+loom::lazy_static! {
+    static ref ACCOUNTS: Mutex<i32> = Mutex::new(10);
+}
+
+#[test]
+fn transfer_never_deadlocks() {
+    loom::model(|| {
+        let t = loom::thread::spawn(|| {
+            *lock_derive::locks_sync!(write: [accounts, users]).unwrap().accounts += 1;
+        });
+
+        *lock_derive::locks_sync!(write: [users, accounts]).unwrap().users += 1;
+        t.join().unwrap();
+    });
+}
+```
+
+# Telemetry
+
+With the `telemetry` feature, a failure to resolve the locks emits a `log` event on the
+`lock_derive::telemetry` target. The event is labeled with the `locks` of the bundle and the
+`cause` of the failure: `timeout`, `cancellation`, `shutdown`, `overload` or `backend` when the
+error comes from a recipe. The cause is found by walking the error chain for the tokio timer and
+executor errors, `futures::Canceled`, `Cancelled` and the timed out or interrupted
+`std::io::Error`, which the timeouts of `@ <duration>` and `builder;` fail with.
+
+The event is logged by `lock_derive_runtime`, so the calling crate depends on neither `log` nor
+`tokio`; the tokio errors are only told apart with the default `tokio` feature of
+`lock_derive_runtime`.
+
+# Diagnostics
+
+With the `diagnostics` feature, a warning is logged on the `lock_derive::diagnostics` target when
+acquiring all the locks takes longer than 100ms, and another one when the struct is held longer
+than 1s before being dropped, naming the locks and the time taken. The thresholds are set per
+invocation with `diagnostics: [acquire @ 20ms, hold @ 500ms]`, which is ignored without the
+feature. The hold ends with the struct, `into_parts()` ends it early. The warnings are logged by
+`lock_derive_runtime`, so the calling crate does not depend on `log`.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+# fn main() {
+let future = lock_derive::locks!(diagnostics: [hold @ 500ms], write: [accounts]);
+*block_on_all(future).unwrap().accounts += 1;
+# }
+```
+
+# Audit log
+
+With the `audit` feature, each struct logs an event on the `lock_derive::audit` target when its
+locks are acquired and another one when it is dropped or taken apart by `into_parts()`. The
+events carry the call site of the invocation as `site`, the locks with their access as `locks`,
+such as `accounts:read,users:write`, and the holder as `owner`: the task polling the chain,
+numbered on its first acquisition, or the thread with `locks_sync!`.
+
+The events are logged by `lock_derive_runtime`, which numbers the tasks for all the crates, so
+the feature needs neither a dependency on `log` nor [`runtime!`](#runtime).
+
+# Lock owners
+
+With the `owners` feature, each struct registers its locks along with their holder, the call
+site of the invocation and the instant they were acquired, until it is dropped. When the
+[timeout](#timeouts) of a lock elapses, its error names the holder keeping it from being acquired,
+the one holding it the longest; when the timeout of the [builder](#builder) elapses, its error
+names the first lock of the invocation held that way, and its holder:
+
+```text
+resolving the locks timed out after 2s, waiting for `accounts` (write), held by task
+`billing-worker-3` acquired at src/billing.rs:120 for 4.2s
+```
+
+The holder is the task polling the chain, numbered on its first acquisition or named with
+`name_task()` of `lock_derive_runtime`, or the thread with `locks_sync!`. The locks are registered
+once all of them are acquired; borrowed, keyed and sourced locks and the replicas of a quorum are
+not tracked. Like the registry of the `reentrancy` feature, the holders are kept by
+`lock_derive_runtime` for all the crates, each naming only the holders of its own locks.
+
+```ignore
+let worker = futures::future::lazy(|| {
+    lock_derive_runtime::name_task("billing-worker-3");
+    lock_derive::locks!(write: [accounts @ 200ms])
+});
+```
+
+# Hold durations
+
+`report_hold: "label"` reports how long each struct of the invocation was held, from the
+acquisition of its locks to its drop, whatever the duration and without a feature. The report
+goes to the observer given to `set_hold_observer()` of the [runtime](#runtime) with the label of
+the call site and the duration, or to `log` on the `lock_derive::hold` target from
+`lock_derive_runtime` when there is none. The observer is the one of all the crates of the
+program. As with the `diagnostics` feature, `into_parts()` ends the hold early. The option is
+also taken by `locks_sync!`, but not with `tuple;`.
+
+```
+# use failure::format_err;
+# use futures_locks::{RwLock, RwLockWriteGuard};
+# use tokio::executor::current_thread::block_on_all;
+# macro_rules! accounts {
+#     (ty write) => { RwLockWriteGuard<i32> };
+#     (resolve write) => { ACCOUNTS.write().map_err(|_| format_err!("Lock error")) };
+#     (traits $access:ident $struct:ty) => {};
+# }
+# lazy_static::lazy_static! {
+#     static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+# }
+lock_derive::runtime!();
+
+# fn main() {
+set_hold_observer(|label, held| println!("{} held for {:?}", label, held));
+
+let future = lock_derive::locks!(report_hold: "accounts::credit", write: [accounts]);
+*block_on_all(future).unwrap().accounts += 1;
+# }
+```
+
+# Lock graph
+
+With the `graph` feature, each invocation of `locks!`, `locks_struct!` and `locks_sync!` registers
+its call site and its locks, in acquisition order, with `inventory`. The `lock_sites()`,
+`lock_graph_dot()` and `lock_graph_json()` functions of `lock_derive_runtime`, also imported by
+[`runtime!`](#runtime), render all the sites of the program, with an edge going from each lock to
+the one acquired while holding it. The graph is built at compile time, so it covers the code paths
+that never run in the tests.
+
+The sites are registered through `lock_derive_runtime`, so the calling crate turns on its `graph`
+feature rather than depending on `inventory`; an invocation fails to compile without it.
+
+```ignore
+fn main() {
+    std::fs::write("locks.dot", lock_derive_runtime::lock_graph_dot()).unwrap();
+}
+```
+
+# Recording acquisitions
+
+With the `test-util` feature, typically enabled for the tests only, the `Recorder` of
+`lock_derive_runtime` records the locks acquired by the futures it wraps with `record()`, or by the
+closures it runs with `scope()` for `locks_sync!`. Each acquisition gives the identity of the lock,
+its access, when its guard was resolved and when its struct was dropped. `assert_order()` panics
+unless the locks given were first acquired in that order and `held_together()` tells whether two
+locks were held at the same time, so a test can pin down the order of a set of locks. The feature
+needs no [runtime](#runtime) in the crate.
+
+```ignore
+use lock_derive_runtime::Recorder;
+
+let recorder = Recorder::new();
+let future = locks!(read: [users], write: [accounts]).map(drop);
+
+block_on_all(recorder.record(future)).unwrap();
+recorder.assert_order(&["users", "accounts"]);
+assert!(recorder.held_together("users", "accounts"));
+```
+!*/
+
+#![recursion_limit = "256"]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+
+mod args;
+mod assert;
+#[cfg(feature = "audit")]
+mod audit;
+mod blocking;
+mod bridge;
+mod builder;
+mod cancel;
+mod condvar;
+mod debug;
+mod define;
+mod delegate;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod erased;
+mod escalate;
+#[cfg(feature = "graph")]
+mod graph;
+mod group;
+mod hierarchy;
+mod hold;
+mod include;
+mod init;
+mod intent;
+mod lock_set;
+mod locks_for;
+mod locks_struct;
+mod machine;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod manager;
+mod merge;
+mod more;
+#[cfg(not(feature = "std"))]
+mod no_std;
+mod optimistic;
+mod order;
+#[cfg(feature = "owners")]
+mod owners;
+mod parts;
+mod poison;
+mod policy;
+mod quorum;
+#[cfg(feature = "test-util")]
+mod recorder;
+#[cfg(feature = "reentrancy")]
+mod reentrancy;
+mod release;
+mod resource;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod runtime;
+mod source;
+#[cfg(feature = "supervisor")]
+mod supervisor;
+mod sync;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+mod timeout;
+mod transact;
+mod tuple;
+mod unlock;
+mod upgrade;
+mod versioned;
+mod with_locks;
+mod yielding;
+
+use args::{Access, Args, Item};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use syn::{parse_macro_input, parse_quote, Ident, Lifetime};
+
+#[proc_macro]
+pub fn locks(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(item as Args);
+
+    include::write_include(&args, quote! { lock_derive::locks })
+        .unwrap_or_else(|| write_all(&args))
+        .into()
+}
+
+/// Defines the struct of an invocation at module level under the name given, see the
+/// [crate documentation](index.html#named-structs).
+#[proc_macro]
+pub fn locks_struct(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let def = parse_macro_input!(item as locks_struct::Definition);
+    locks_struct::write_locks_struct(&def).into()
+}
+
+/// A blocking variant of `locks!` for `std::sync` or `parking_lot` locks, see the
+/// [crate documentation](index.html#blocking-locks).
+#[proc_macro]
+pub fn locks_sync(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(item as Args);
+
+    include::write_include(&args, quote! { lock_derive::locks_sync })
+        .unwrap_or_else(|| sync::write_all(&args))
+        .into()
+}
+
+/// Chooses the locks to acquire with a match, resolving to an enum of the guards of each arm,
+/// see the [crate documentation](index.html#choosing-the-locks).
+#[proc_macro]
+pub fn locks_for(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let locks_for = parse_macro_input!(item as locks_for::LocksFor);
+    locks_for::write_locks_for(&locks_for).into()
+}
+
+/// Acquires more locks while holding the struct of an earlier invocation, see the
+/// [crate documentation](index.html#acquiring-more-locks).
+#[proc_macro]
+pub fn acquire_more(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let more = parse_macro_input!(item as more::More);
+    more::write_more(&more).into()
+}
+
+/// Merges structs already held and new locks into a single struct, see the
+/// [crate documentation](index.html#merging-locks).
+#[proc_macro]
+pub fn merge_locks(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let merge = parse_macro_input!(item as merge::Merge);
+    merge::write_merge(&merge).into()
+}
+
+/// Acquires the locks at the start of an `async fn` and binds the guards to local names, see the
+/// [crate documentation](index.html#async-functions).
+#[proc_macro_attribute]
+pub fn with_locks(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let contract = parse_macro_input!(attr as with_locks::Contract);
+
+    with_locks::write_with_locks(&contract, item.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Acquires the locks in an `async` block and runs the body with the guards bound to local names,
+/// see the [crate documentation](index.html#async-functions).
+#[proc_macro]
+pub fn with_locks_block(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let block = parse_macro_input!(item as with_locks::Block);
+    with_locks::write_block(&block).into()
+}
+
+/// Defines the state shared by all the invocations of the crate, at the root of the crate, see
+/// the [crate documentation](index.html#runtime).
+#[cfg(feature = "std")]
+#[proc_macro]
+pub fn runtime(_: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    runtime::write_runtime().into()
+}
+
+/// Defines the error of the chain, `runtime!(error: Type)`, at the root of the crate, see the
+/// [crate documentation](index.html#without-std).
+#[cfg(not(feature = "std"))]
+#[proc_macro]
+pub fn runtime(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let runtime = parse_macro_input!(item as no_std::Runtime);
+    no_std::write_runtime(&runtime).into()
+}
+
+/// Defines `LockSet`, a builder of locks chosen at runtime among the given recipes, see the
+/// [crate documentation](index.html#dynamic-lock-sets).
+#[proc_macro]
+pub fn lock_set(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let registry = parse_macro_input!(item as lock_set::Registry);
+    lock_set::write_lock_set(&registry).into()
+}
+
+/// Registers the given recipes in the `LockManager` of the runtime, under the name of the
+/// recipe, see the [crate documentation](index.html#lock-manager).
+#[cfg(feature = "std")]
+#[proc_macro]
+pub fn register_locks(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let registry = parse_macro_input!(item as lock_set::Registry);
+    manager::write_register(&registry).into()
+}
+
+/// Generates the recipe macro of a `futures_locks::RwLock`, see the
+/// [crate documentation](index.html#defining-recipes).
+#[proc_macro]
+pub fn define_lock(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let def = parse_macro_input!(item as define::Definition);
+    define::write_definition(&def).into()
+}
+
+/// Declares a group of locks included with `group: name`, see the
+/// [crate documentation](index.html#lock-groups).
+#[proc_macro]
+pub fn lock_group(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let group = parse_macro_input!(item as group::Group);
+    group::write_group(&group).into()
+}
+
+/// Writes the recipe of a `static` holding a `RwLock<T>`, or of each static of a `lazy_static!`
+/// invocation, see the [crate documentation](index.html#recipes-of-statics).
+#[proc_macro_attribute]
+pub fn lock_recipe(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let attr = parse_macro_input!(attr as define::Attribute);
+
+    define::write_lock_recipe(&attr, item.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn lifetime() -> Lifetime {
+    Lifetime::new("'a", Span::call_site())
+}
+
+/// The error of the chain, the one given to `runtime!` without the `std` feature.
+fn error_ty() -> TokenStream {
+    if cfg!(not(feature = "std")) {
+        quote! { crate::__lock_derive_runtime::Error }
+    } else {
+        quote! { failure::Error }
+    }
+}
+
+/// An error of the chain built from a message, with `From<&'static str>` without the `std`
+/// feature.
+fn write_error(message: &str) -> TokenStream {
+    let error = error_ty();
+
+    if cfg!(not(feature = "std")) {
+        quote! { <#error>::from(#message) }
+    } else {
+        quote! { failure::err_msg(#message) }
+    }
+}
+
+/// The name of the generated struct, unique to the invocation so that two invocations never
+/// define the same type in a scope. It is derived from the tokens of the invocation, so the
+/// expansion stays deterministic.
+fn struct_ident(args: &Args) -> Ident {
+    if let Some(ident) = &args.ident {
+        return ident.clone();
+    }
+
+    let mut hasher = DefaultHasher::new();
+    args.tokens.to_string().hash(&mut hasher);
+    Ident::new(
+        &format!("Locks{:08x}", hasher.finish() as u32),
+        Span::call_site(),
+    )
+}
+
+/// The visibility of the fields and the methods of the generated struct, public when it is
+/// defined by `locks_struct!` so they can be reached from outside of its module.
+fn struct_vis(args: &Args) -> TokenStream {
+    if args.ident.is_some() {
+        quote! { pub }
+    } else {
+        quote! {}
+    }
+}
+
+/// The type of the generated struct, as seen from the recipes `traits` arm.
+fn struct_ty(args: &Args) -> TokenStream {
+    let ident = struct_ident(args);
+
+    if args.has_lifetime() {
+        quote! { #ident<'_> }
+    } else {
+        quote! { #ident }
+    }
+}
+
+/// The hidden fields of the struct, given when it is built. `sync` tells whether the struct is
+/// built by `locks_sync!`.
+fn write_markers(args: &Args, sync: bool) -> TokenStream {
+    let lifetime = if args.has_lifetime() {
+        quote! { __lifetime: core::marker::PhantomData, }
+    } else {
+        quote! {}
+    };
+
+    #[cfg(feature = "reentrancy")]
+    let lifetime = {
+        let held = reentrancy::write_enter(args, sync);
+        quote! { #lifetime #held }
+    };
+
+    #[cfg(not(feature = "reentrancy"))]
+    let _ = sync;
+
+    let lifetime = {
+        let timer = hold::write_hold_timer(args);
+        quote! { #lifetime #timer }
+    };
+
+    let lifetime = match versioned::write_written(args) {
+        Some(written) => quote! { #lifetime __written: #written, },
+        None => lifetime,
+    };
+
+    #[cfg(feature = "audit")]
+    let lifetime = {
+        let audit = audit::write_audit(args, sync);
+        quote! { #lifetime #audit }
+    };
+
+    #[cfg(feature = "owners")]
+    let lifetime = {
+        let owners = owners::write_owners(args, sync);
+        quote! { #lifetime #owners }
+    };
+
+    #[cfg(feature = "diagnostics")]
+    let lifetime = {
+        let hold = diagnostics::write_hold(args);
+        quote! { #lifetime #hold }
+    };
+
+    #[cfg(feature = "test-util")]
+    let lifetime = {
+        let hold = recorder::write_hold(args);
+        quote! { #lifetime #hold }
+    };
+
+    lifetime
+}
+
+/// The generics of the `impl` block of the generated struct, and the struct type.
+fn struct_generics(args: &Args) -> (TokenStream, TokenStream) {
+    let ident = struct_ident(args);
+
+    if args.has_lifetime() {
+        let lifetime = lifetime();
+        (quote! { <#lifetime> }, quote! { #ident<#lifetime> })
+    } else {
+        (quote! {}, quote! { #ident })
+    }
+}
+
+fn write_resolve(args: &Args) -> TokenStream {
+    let fields = args.items.iter().enumerate().map(|(i, t)| {
+        let field = &t.field;
+        let v = value_ident(i);
+        quote! { #field: #v }
+    });
+
+    let marker = write_markers(args, false);
+
+    let ident = struct_ident(args);
+    let mut inner_code = Some(if args.tuple {
+        let tuple = tuple::write_tuple(args);
+        quote! { Ok(#tuple) }
+    } else {
+        quote! { Ok(#ident { #marker #(#fields,)* }) }
+    });
+
+    // owned locks move their instances and keys along the chain so the future is `'static`.
+    let capture = if args.owned {
+        quote! { move }
+    } else {
+        quote! {}
+    };
+
+    // with timeouts, `__since` is carried along the chain from the first lock acquired.
+    let timed = args.items.iter().any(|t| t.timeout.is_some());
+    let steps = args.steps();
+    let last = steps.len().saturating_sub(1);
+
+    let stages = steps.into_iter().enumerate().map(|(n, step)| {
+        if step.len() == 1 && !step[0].1.quorum {
+            let (i, t) = step[0];
+            let resolve = timeout::write_timeout(t, write_item_resolve(t), n != last);
+            let v = value_ident(i);
+
+            (resolve, quote! { #v })
+        } else {
+            let resolve = match &args.quorum {
+                Some((threshold, deadline)) if step[0].1.quorum => {
+                    quorum::write_quorum(&step, *threshold, deadline)
+                }
+                _ => write_keyed_resolve(&step),
+            };
+            let vs = step.iter().map(|t| value_ident(t.0));
+
+            (resolve, quote! { (#(#vs,)*) })
+        }
+    });
+
+    // the chain is built from its last step, acquired first.
+    let mut stages = stages.collect::<Vec<_>>();
+    stages.reverse();
+
+    if !timed && stages.len() >= machine::MACHINE_STEPS {
+        let done = inner_code.take().expect("inner_code");
+        return machine::write_machine(stages, done, &capture);
+    }
+
+    for (n, (resolve, pattern)) in stages.into_iter().rev().enumerate() {
+        let code = inner_code.take().expect("inner_code");
+
+        inner_code = Some(match (timed, n == last) {
+            (false, _) => quote! { #resolve.and_then(#capture |#pattern| #code) },
+            (true, true) => quote! {
+                #resolve
+                    .map(|__guard| (__guard, std::time::Instant::now()))
+                    .and_then(#capture |(#pattern, __since)| #code)
+            },
+            (true, false) => quote! {
+                #resolve
+                    .map(move |__guard| (__guard, __since))
+                    .and_then(#capture |(#pattern, __since)| #code)
+            },
+        });
+    }
+
+    inner_code.expect("inner_code")
+}
+
+fn value_ident(i: usize) -> Ident {
+    Ident::new(&format!("__v{}", i), Span::call_site())
+}
+
+fn write_item_resolve(item: &Item) -> TokenStream {
+    if item.keys.is_some() {
+        return write_many_resolve(item);
+    }
+
+    #[cfg(feature = "reentrancy")]
+    {
+        if item.reentrant {
+            return reentrancy::write_reentrant(item, write_supervised_resolve(item), false);
+        }
+    }
+
+    write_supervised_resolve(item)
+}
+
+/// Expands the `resolve` arm of the recipe, watched by the supervisor of the lock for
+/// `supervised;`, after the intent of the lock for `intent: [..]`.
+fn write_supervised_resolve(item: &Item) -> TokenStream {
+    let resolve = write_recipe_resolve(item);
+
+    // a lock source has no recipe to give its supervisor.
+    #[cfg(feature = "supervisor")]
+    let resolve = match item.source {
+        None if item.supervised => supervisor::write_supervised(item, resolve),
+        _ => resolve,
+    };
+
+    let resolve = if item.write_preferring {
+        policy::write_preferring(item, resolve)
+    } else {
+        resolve
+    };
+
+    match item.access {
+        Access::Intent => intent::write_intent(item, resolve),
+        Access::Init => init::write_init(item, resolve),
+        _ if item.upgraded => intent::write_upgraded(resolve),
+        _ => resolve,
+    }
+}
+
+/// Expands the `resolve` arm of the recipe for a single lock. The error of the recipe is given
+/// the context of the lock and its access.
+fn write_recipe_resolve(item: &Item) -> TokenStream {
+    write_recipe_context(item, write_recipe_future(item, quote! { resolve }))
+}
+
+/// Expands the `resolve` arm of the recipe for a single lock of `locks_sync!`, returning a
+/// `Result`.
+fn write_recipe_result(item: &Item) -> TokenStream {
+    let resolve = poison::write_poison(item, write_recipe_call(item, quote! { resolve }));
+    write_recipe_context(item, write_recipe_guard(item, resolve))
+}
+
+fn write_recipe_context(item: &Item, resolve: TokenStream) -> TokenStream {
+    let context = format!("acquiring {} lock `{}`", item.access_name(), item.id);
+    write_context(&context, resolve)
+}
+
+/// Gives the context to the error of a recipe arm.
+fn write_context(context: &str, resolve: TokenStream) -> TokenStream {
+    // the errors of the recipes are the error of the runtime, without a context to give.
+    if cfg!(not(feature = "std")) {
+        return resolve;
+    }
+
+    quote! {
+        #resolve.map_err(|e: failure::Error| {
+            let context = format!("{}: {}", #context, e);
+            failure::Error::from(e.context(context))
+        })
+    }
+}
+
+/// Expands a resolving arm of the recipe returning a future, `resolve` or `force_resolve`, for a
+/// single lock. The future may be an `async` block.
+fn write_recipe_future(item: &Item, arm: TokenStream) -> TokenStream {
+    let resolve = match &item.source {
+        Some(source) => source::write_source_call(item, source, false),
+        None if item.access.is_blocking() => {
+            blocking::write_blocking(poison::write_poison(item, write_recipe_call(item, arm)))
+        }
+        None if item.infallible => bridge::write_infallible(write_recipe_call(item, arm)),
+        None => bridge::write_bridged(write_recipe_call(item, arm)),
+    };
+
+    write_recipe_guard(item, resolve)
+}
+
+/// Expands a resolving arm of the recipe not returning a future, `try_resolve` or the
+/// `resolve` arm of `locks_sync!`, for a single lock.
+fn write_recipe_arm(item: &Item, arm: TokenStream) -> TokenStream {
+    let resolve = write_recipe_call(item, arm);
+    write_recipe_guard(item, resolve)
+}
+
+fn write_recipe_call(item: &Item, arm: TokenStream) -> TokenStream {
+    let name = &item.name;
+    let path = &item.path;
+    let generics = &item.generics;
+    let access = item.access.resolve_ident();
+    let lock = &item.lock;
+    let key = item.key.as_ref().map(|key| quote! { [#key] });
+    let count = &item.count;
+    let owned = match item.lock {
+        Some(_) if item.owned => Some(quote! { owned }),
+        _ => None,
+    };
+
+    let arm = respan(quote! { #arm #owned #access }, name.span());
+    quote! { #path!(#arm #generics #key #lock #count) }
+}
+
+/// Maps the guard resolved by the recipe to the field of the struct.
+fn write_recipe_guard(item: &Item, resolve: TokenStream) -> TokenStream {
+    #[cfg(feature = "test-util")]
+    let resolve = recorder::write_recorded(item, resolve);
+
+    match item.access {
+        // the write guard is dropped as soon as the readable bytes are split off.
+        Access::FreezeBytes => quote! {
+            #resolve.map(|mut __guard| {
+                let __len = __guard.len();
+                __guard.split_to(__len).freeze()
+            })
+        },
+        // the read guard is dropped as soon as the `Arc` is cloned.
+        Access::Snapshot => quote! {
+            #resolve.map(|__guard| (*__guard).clone())
+        },
+        // the version is read under the read guard, no write being made in between.
+        Access::Versioned => versioned::write_versioned_value(item, resolve),
+        _ => match &item.projection {
+            Some(projection) => {
+                let members = &projection.members;
+                let ty = &projection.ty;
+
+                quote! {
+                    #resolve.map(|__guard| __Projection::<_, #ty> {
+                        guard: __guard,
+                        get: |__t| &__t #(.#members)*,
+                        get_mut: |__t| &mut __t #(.#members)*,
+                    })
+                }
+            }
+            None => resolve,
+        },
+    }
+}
+
+fn write_item_ty(item: &Item) -> TokenStream {
+    if item.upgraded {
+        let write = write_item_ty(&Item {
+            upgraded: false,
+            ..item.clone()
+        });
+
+        return quote! { lock_derive_runtime::Upgraded<#write> };
+    }
+
+    match item.access {
+        Access::FreezeBytes => return quote! { bytes::Bytes },
+        Access::Snapshot => {
+            let read = write_item_ty(&Item {
+                access: Access::Read,
+                ..item.clone()
+            });
+
+            return quote! { <#read as core::ops::Deref>::Target };
+        }
+        Access::Versioned => {
+            let read = write_item_ty(&Item {
+                access: Access::Read,
+                ..item.clone()
+            });
+
+            return quote! { lock_derive_runtime::Versioned<<#read as core::ops::Deref>::Target> };
+        }
+        Access::Intent => {
+            let read = write_item_ty(&Item {
+                access: Access::Read,
+                ..item.clone()
+            });
+
+            return quote! { lock_derive_runtime::Intent<#read> };
+        }
+        _ => {}
+    }
+
+    let name = &item.name;
+    let path = &item.path;
+    let generics = &item.generics;
+    let access = item.access.guard_ident();
+
+    let ty = if let Some(source) = &item.source {
+        source::write_source_ty(item, source)
+    } else if item.lock.is_some() && item.owned {
+        let arm = respan(quote! { ty owned #access }, name.span());
+        quote! { #path!(#arm #generics) }
+    } else {
+        let arm = respan(quote! { ty #access }, name.span());
+        let lifetime = item.lock.as_ref().map(|_| lifetime());
+        quote! { #path!(#arm #generics #lifetime) }
+    };
+
+    match &item.projection {
+        Some(projection) => {
+            let projected = &projection.ty;
+            quote! { __Projection<#ty, #projected> }
+        }
+        None => ty,
+    }
+}
+
+/// Gives the arm of a recipe invocation the span of the lock, so a missing recipe or arm is
+/// reported on the lock rather than on the whole invocation.
+fn respan(arm: TokenStream, span: Span) -> TokenStream {
+    arm.into_iter()
+        .map(|mut t| {
+            t.set_span(span);
+            t
+        })
+        .collect()
+}
+
+/// The type of the field holding the guard(s) of an item.
+fn write_field_ty(item: &Item) -> TokenStream {
+    let ty = write_item_ty(item);
+
+    // the guard borrowed from an outer invocation or acquired.
+    let ty = if item.reentrant {
+        quote! { lock_derive_runtime::held::Reentrant<#ty> }
+    } else {
+        ty
+    };
+
+    match item.keys {
+        Some(_) => quote! { Vec<#ty> },
+        None if item.quorum => quote! { Option<#ty> },
+        None => ty,
+    }
+}
+
+/// Resolves a batch of keys of the same lock. The keys are sorted and deduplicated at runtime,
+/// then the locks are acquired in that order. The future yields the guards in a `Vec`, in the
+/// order of the sorted keys.
+fn write_many_resolve(item: &Item) -> TokenStream {
+    let keys = &item.keys;
+    let error = error_ty();
+    let lock = item
+        .lock
+        .as_ref()
+        .map(|lock| quote! { let __lock = #lock; });
+    let resolve = write_recipe_resolve(&Item {
+        key: Some(parse_quote! { __key }),
+        lock: item.lock.as_ref().map(|_| parse_quote! { __lock }),
+        keys: None,
+        ..item.clone()
+    });
+
+    quote! {{
+        use futures::Stream;
+
+        #lock
+        let mut __keys = (#keys).into_iter().collect::<Vec<_>>();
+        __keys.sort();
+        __keys.dedup();
+
+        futures::stream::iter_ok::<_, #error>(__keys)
+            .and_then(move |__key| #resolve)
+            .collect()
+    }}
+}
+
+fn keyed_idents(prefix: &str, len: usize) -> Vec<Ident> {
+    (0..len)
+        .map(|i| Ident::new(&format!("{}{}", prefix, i), Span::call_site()))
+        .collect()
+}
+
+/// Binds the keys, `__k0..`, and the lock instances, `__l0..`, of a step sharing the same lock,
+/// along with the error given when two of them are the same. The borrowed instances of the same
+/// lock without key are keyed by their address.
+fn write_keyed_bindings(step: &[(usize, &Item)]) -> (TokenStream, String) {
+    let name = &step[0].1.name;
+    let keys = keyed_idents("__k", step.len());
+    let locks = keyed_idents("__l", step.len());
+
+    if step[0].1.key.is_none() {
+        let instances = step.iter().map(|t| &t.1.lock);
+        let locks = &locks;
+
+        let bindings = quote! {
+            #(let #locks: &_ = #instances;)*
+            #(let #keys = #locks as *const _ as *const () as usize;)*
+        };
+
+        let message = format!(
+            "Lock `{}` requested more than once with the same instance.",
+            name
+        );
+        return (bindings, message);
+    }
+
+    let key_exprs = step.iter().map(|t| &t.1.key);
+    let lock_bindings = step
+        .iter()
+        .zip(&locks)
+        .filter_map(|(t, l)| t.1.lock.as_ref().map(|lock| quote! { let #l = #lock; }));
+
+    let bindings = quote! {
+        #(let #keys = #key_exprs;)*
+        #(#lock_bindings)*
+    };
+
+    let message = format!(
+        "Lock `{}` requested more than once with the same key.",
+        name
+    );
+    (bindings, message)
+}
+
+/// Resolves several keys of the same lock, or several instances of it. The keys are sorted at
+/// runtime by `lock_derive_runtime` and the locks are acquired in that order, one after the
+/// other. The future yields the guards as a tuple, in the same order as the items.
+fn write_keyed_resolve(step: &[(usize, &Item)]) -> TokenStream {
+    let (bindings, message) = write_keyed_bindings(step);
+    let message = write_error(&message);
+    let error = error_ty();
+    let lifetime = lifetime();
+    let borrowed = step.iter().any(|t| t.1.is_borrowed());
+
+    // the lifetime variant makes sure the lifetime is used, whatever the guard types are.
+    let (generics, slot_ty, marker) = if borrowed {
+        (
+            quote! { <#lifetime> },
+            quote! { __Slot<'f> },
+            quote! { __Lifetime(core::marker::PhantomData<&#lifetime ()>), },
+        )
+    } else {
+        (quote! {}, quote! { __Slot }, quote! {})
+    };
+
+    let marker_arm = if borrowed {
+        quote! { __Slot::__Lifetime(_) => {} }
+    } else {
+        quote! {}
+    };
+
+    let slots = (0..step.len())
+        .map(|i| Ident::new(&format!("S{}", i), Span::call_site()))
+        .collect::<Vec<_>>();
+
+    let keys = keyed_idents("__k", step.len());
+    let locks = keyed_idents("__l", step.len());
+    let indexes = 0..step.len();
+    let tys = step.iter().map(|t| write_item_ty(t.1));
+
+    let thunks = step.iter().zip(&slots).enumerate().map(|(i, (t, slot))| {
+        let (key, lock) = (&keys[i], &locks[i]);
+        let item = Item {
+            key: t.1.key.as_ref().map(|_| parse_quote! { #key }),
+            lock: t.1.lock.as_ref().map(|_| parse_quote! { #lock }),
+            ..t.1.clone()
+        };
+
+        let resolve = write_supervised_resolve(&item);
+
+        let thunk = quote! { move || Box::new(#resolve.map(__Slot::#slot)) as __Fut<'_> };
+
+        quote! { Some(Box::new(#thunk) as __Thunk<'_>) }
+    });
+
+    let values = (0..step.len()).map(value_ident).collect::<Vec<_>>();
+    let (keys, slots, values) = (&keys, &slots, &values);
+
+    quote! {{
+        use futures::Stream;
+
+        enum __Slot #generics {
+            #(#slots(#tys),)*
+            #marker
+        }
+
+        type __Fut<'f> = Box<dyn futures::Future<Item = #slot_ty, Error = #error> + Send + 'f>;
+        type __Thunk<'f> = Box<dyn FnOnce() -> __Fut<'f> + Send + 'f>;
+
+        #bindings
+
+        let mut __order = [#(#indexes,)*];
+        let __ordered = lock_derive_runtime::keyed::order(&[#(&#keys,)*], &mut __order);
+        let mut __thunks: Vec<Option<__Thunk<'_>>> = vec![#(#thunks,)*];
+
+        futures::future::result(if __ordered { Ok(__order) } else { Err(#message) })
+        .and_then(move |__order| {
+            let __thunks = __order
+                .iter()
+                .map(|i| __thunks[*i].take().expect("thunk"))
+                .collect::<Vec<_>>();
+
+            futures::stream::iter_ok::<_, #error>(__thunks)
+                .and_then(|thunk| thunk())
+                .collect()
+        })
+        .map(|__slots| {
+            #(let mut #values = None;)*
+
+            for __slot in __slots {
+                match __slot {
+                    #(__Slot::#slots(v) => #values = Some(v),)*
+                    #marker_arm
+                }
+            }
+
+            (#(#values.expect("slot"),)*)
+        })
+    }}
+}
+
+fn write_struct(args: &Args) -> TokenStream {
+    let ident = struct_ident(args);
+    let vis = struct_vis(args);
+    let lifetime = lifetime();
+
+    // the fields are dropped in the order of their declaration, so the guards are declared in
+    // the order of their release, before the markers reporting it. The versions are bumped
+    // first, while the write guards are still held.
+    let written = if versioned::has_written(args) {
+        Some(quote! { __written: lock_derive_runtime::Written })
+    } else {
+        None
+    };
+
+    let fields = written
+        .into_iter()
+        .chain(args.release_order().into_iter().map(|t| {
+            let field = &t.field;
+            let ty = write_field_ty(t);
+
+            quote! { #vis #field: #ty }
+        }));
+
+    let projection = if args.items.iter().any(|t| t.projection.is_some()) {
+        write_projection()
+    } else {
+        quote! {}
+    };
+
+    let fields = fields.chain(
+        args.report_hold
+            .as_ref()
+            .map(|_| quote! { __hold_timer: lock_derive_runtime::hold::Timer }),
+    );
+
+    #[cfg(feature = "reentrancy")]
+    let fields = fields.chain(Some(quote! { __held: lock_derive_runtime::held::Held }));
+
+    #[cfg(feature = "test-util")]
+    let fields = fields.chain(Some(
+        quote! { __recorded: lock_derive_runtime::recorder::Hold },
+    ));
+
+    #[cfg(feature = "audit")]
+    let fields = fields.chain(Some(quote! { __audit: lock_derive_runtime::audit::Audit }));
+
+    #[cfg(feature = "owners")]
+    let fields = fields.chain(Some(
+        quote! { __owners: lock_derive_runtime::owners::Owned },
+    ));
+
+    #[cfg(feature = "diagnostics")]
+    let fields = fields.chain(Some(
+        quote! { __hold: lock_derive_runtime::diagnostics::Hold },
+    ));
+
+    #[cfg(not(feature = "std"))]
+    let projection = {
+        let prelude = no_std::write_prelude();
+        quote! { #prelude #projection }
+    };
+
+    // the struct resolved by `upgrade_<field>()` shares the helpers of the struct upgraded.
+    let projection = match args.upgraded {
+        Some(_) => quote! {},
+        None => projection,
+    };
+
+    if args.has_lifetime() {
+        quote! {
+            #projection
+
+            #vis struct #ident<#lifetime> {
+                #(#fields,)*
+                __lifetime: core::marker::PhantomData<&#lifetime ()>,
+            }
+        }
+    } else {
+        quote! {
+            #projection
+
+            #vis struct #ident {
+                #(#fields,)*
+            }
+        }
+    }
+}
+
+/// Brings `__Projection` of the runtime crate into scope, a guard dereferencing to one of the
+/// inner fields of its value.
+fn write_projection() -> TokenStream {
+    quote! {
+        use lock_derive_runtime::Projection as __Projection;
+    }
+}
+
+fn write_traits(args: &Args) -> TokenStream {
+    // the traits of `impl Trait;` are asked for by the invocation, not given by the recipes.
+    let delegates = delegate::write_delegates(args);
+
+    if args.no_traits {
+        return delegates;
+    }
+
+    let ty = struct_ty(args);
+
+    // the traits arm refers to the field by the lock name, which is not the case when a lock
+    // is keyed more than once, and expects the guard of the recipe, not a projection or the
+    // `Option` of a replica. A lock source has no recipe.
+    let items = args
+        .items
+        .iter()
+        .filter(|t| t.field == t.name && t.projection.is_none() && t.source.is_none() && !t.quorum);
+
+    let fields = items.map(|t| {
+        let path = &t.path;
+        let generics = &t.generics;
+        let ident = &t.access.guard_ident();
+        let arm = respan(quote! { traits #ident }, t.name.span());
+
+        quote! { #path!{ #arm #generics #ty } }
+    });
+
+    quote! {
+        #delegates
+        #(#fields)*
+    }
+}
+
+/// The methods generated on the struct, shared by `locks!` and `locks_sync!`.
+fn write_impls(args: &Args, sync: bool) -> TokenStream {
+    let resources = resource::write_resources(args);
+    let into_parts = parts::write_into_parts(args);
+    let unlock_all = unlock::write_unlock_all(args, sync);
+    let debug = debug::write_debug(args);
+    let order = order::write_order(args);
+
+    // the versions are kept by the runtime crate, which needs `std`.
+    let versioned = if cfg!(not(feature = "std")) {
+        quote! {}
+    } else {
+        versioned::write_versioned(args)
+    };
+
+    #[cfg(feature = "supervisor")]
+    let resources = if args.supervised {
+        let is_invalidated = supervisor::write_is_invalidated(args);
+        quote! { #resources #is_invalidated }
+    } else {
+        resources
+    };
+
+    let hierarchy = if args.hierarchy {
+        hierarchy::write_hierarchy(args)
+    } else {
+        quote! {}
+    };
+
+    let transact = if args.transact {
+        transact::write_transact(args)
+    } else {
+        quote! {}
+    };
+
+    let release = if args.release {
+        release::write_release(args)
+    } else {
+        quote! {}
+    };
+
+    let erased = if args.erased {
+        erased::write_erased(args)
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #resources
+        #into_parts
+        #unlock_all
+        #debug
+        #order
+        #hierarchy
+        #transact
+        #release
+        #erased
+        #versioned
+    }
+}
+
+/// Evaluates the expressions of the call site up front, so they can be moved along the chain.
+/// Returns the bindings and the items referring to them.
+fn write_owned_bindings(args: &Args) -> (TokenStream, Args) {
+    let mut bindings = Vec::new();
+    let mut args = args.clone();
+
+    for (i, item) in args.items.iter_mut().enumerate() {
+        let exprs = vec![
+            ("l", &mut item.lock),
+            ("k", &mut item.key),
+            ("ks", &mut item.keys),
+            ("c", &mut item.count),
+        ];
+
+        for (prefix, expr) in exprs {
+            if let Some(e) = expr.take() {
+                let ident = Ident::new(&format!("__o{}{}", prefix, i), Span::call_site());
+                bindings.push(quote! { let #ident = #e; });
+                *expr = Some(parse_quote! { #ident });
+            }
+        }
+    }
+
+    (quote! { #(#bindings)* }, args)
+}
+
+fn write_all(args: &Args) -> TokenStream {
+    #[cfg(not(feature = "std"))]
+    {
+        if let Some(error) = no_std::write_unsupported(args, false) {
+            return error;
+        }
+    }
+
+    if let Some(error) = poison::write_unused(args) {
+        return error;
+    }
+
+    let all = if args.owned {
+        let (bindings, args) = write_owned_bindings(args);
+        let all = write_all_inner(&args);
+        quote! {{ #bindings #all }}
+    } else {
+        write_all_inner(args)
+    };
+
+    let all = match &args.closure {
+        Some(closure) => write_scoped(all, closure),
+        None if args.infallible => write_infallible(all),
+        None => all,
+    };
+
+    let all = assert::write_assert(args, all);
+
+    #[cfg(feature = "graph")]
+    let all = {
+        let site = graph::write_site(args);
+        quote! {{ #site #all }}
+    };
+
+    all
+}
+
+/// Runs the closure with the locks, the guards are dropped as soon as its future completes. The
+/// closure borrows the struct, so neither it nor its guards can leave the scope.
+fn write_scoped(resolve: TokenStream, closure: &TokenStream) -> TokenStream {
+    let compat = with_locks::write_compat();
+    let error = error_ty();
+
+    quote! {{
+        #compat
+
+        // gives the type of the locks to the closure.
+        fn __scope<'l, L, F: FnOnce(&'l mut L) -> R, R>(locks: &'l mut L, f: F) -> R {
+            f(locks)
+        }
+
+        let __resolve = #resolve;
+
+        async move {
+            let mut __locks = __Compat(futures::executor::spawn(__resolve)).await?;
+            let __output = __scope(&mut __locks, #closure).await;
+            Ok::<_, #error>(__output)
+        }
+    }}
+}
+
+/// Awaits the chain of `infallible;` from a `std::future::Future` resolving to the struct
+/// itself. The recipes cannot fail and nothing else is left to, so an error would be a bug of
+/// the runtime.
+fn write_infallible(resolve: TokenStream) -> TokenStream {
+    let compat = with_locks::write_compat();
+
+    quote! {{
+        #compat
+
+        let __resolve = #resolve;
+
+        async move {
+            match __Compat(futures::executor::spawn(__resolve)).await {
+                Ok(locks) => locks,
+                Err(e) => panic!("infallible locks failed: {}", e),
+            }
+        }
+    }}
+}
+
+/// The chain resolving the locks to the struct.
+fn write_chain(args: &Args) -> TokenStream {
+    let resolve = write_resolve(args);
+
+    // trying the locks at once would let the readers barge in front of the waiting writers.
+    let resolve = if args.optimistic && !args.write_preferring {
+        optimistic::write_optimistic(args, resolve)
+    } else {
+        resolve
+    };
+
+    #[cfg(feature = "diagnostics")]
+    let resolve = diagnostics::write_slow(args, resolve);
+
+    #[cfg(feature = "reentrancy")]
+    let resolve = reentrancy::write_check(args, resolve);
+
+    #[cfg(feature = "telemetry")]
+    let resolve = telemetry::write_telemetry(args, resolve);
+
+    resolve
+}
+
+/// The struct and its impls, with `resolve()` running the chain.
+fn write_items(args: &Args, resolve: TokenStream) -> TokenStream {
+    let items = write_struct_items(args, resolve);
+    let bridge = bridge::write_bridge();
+
+    quote! {
+        #bridge
+        #items
+    }
+}
+
+/// The struct, its `resolve()` and its impls, without the bridge shared by the structs of the
+/// same scope.
+fn write_struct_items(args: &Args, resolve: TokenStream) -> TokenStream {
+    let ident = struct_ident(args);
+    let vis = struct_vis(args);
+    let error = error_ty();
+    let locks = write_struct(args);
+    let traits = write_traits(args);
+    let impls = write_impls(args, false);
+
+    // the blocking resolver waits on the same chain, so both acquire the locks in the same order.
+    let resolve_blocking = if args.blocking {
+        quote! {
+            #vis fn resolve_blocking() -> Result<Self, #error> {
+                use futures::Future;
+                Self::resolve().wait()
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let condvar = if args.condvar {
+        condvar::write_condvar(args)
+    } else {
+        quote! {}
+    };
+
+    let yield_locks = yielding::write_yield_locks(args);
+    let upgrades = upgrade::write_upgrades(args);
+    let escalate = escalate::write_escalate(args);
+
+    quote! {
+        #locks
+
+        impl #ident {
+            #vis fn resolve() -> impl futures::Future<Item = Self, Error = #error> {
+                use futures::Future;
+
+                #resolve
+            }
+
+            #resolve_blocking
+        }
+
+        #condvar
+        #yield_locks
+        #upgrades
+        #escalate
+        #impls
+        #traits
+    }
+}
+
+fn write_all_inner(args: &Args) -> TokenStream {
+    let ident = struct_ident(args);
+    let resolve = write_chain(args);
+    let adapter = runtime::write_adapter(&args.adapter);
+
+    // the timeout of `builder;` names the lock waited for and its holder.
+    #[cfg(feature = "owners")]
+    let blocked = owners::write_blocked(args);
+
+    #[cfg(not(feature = "owners"))]
+    let blocked = quote! {};
+
+    if args.tuple {
+        if let Some(error) = tuple::write_unsupported(args) {
+            return error;
+        }
+
+        let resolve = match &args.cancel {
+            Some(token) => cancel::write_cancel(token, resolve),
+            None => resolve,
+        };
+
+        let helpers = tuple::write_helpers(args);
+        let bridge = bridge::write_bridge();
+
+        return quote! {{
+            use futures::Future;
+
+            #helpers
+            #bridge
+
+            #resolve
+        }};
+    }
+
+    // borrowed and keyed locks are only reachable from the call site, so the chain is inlined
+    // there.
+    if args.is_inline() {
+        if args.condvar {
+            return syn::Error::new(
+                Span::call_site(),
+                "`condvar` resolves the locks again, which needs them all to be resolved from \
+                 their recipe, without a lock, a key or a count.",
+            )
+            .to_compile_error();
+        }
+
+        if args.items.iter().any(|t| t.upgradable) {
+            return syn::Error::new(
+                Span::call_site(),
+                "`upgrade` resolves the locks again, which needs them all to be resolved from \
+                 their recipe, without a lock, a key or a count.",
+            )
+            .to_compile_error();
+        }
+
+        if args.items.iter().any(|t| t.escalated) {
+            return syn::Error::new(
+                Span::call_site(),
+                "`escalate` resolves the locks again, which needs them all to be resolved from \
+                 their recipe, without a lock, a key or a count.",
+            )
+            .to_compile_error();
+        }
+
+        let wait = if args.blocking {
+            quote! { .wait() }
+        } else {
+            quote! {}
+        };
+
+        let (builder, resolve) = if args.builder {
+            (
+                builder::write_builder(),
+                quote! {
+                    LocksBuilder::<_, #adapter>::new(|| {
+                        use futures::Future;
+                        #resolve
+                    })
+                    #blocked
+                },
+            )
+        } else {
+            let resolve = match &args.cancel {
+                Some(token) => cancel::write_cancel(token, resolve),
+                None => resolve,
+            };
+
+            (
+                quote! {},
+                quote! {{
+                    use futures::Future;
+                    (#resolve)#wait
+                }},
+            )
+        };
+
+        let locks = write_struct(args);
+        let bridge = bridge::write_bridge();
+        let impls = write_impls(args, false);
+        let traits = write_traits(args);
+
+        return quote! {{
+            #locks
+            #bridge
+            #builder
+            #impls
+            #traits
+
+            #resolve
+        }};
+    }
+
+    let items = write_items(args, resolve);
+
+    let call = match (&args.cancel, args.blocking) {
+        (Some(token), blocking) => {
+            let cancel = cancel::write_cancel(token, quote! { #ident::resolve() });
+            let wait = if blocking {
+                quote! { .wait() }
+            } else {
+                quote! {}
+            };
+
+            quote! {{
+                use futures::Future;
+                (#cancel)#wait
+            }}
+        }
+        (None, true) => quote! { #ident::resolve_blocking() },
+        (None, false) => quote! { #ident::resolve() },
+    };
+
+    let (builder, call) = if args.builder {
+        (
+            builder::write_builder(),
+            quote! { LocksBuilder::<_, #adapter>::new(#ident::resolve)#blocked },
+        )
+    } else {
+        (quote! {}, call)
+    };
+
+    quote! {{
+        #items
+        #builder
 
-        Locks::resolve()
+        #call
     }}
 }
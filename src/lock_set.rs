@@ -0,0 +1,116 @@
+use crate::args::{Access, Item};
+use crate::write_item_resolve;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Error, Ident, Token};
+
+/// The recipes reachable from a `LockSet`, `read: [accounts, users], write: [users]`.
+pub struct Registry {
+    pub items: Vec<Item>,
+}
+
+impl Parse for Registry {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let mut items = Vec::new();
+
+        while !stream.is_empty() {
+            let section: Ident = stream.parse()?;
+            let _: Token![:] = stream.parse()?;
+
+            let access = match section.to_string().as_str() {
+                "read" => Access::Read,
+                "write" => Access::Write,
+                _ => return Err(Error::new(section.span(), "Expected `read` or `write`.")),
+            };
+
+            let content;
+            bracketed!(content in stream);
+
+            for name in <Punctuated<Ident, Token![,]>>::parse_terminated(&content)? {
+                items.push(Item {
+                    id: name.to_string(),
+                    field: name.clone(),
+                    path: name.clone().into(),
+                    generics: None,
+                    name,
+                    access,
+                    lock: None,
+                    key: None,
+                    keys: None,
+                    count: None,
+                    owned: false,
+                    source: None,
+                    timeout: None,
+                    projection: None,
+                    write_preferring: false,
+                    poison: None,
+                    infallible: false,
+                    adapter: None,
+                    index: items.len(),
+                    reentrant: false,
+                    upgradable: false,
+                    escalated: false,
+                    upgraded: false,
+                    quorum: false,
+                    supervised: false,
+                });
+            }
+
+            if !stream.is_empty() {
+                let _: Token![,] = stream.parse()?;
+            }
+        }
+
+        Ok(Self { items })
+    }
+}
+
+/// Writes `LockSet`, the builder of the runtime crate choosing the locks at runtime among the
+/// recipes of the registry, and `LockSetRegistry`, resolving them by name and access.
+pub fn write_lock_set(registry: &Registry) -> TokenStream {
+    let arms = registry.items.iter().map(|item| {
+        let name = item.name.to_string();
+        let access = item.access_name();
+        let resolve = write_item_resolve(item);
+
+        quote! {
+            (#name, #access) => Some(Box::new(
+                #resolve.map(|g| Box::new(g) as Box<dyn std::any::Any>),
+            )),
+        }
+    });
+
+    let bridge = crate::bridge::write_bridge();
+
+    quote! {
+        /// The recipes reachable from `LockSet`.
+        pub(crate) struct LockSetRegistry;
+
+        impl lock_derive_runtime::LockRegistry for LockSetRegistry {
+            fn resolve(
+                name: &'static str,
+                access: &'static str,
+            ) -> Option<
+                Box<dyn futures::Future<Item = Box<dyn std::any::Any>, Error = failure::Error>>,
+            > {
+                use futures::Future;
+
+                #bridge
+
+                match (name, access) {
+                    #(#arms)*
+                    _ => None,
+                }
+            }
+        }
+
+        /// A set of locks chosen at runtime, acquired in the same order as `locks!`.
+        #[allow(dead_code)]
+        pub(crate) type LockSet = lock_derive_runtime::LockSet<LockSetRegistry>;
+
+        #[allow(unused_imports)]
+        pub(crate) use lock_derive_runtime::LockSetGuards;
+    }
+}
@@ -0,0 +1,243 @@
+use crate::args::{parse_closure, Args};
+use crate::{error_ty, write_all, write_scoped};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{braced, Error, Expr, Ident, Pat, Token};
+
+/// The arguments of `locks_for!`, the name of the enum of the guards, `OpLocks;`, followed by
+/// the match choosing the locks, `match op { Op::Transfer => { write: [accounts] }, .. }`, and
+/// by the closure running with the enum, the only place where the enum can be named.
+pub struct LocksFor {
+    ident: Ident,
+    expr: Expr,
+    arms: Vec<Arm>,
+    closure: TokenStream,
+}
+
+/// `Op::Transfer if amount > 0 => { write: [accounts] }`, the variant being named after the last
+/// segment of the pattern.
+struct Arm {
+    pats: Punctuated<Pat, Token![|]>,
+    guard: Option<Expr>,
+    variant: Ident,
+    args: Args,
+}
+
+impl Parse for LocksFor {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let ident = stream.parse()?;
+        let _: Token![;] = stream.parse()?;
+        let _: Token![match] = stream.parse()?;
+
+        // the scrutinee ends at the braces of the arms, which would otherwise be taken for a
+        // struct literal.
+        let mut scrutinee = TokenStream::new();
+
+        while !stream.is_empty() && !is_arms(stream) {
+            scrutinee.extend(Some(stream.parse::<proc_macro2::TokenTree>()?));
+        }
+
+        let expr = syn::parse2(scrutinee)?;
+        let content;
+        braced!(content in stream);
+
+        let mut arms = Vec::<Arm>::new();
+
+        while !content.is_empty() {
+            let arm: Arm = content.parse()?;
+
+            if arms.iter().any(|t| t.variant == arm.variant) {
+                return Err(Error::new(
+                    arm.variant.span(),
+                    format!("The variant `{}` is already taken by an arm.", arm.variant),
+                ));
+            }
+
+            arms.push(arm);
+
+            if !content.is_empty() {
+                let _: Token![,] = content.parse()?;
+            }
+        }
+
+        if stream.is_empty() {
+            return Err(stream
+                .error("Expected `, |locks| ..`, the closure matching the enum of the guards."));
+        }
+
+        let _: Token![,] = stream.parse()?;
+        let closure = parse_closure(stream)?;
+
+        Ok(Self {
+            ident,
+            expr,
+            arms,
+            closure,
+        })
+    }
+}
+
+impl Parse for Arm {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let pats = Punctuated::parse_separated_nonempty(stream)?;
+
+        let guard = if stream.peek(Token![if]) {
+            let _: Token![if] = stream.parse()?;
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+
+        let _: Token![=>] = stream.parse()?;
+        let content;
+        braced!(content in stream);
+
+        let variant = variant(&pats)?;
+        let args: Args = content.parse()?;
+
+        if args.blocking
+            || args.builder
+            || args.infallible
+            || args.closure.is_some()
+            || !args.includes.is_empty()
+        {
+            return Err(Error::new(
+                variant.span(),
+                "The arms of `locks_for!` resolve to a variant of the enum, they cannot be \
+                 combined with `blocking`, `builder`, `infallible`, groups or a closure.",
+            ));
+        }
+
+        Ok(Self {
+            pats,
+            guard,
+            variant,
+            args,
+        })
+    }
+}
+
+/// Whether the stream is at the braces of the arms, the last tokens before the closure.
+fn is_arms(stream: ParseStream) -> bool {
+    let fork = stream.fork();
+    let arms = (|| -> Result<()> {
+        let _content;
+        braced!(_content in fork);
+        Ok(())
+    })();
+
+    arms.is_ok() && (fork.is_empty() || fork.peek(Token![,]))
+}
+
+/// The variant of an arm, named after the last segment of the path of its pattern, `Other` for
+/// `_`.
+fn variant(pats: &Punctuated<Pat, Token![|]>) -> Result<Ident> {
+    let path = match pats.iter().collect::<Vec<_>>().as_slice() {
+        [Pat::Path(pat)] => Some(&pat.path),
+        [Pat::TupleStruct(pat)] => Some(&pat.path),
+        [Pat::Struct(pat)] => Some(&pat.path),
+        [Pat::Wild(_)] => return Ok(Ident::new("Other", Span::call_site())),
+        _ => None,
+    };
+
+    path.and_then(|path| path.segments.last())
+        .map(|segment| segment.value().ident.clone())
+        .ok_or_else(|| {
+            Error::new(
+                Span::call_site(),
+                "Expected the pattern of a variant, `Op::Transfer`, naming the variant of the \
+                 guards, or `_`.",
+            )
+        })
+}
+
+/// Resolves the locks of the arm matching the scrutinee to the variant of the same name. The
+/// enum is generic over the structs of the arms, which are only reached from their arm, and
+/// the future of each arm is kept in a variant of its own, so nothing is boxed.
+pub fn write_locks_for(locks_for: &LocksFor) -> TokenStream {
+    let LocksFor {
+        ident,
+        expr,
+        arms,
+        closure,
+    } = locks_for;
+
+    if cfg!(not(feature = "std")) {
+        return Error::new(
+            Span::call_site(),
+            "`locks_for!` is not supported without the `std` feature, its closure needs `std` to \
+             wait on the locks.",
+        )
+        .to_compile_error();
+    }
+
+    let error = error_ty();
+    let variants = arms.iter().map(|t| &t.variant).collect::<Vec<_>>();
+    let tys = (0..arms.len())
+        .map(|n| Ident::new(&format!("T{}", n), Span::call_site()))
+        .collect::<Vec<_>>();
+
+    let paths = variants.iter().map(|variant| quote! { #ident::#variant });
+    let bounds = tys
+        .iter()
+        .map(|ty| quote! { #ty: futures::Future<Error = #error> });
+
+    let (variants, tys) = (&variants, &tys);
+
+    let matches = arms.iter().map(|arm| {
+        let Arm {
+            pats,
+            guard,
+            variant,
+            args,
+        } = arm;
+
+        let guard = guard.as_ref().map(|guard| quote! { if #guard });
+        let resolve = write_all(args);
+
+        quote! {
+            #pats #guard => __LocksFor::#variant(#resolve),
+        }
+    });
+
+    // the enum is defined next to the closure, so the closure can name it.
+    let scoped = write_scoped(
+        quote! {
+            match #expr {
+                #(#matches)*
+            }
+        },
+        closure,
+    );
+
+    quote! {{
+        use futures::Future;
+
+        #[allow(dead_code)]
+        enum #ident<#(#tys),*> {
+            #(#variants(#tys),)*
+        }
+
+        enum __LocksFor<#(#tys),*> {
+            #(#variants(#tys),)*
+        }
+
+        impl<#(#tys),*> futures::Future for __LocksFor<#(#tys),*>
+        where
+            #(#bounds,)*
+        {
+            type Item = #ident<#(#tys::Item),*>;
+            type Error = #error;
+
+            fn poll(&mut self) -> futures::Poll<Self::Item, #error> {
+                Ok(match self {
+                    #(__LocksFor::#variants(f) => f.poll()?.map(#paths),)*
+                })
+            }
+        }
+
+        #scoped
+    }}
+}
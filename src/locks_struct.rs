@@ -0,0 +1,113 @@
+use crate::args::Args;
+use crate::{write_chain, write_items};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{Error, Ident, Token, Visibility};
+
+/// The arguments of `locks_struct!`, the visibility and the name of the struct,
+/// `pub AccountLocks;`, followed by the invocation.
+pub struct Definition {
+    vis: Visibility,
+    args: Args,
+}
+
+impl Parse for Definition {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let vis = stream.parse()?;
+        let ident: Ident = stream.parse()?;
+        let _: Token![;] = stream.parse()?;
+        let mut args: Args = stream.parse()?;
+
+        args.ident = Some(ident);
+        Ok(Self { vis, args })
+    }
+}
+
+/// Defines the struct, its `resolve()` and its impls at module level. They live in a hidden
+/// module, so the helpers of several structs never collide, and the struct is reexported with
+/// the visibility given.
+pub fn write_locks_struct(def: &Definition) -> TokenStream {
+    let Definition { vis, args } = def;
+    let ident = args.ident.as_ref().expect("ident");
+
+    // the tokens given back to the groups keep the name of the struct.
+    let mut include = args.clone();
+    let tokens = &args.tokens;
+    include.tokens = quote! { #vis #ident; #tokens };
+
+    if let Some(tokens) =
+        crate::include::write_include(&include, quote! { lock_derive::locks_struct })
+    {
+        return tokens;
+    }
+
+    if args.is_inline() {
+        return Error::new(
+            Span::call_site(),
+            "`locks_struct!` defines the struct at module level, its locks must all be resolved \
+             from their recipe, without a lock, a key or a count.",
+        )
+        .to_compile_error();
+    }
+
+    if args.owned || args.builder || args.tuple || args.infallible || args.closure.is_some() {
+        return Error::new(
+            Span::call_site(),
+            "`locks_struct!` defines the struct and its `resolve()`, it cannot be combined with \
+             `owned`, `builder`, `tuple`, `infallible` or a closure.",
+        )
+        .to_compile_error();
+    }
+
+    #[cfg(not(feature = "std"))]
+    {
+        if let Some(error) = crate::no_std::write_unsupported(args, false) {
+            return error;
+        }
+    }
+
+    if let Some(error) = crate::poison::write_unused(args) {
+        return error;
+    }
+
+    let module = Ident::new(&format!("__{}", ident), ident.span());
+    let items = write_items(args, write_chain(args));
+
+    // the struct resolves its locks in a single place, the site is its definition.
+    #[cfg(feature = "graph")]
+    let items = {
+        let site = crate::graph::write_site(args);
+        quote! { #site #items }
+    };
+
+    // the structs of the upgrades are named after this one.
+    let upgraded = crate::upgrade::upgradable(args).map(|item| {
+        let upgraded = crate::upgrade::upgraded_args(args, item);
+        let upgraded = upgraded.ident.expect("ident");
+        quote! { #vis use self::#module::#upgraded; }
+    });
+
+    // so is the struct of the escalation, next to the values given to its check.
+    let escalated = if args.items.iter().any(|t| t.escalated) {
+        let escalated = crate::escalate::escalated_args(args).ident.expect("ident");
+        let view = crate::escalate::view_ident(args);
+        quote! { #vis use self::#module::{#escalated, #view}; }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case, private_interfaces)]
+        mod #module {
+            use super::*;
+
+            #items
+        }
+
+        #vis use self::#module::#ident;
+        #(#upgraded)*
+        #escalated
+    }
+}
@@ -0,0 +1,133 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::Ident;
+
+/// The number of steps from which the chain is resolved by a state machine rather than by
+/// nested `and_then`.
+pub const MACHINE_STEPS: usize = 5;
+
+/// Resolves the steps by a flat state machine, one state per step, rather than by nested
+/// `and_then` whose type grows with each lock. `stages` are the futures and patterns of the
+/// steps in the order of acquisition: the first future is created on the spot, as in the
+/// chain, the others by a closure once the previous guard is held. `done` builds the result
+/// from the guards.
+pub fn write_machine(
+    stages: Vec<(TokenStream, TokenStream)>,
+    done: TokenStream,
+    capture: &TokenStream,
+) -> TokenStream {
+    let count = stages.len();
+    let idents = |prefix: &str| {
+        (0..count)
+            .map(|n| Ident::new(&format!("{}{}", prefix, n), Span::call_site()))
+            .collect::<Vec<_>>()
+    };
+
+    let states = idents("S");
+    let futures = idents("F");
+    let values = idents("v");
+    let makers = idents("M")[1..].to_vec();
+    let maker_fields = idents("m")[1..].to_vec();
+
+    let (resolves, patterns): (Vec<_>, Vec<_>) = stages.into_iter().unzip();
+    let first = &resolves[0];
+    let later = resolves[1..]
+        .iter()
+        .map(|resolve| quote! { #capture || #resolve });
+
+    let (futures, makers) = (&futures, &makers);
+    let generics = quote! { D, #(#futures,)* #(#makers),* };
+    let bounds = futures
+        .iter()
+        .map(|f| quote! { #f: futures::Future<Error = E> })
+        .chain(
+            makers
+                .iter()
+                .zip(&futures[1..])
+                .map(|(m, f)| quote! { #m: FnOnce() -> #f }),
+        )
+        .collect::<Vec<_>>();
+    let items = futures.iter().map(|f| quote! { #f::Item });
+    let struct_bounds = futures.iter().map(|f| quote! { #f: futures::Future });
+
+    // each state polls its future, keeps the guard and starts the future of the next step.
+    let polls = (0..count).map(|n| {
+        let state = &states[n];
+        let value = &values[n];
+
+        let next = match (states.get(n + 1), maker_fields.get(n)) {
+            (Some(next), Some(maker)) => {
+                quote! { __State::#next((self.#maker.take().expect("maker"))()) }
+            }
+            _ => quote! { __State::Done },
+        };
+
+        quote! {
+            __State::#state(f) => {
+                self.#value = Some(futures::try_ready!(futures::Future::poll(f)));
+                #next
+            }
+        }
+    });
+
+    let (states, values, maker_fields, bounds) = (&states, &values, &maker_fields, &bounds);
+    let maker_args = maker_fields;
+
+    quote! {{
+        enum __State<#(#futures),*> {
+            #(#states(#futures),)*
+            Done,
+        }
+
+        struct __Chain<#generics>
+        where
+            #(#struct_bounds,)*
+        {
+            state: __State<#(#futures),*>,
+            #(#maker_fields: Option<#makers>,)*
+            #(#values: Option<#futures::Item>,)*
+            done: Option<D>,
+        }
+
+        impl<T, E, #generics> futures::Future for __Chain<#generics>
+        where
+            #(#bounds,)*
+            D: FnOnce(#(#items),*) -> Result<T, E>,
+        {
+            type Item = T;
+            type Error = E;
+
+            fn poll(&mut self) -> futures::Poll<T, E> {
+                loop {
+                    self.state = match &mut self.state {
+                        #(#polls)*
+                        __State::Done => {
+                            let done = self.done.take().expect("done");
+                            return done(#(self.#values.take().expect("value")),*)
+                                .map(futures::Async::Ready);
+                        }
+                    };
+                }
+            }
+        }
+
+        fn __chain<T, E, #generics>(
+            first: F0,
+            #(#maker_fields: #makers,)*
+            done: D,
+        ) -> __Chain<#generics>
+        where
+            #(#bounds,)*
+            D: FnOnce(#(#futures::Item),*) -> Result<T, E>,
+        {
+            __Chain {
+                state: __State::S0(first),
+                #(#maker_fields: Some(#maker_args),)*
+                #(#values: None,)*
+                done: Some(done),
+            }
+        }
+
+        __chain(#first, #(#later,)* #capture |#(#patterns),*| #done)
+    }}
+}
@@ -0,0 +1,29 @@
+use crate::lock_set::Registry;
+use crate::write_item_resolve;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Registers the recipes of the registry in the `LockManager` of `lock_derive_runtime`, under
+/// the name of the recipe.
+pub fn write_register(registry: &Registry) -> TokenStream {
+    let registers = registry.items.iter().map(|item| {
+        let name = item.name.to_string();
+        let access = item.access_name();
+        let resolve = write_item_resolve(item);
+
+        quote! {
+            lock_derive_runtime::LockManager::register(#name, #access, || {
+                Box::new(#resolve.map(|g| Box::new(g) as Box<dyn std::any::Any>))
+            });
+        }
+    });
+
+    let bridge = crate::bridge::write_bridge();
+
+    quote! {{
+        use futures::Future;
+
+        #bridge
+        #(#registers)*
+    }}
+}
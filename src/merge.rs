@@ -0,0 +1,234 @@
+use crate::args::{Args, Item};
+use crate::{bridge, error_ty, write_all, write_chain, write_impls, write_struct, write_traits};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{parse_quote, Error, Expr, Ident, Token};
+
+/// The arguments of `merge_locks!`, the structs already held with the sections of their
+/// invocation, `locks => read: [users];`, followed by the sections of the new locks.
+pub struct Merge {
+    held: Vec<(Expr, Args)>,
+    args: Args,
+}
+
+impl Parse for Merge {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let mut held = Vec::new();
+
+        loop {
+            let fork = stream.fork();
+
+            if fork.parse::<Expr>().is_err() || !fork.peek(Token![=>]) {
+                break;
+            }
+
+            let expr = stream.parse()?;
+            let _: Token![=>] = stream.parse()?;
+            let mut sections = TokenStream::new();
+
+            while !stream.is_empty() && !stream.peek(Token![;]) {
+                sections.extend(Some(stream.parse::<TokenTree>()?));
+            }
+
+            let _: Token![;] = stream.parse()?;
+            held.push((expr, syn::parse2(sections)?));
+        }
+
+        if held.is_empty() {
+            return Err(stream.error("Expected `locks => sections;` for the locks held."));
+        }
+
+        Ok(Self {
+            held,
+            args: stream.parse()?,
+        })
+    }
+}
+
+/// Merges the held structs and the new locks into a single struct, as if all the locks had
+/// been acquired by a single invocation. When the new locks all come after the held ones in the
+/// order of the chain, the held guards are moved into the chain in place of their acquisition.
+/// Otherwise, the held structs are released and all the locks are acquired again, in order.
+pub fn write_merge(merge: &Merge) -> TokenStream {
+    let Merge { held, args } = merge;
+
+    if let Some(error) = write_unsupported(merge) {
+        return error;
+    }
+
+    // the union of the sections, parsed again so the locks held twice are rejected.
+    let mut tokens = args.tokens.clone();
+
+    for (_, held) in held {
+        let sections = &held.tokens;
+        tokens.extend(quote! { #sections });
+    }
+
+    let union: Args = match syn::parse2(tokens) {
+        Ok(union) => union,
+        Err(e) => return e.to_compile_error(),
+    };
+
+    let bindings = held.iter().enumerate().map(|(n, (expr, held))| {
+        let ident = held_ident(n);
+        let fields = held.items.iter().map(|t| {
+            let field = &t.field;
+            quote! { let _ = &#ident.#field; }
+        });
+        let parts = held.items.iter().map(|_| quote! { _ });
+
+        quote! {
+            let #ident = #expr;
+
+            // the held locks are listed completely, by field.
+            #(#fields)*
+            __check(&#ident, |__held| {
+                let (#(#parts,)*) = __held.into_guards();
+            });
+        }
+    });
+
+    let bindings = quote! {
+        fn __check<L>(_: &L, _: fn(L)) {}
+        #(#bindings)*
+    };
+
+    // the chain acquires the locks from the last identity to the first one.
+    let held_ids = held.iter().flat_map(|t| &t.1.items).map(|t| &t.id);
+    let in_order = args
+        .items
+        .iter()
+        .all(|item| held_ids.clone().all(|id| item.id < *id));
+
+    if !in_order {
+        let error = error_ty();
+        let releases = held.iter().enumerate().map(|(n, _)| {
+            let ident = held_ident(n);
+            quote! { .and_then(move |_| #ident.unlock_all()) }
+        });
+
+        let resolve = write_all(&union);
+
+        return quote! {{
+            use futures::Future;
+
+            #bindings
+
+            futures::future::ok::<_, #error>(())
+                #(#releases)*
+                .and_then(move |_| #resolve)
+        }};
+    }
+
+    // the held guards are taken by recipes of their own, delegating all the arms but `resolve`.
+    let mut union = union;
+    let mut takes = Vec::new();
+
+    for (n, (_, held)) in held.iter().enumerate() {
+        let ident = held_ident(n);
+        let parts = (0..held.items.len())
+            .map(|i| part_ident(n, i))
+            .collect::<Vec<_>>();
+
+        let values = &parts;
+        takes.push(quote! { let (#(#values,)*) = #ident.into_guards(); });
+
+        for (item, part) in held.items.iter().zip(&parts) {
+            takes.push(write_take(&mut union, item, part));
+        }
+    }
+
+    let resolve = write_chain(&union);
+    let locks = write_struct(&union);
+    let bridge = bridge::write_bridge();
+    let impls = write_impls(&union, false);
+    let traits = write_traits(&union);
+
+    quote! {{
+        use futures::Future;
+
+        #bindings
+        #(#takes)*
+
+        #locks
+        #bridge
+        #impls
+        #traits
+
+        #resolve
+    }}
+}
+
+/// Rejects what the merged struct cannot be: a future resolved again, a pair of structs or a
+/// struct borrowing the guards of the held ones.
+fn write_unsupported(merge: &Merge) -> Option<TokenStream> {
+    let args = &merge.args;
+
+    if args.blocking
+        || args.builder
+        || args.optimistic
+        || args.condvar
+        || args.tuple
+        || args.infallible
+        || args.closure.is_some()
+        || !args.includes.is_empty()
+    {
+        let error = Error::new(
+            Span::call_site(),
+            "`merge_locks!` resolves to the merged struct, it cannot be combined with \
+             `blocking`, `builder`, `optimistic`, `condvar`, `tuple`, `infallible`, groups or a \
+             closure.",
+        );
+
+        return Some(error.to_compile_error());
+    }
+
+    for (_, held) in &merge.held {
+        if let Some(item) = held
+            .items
+            .iter()
+            .find(|t| !t.is_lendable() || t.access.is_blocking())
+        {
+            let error = Error::new(
+                item.name.span(),
+                "`merge_locks!` only moves the `read` and `write` locks of a recipe, without a \
+                 lock, a key or a projection.",
+            );
+
+            return Some(error.to_compile_error());
+        }
+    }
+
+    None
+}
+
+/// Writes the recipe taking the guard of a held lock, `part`, and gives it to the item of the
+/// lock in the merged struct.
+fn write_take(union: &mut Args, held: &Item, part: &Ident) -> TokenStream {
+    let error = error_ty();
+    let item = union
+        .items
+        .iter_mut()
+        .find(|t| t.id == held.id)
+        .expect("held item");
+
+    let path = item.path.clone();
+    let ident = Ident::new(&format!("{}_take", part), Span::call_site());
+    item.path = parse_quote! { #ident };
+
+    quote! {
+        macro_rules! #ident {
+            (resolve $($t:tt)*) => { futures::future::ok::<_, #error>(#part) };
+            ($($t:tt)*) => { #path!($($t)*) };
+        }
+    }
+}
+
+fn held_ident(n: usize) -> Ident {
+    Ident::new(&format!("__held{}", n), Span::call_site())
+}
+
+fn part_ident(n: usize, i: usize) -> Ident {
+    Ident::new(&format!("__part{}_{}", n, i), Span::call_site())
+}
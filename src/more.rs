@@ -0,0 +1,105 @@
+use crate::args::Args;
+use crate::write_all;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{Error, Expr, Ident, Token};
+
+/// The arguments of `acquire_more!`, the locks already held, `locks => accounts, users;`, and
+/// the invocation acquiring the new ones.
+pub struct More {
+    held: Expr,
+    names: Vec<Ident>,
+    args: Args,
+}
+
+impl Parse for More {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let held = stream.parse()?;
+        let _: Token![=>] = stream.parse()?;
+        let mut names = Vec::<Ident>::new();
+
+        loop {
+            names.push(stream.parse()?);
+
+            if stream.peek(Token![;]) {
+                let _: Token![;] = stream.parse()?;
+                break;
+            }
+
+            let _: Token![,] = stream.parse()?;
+        }
+
+        Ok(Self {
+            held,
+            names,
+            args: stream.parse()?,
+        })
+    }
+}
+
+/// Acquires the locks of the invocation while the locks of `held` are kept, once checked at
+/// compile time that they all come later in the order of the chain. `held` must list all its
+/// locks, which is checked against its fields and the arity of its `into_guards()`.
+pub fn write_more(more: &More) -> TokenStream {
+    let More { held, names, args } = more;
+
+    // the tokens given back to the groups keep the held locks.
+    let mut include = args.clone();
+    let tokens = &args.tokens;
+    include.tokens = quote! { #held => #(#names),*; #tokens };
+
+    if let Some(tokens) =
+        crate::include::write_include(&include, quote! { lock_derive::acquire_more })
+    {
+        return tokens;
+    }
+
+    if args.blocking || args.builder || args.tuple || args.infallible || args.closure.is_some() {
+        return Error::new(
+            Span::call_site(),
+            "`acquire_more!` resolves to the held locks and the new ones, it cannot be combined \
+             with `blocking`, `builder`, `tuple`, `infallible` or a closure.",
+        )
+        .to_compile_error();
+    }
+
+    // the chain acquires the locks from the last identity to the first one.
+    for item in &args.items {
+        if names.contains(&item.name) {
+            return Error::new(
+                item.name.span(),
+                format!("The lock `{}` is already held.", item.name),
+            )
+            .to_compile_error();
+        }
+
+        if let Some(name) = names.iter().find(|n| item.id >= n.to_string()) {
+            return Error::new(
+                item.name.span(),
+                format!(
+                    "The lock `{}` is acquired before `{}` by `locks!`, it cannot be acquired \
+                     while `{}` is held. Acquire them in a single invocation.",
+                    item.id, name, name
+                ),
+            )
+            .to_compile_error();
+        }
+    }
+
+    let resolve = write_all(args);
+    let parts = names.iter().map(|_| quote! { _ });
+
+    quote! {{
+        let __held = #held;
+
+        // the held locks are listed completely, by field.
+        fn __check<L>(_: &L, _: fn(L)) {}
+        #(let _ = &__held.#names;)*
+        __check(&__held, |__held| {
+            let (#(#parts,)*) = __held.into_guards();
+        });
+
+        futures::Future::map(#resolve, move |__locks| (__held, __locks))
+    }}
+}
@@ -0,0 +1,101 @@
+use crate::args::{Access, Args};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{Error, Ident, Token, Type};
+
+/// Brings the items of `alloc` found in the prelude of `std` into the scope of the generated
+/// code.
+pub fn write_prelude() -> TokenStream {
+    quote! {
+        #[allow(unused_imports)]
+        use alloc::{boxed::Box, format, string::String, vec, vec::Vec};
+    }
+}
+
+/// Rejects what needs `std` to run: a timer, a thread to block on, or the state shared through
+/// the runtime. The features needing `std` turn it on. `sync` tells whether the locks are
+/// resolved by `locks_sync!`, which rejects the futures on its own.
+pub fn write_unsupported(args: &Args, sync: bool) -> Option<TokenStream> {
+    let message = if args.poison.is_some() {
+        "`poison` is not supported without the `std` feature, the poisoning is that of \
+         `std::sync`."
+    } else if args
+        .items
+        .iter()
+        .any(|t| matches!(t.access, Access::Versioned))
+    {
+        "`versioned` is not supported without the `std` feature, the versions are shared \
+         through `std`."
+    } else if sync {
+        return None;
+    } else if args.blocking || args.builder || args.infallible || args.closure.is_some() {
+        "`blocking`, `builder`, `infallible` and closures are not supported without the `std` \
+         feature, they need `std` to wait on the locks."
+    } else if args.items.iter().any(|t| t.access.is_blocking()) {
+        "`blocking_read` and `blocking_write` are not supported without the `std` feature, they \
+         need a thread to block."
+    } else if args.items.iter().any(|t| t.timeout.is_some()) || args.quorum.is_some() {
+        "Timeouts and `quorum` are not supported without the `std` feature, they need the timer \
+         of a `RuntimeAdapter`."
+    } else if args.cancel.is_some() {
+        "`cancel` is not supported without the `std` feature, the token is polled through \
+         `std`."
+    } else if args.condvar || args.write_preferring || args.report_hold.is_some() {
+        "`condvar`, `policy` and `report_hold` are not supported without the `std` feature, \
+         their state is shared through `std`."
+    } else if args
+        .items
+        .iter()
+        .any(|t| t.source.is_some() || matches!(t.access, Access::Intent | Access::Init))
+    {
+        "Lock sources, `intent` and `init` are not supported without the `std` feature, their \
+         state is shared through `std`."
+    } else {
+        return None;
+    };
+
+    Some(Error::new(Span::call_site(), message).to_compile_error())
+}
+
+/// The arguments of `runtime!` without the `std` feature, `error: Type`, the error of the chain.
+pub struct Runtime {
+    error: Type,
+}
+
+impl Parse for Runtime {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let name: Ident = stream.parse()?;
+
+        if name != "error" {
+            return Err(Error::new(name.span(), "Expected `error: Type`."));
+        }
+
+        let _: Token![:] = stream.parse()?;
+
+        Ok(Self {
+            error: stream.parse()?,
+        })
+    }
+}
+
+/// Writes `__lock_derive_runtime` holding the error of the chain, which must be built `From` a
+/// message for the errors of the chain itself.
+pub fn write_runtime(runtime: &Runtime) -> TokenStream {
+    let error = &runtime.error;
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(dead_code)]
+        pub(crate) mod __lock_derive_runtime {
+            use super::*;
+
+            pub use lock_derive_runtime::ErasedLocks;
+
+            pub type Error = #error;
+        }
+
+        #[allow(unused_imports)]
+        pub(crate) use self::__lock_derive_runtime::ErasedLocks;
+    }
+}
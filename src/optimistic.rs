@@ -0,0 +1,93 @@
+use crate::args::{Access, Args, Item};
+use crate::{
+    source, struct_ident, struct_ty, value_ident, write_markers, write_recipe_arm,
+    write_recipe_guard,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse_quote;
+
+/// Tries to acquire all the locks at once with the `try_resolve` arm of the recipes, which
+/// returns an `Option` of the guard. If any of them is not available, the guards already taken
+/// are released and the locks are acquired with the ordered `resolve` chain. The attempt is made
+/// when the future is first polled, as the chain would.
+pub fn write_optimistic(args: &Args, resolve: TokenStream) -> TokenStream {
+    let ty = struct_ty(args);
+    let values = args.items.iter().enumerate().map(|(i, t)| {
+        let v = value_ident(i);
+        let try_resolve = write_try_resolve(t);
+
+        quote! { let #v = #try_resolve?; }
+    });
+
+    let fields = args.items.iter().enumerate().map(|(i, t)| {
+        let field = &t.field;
+        let v = value_ident(i);
+        quote! { #field: #v }
+    });
+
+    // the marker is given first, the guards are still in their bindings.
+    let marker = write_markers(args, false);
+    let ident = struct_ident(args);
+    let capture = if args.owned {
+        quote! { move }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        futures::future::lazy(#capture || {
+            let __locks = (|| -> Option<#ty> {
+                #(#values)*
+                Some(#ident { #marker #(#fields,)* })
+            })();
+
+            match __locks {
+                Some(locks) => futures::future::Either::A(futures::future::ok(locks)),
+                None => futures::future::Either::B(#resolve),
+            }
+        })
+    }
+}
+
+fn write_try_resolve(item: &Item) -> TokenStream {
+    if let Some(source) = &item.source {
+        let try_resolve = source::write_source_call(item, source, true);
+        return write_recipe_guard(item, try_resolve);
+    }
+
+    if let Access::Intent = item.access {
+        let try_resolve = write_recipe_arm(item, quote! { try_resolve });
+        return crate::intent::write_try_intent(item, try_resolve);
+    }
+
+    if item.upgraded {
+        let try_resolve = write_recipe_arm(item, quote! { try_resolve });
+        return quote! { #try_resolve.map(lock_derive_runtime::Upgraded::new) };
+    }
+
+    if item.keys.is_none() {
+        return write_recipe_arm(item, quote! { try_resolve });
+    }
+
+    let keys = &item.keys;
+    let try_resolve = write_recipe_arm(
+        &Item {
+            key: Some(parse_quote! { __key }),
+            keys: None,
+            ..item.clone()
+        },
+        quote! { try_resolve },
+    );
+
+    quote! {{
+        let mut __keys = (#keys).into_iter().collect::<Vec<_>>();
+        __keys.sort();
+        __keys.dedup();
+
+        __keys
+            .into_iter()
+            .map(|__key| #try_resolve)
+            .collect::<Option<Vec<_>>>()
+    }}
+}
@@ -0,0 +1,27 @@
+use crate::args::Args;
+use crate::{struct_generics, struct_vis};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `ORDER` and `order()`, the identity and the access of each lock in the order of their
+/// acquisition. The chain acquires the last item first.
+pub fn write_order(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+    let ids = args.items.iter().rev().map(|t| &t.id);
+    let accesses = args.items.iter().rev().map(|t| t.access_name());
+
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            /// The identity and the access of each lock, in the order of their acquisition.
+            #vis const ORDER: &'static [(&'static str, &'static str)] =
+                &[#((#ids, #accesses)),*];
+
+            /// `ORDER`, from the struct of an invocation, whose type cannot be named.
+            #vis fn order(&self) -> &'static [(&'static str, &'static str)] {
+                Self::ORDER
+            }
+        }
+    }
+}
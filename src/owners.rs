@@ -0,0 +1,76 @@
+use crate::args::{Args, Item};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Whether the holder of the lock is tracked. Borrowed, keyed and sourced locks are only known
+/// at runtime, the canonical identity naming the recipe rather than the instance, and the
+/// replicas of a quorum may be left out.
+fn is_tracked(item: &Item) -> bool {
+    item.lock.is_none()
+        && item.key.is_none()
+        && item.keys.is_none()
+        && item.source.is_none()
+        && !item.quorum
+}
+
+/// The tracked locks, `(id, access, exclusive)`, in the order of their acquisition.
+fn write_locks(args: &Args) -> TokenStream {
+    let locks = args.items.iter().rev().filter(|t| is_tracked(t)).map(|t| {
+        let id = &t.id;
+        let access = t.access_name();
+        let write = t.access.is_exclusive();
+        quote! { (#id, #access, #write) }
+    });
+
+    quote! { &[#(#locks),*] }
+}
+
+/// The marker registering the holder of the locks, with the call site of the invocation, until
+/// the struct is dropped. `sync` tells whether the struct is built by `locks_sync!`, outside of a
+/// task.
+pub fn write_owners(args: &Args, sync: bool) -> TokenStream {
+    let locks = write_locks(args);
+
+    let owner = if sync {
+        quote! { lock_derive_runtime::owners::thread() }
+    } else {
+        quote! { lock_derive_runtime::owners::task() }
+    };
+
+    quote! {
+        __owners: lock_derive_runtime::owners::acquired(
+            module_path!(),
+            concat!(file!(), ":", line!()),
+            #locks,
+            #owner,
+        ),
+    }
+}
+
+/// The holder of the lock given up by its timeout, if it is tracked and held, appended to the
+/// error built by `error`.
+pub fn write_holder(item: &Item, error: TokenStream) -> TokenStream {
+    if !is_tracked(item) {
+        return error;
+    }
+
+    let id = &item.id;
+    let write = item.access.is_exclusive();
+
+    quote! {
+        match lock_derive_runtime::owners::holder(module_path!(), #id, #write) {
+            Some(__holder) => failure::format_err!("{}, {}", #error, __holder),
+            None => #error,
+        }
+    }
+}
+
+/// Names the lock the chain of `builder;` was waiting for and its holder when its timeout
+/// elapses.
+pub fn write_blocked(args: &Args) -> TokenStream {
+    let locks = write_locks(args);
+
+    quote! {
+        .with_blocked(|| lock_derive_runtime::owners::blocked(module_path!(), #locks))
+    }
+}
@@ -0,0 +1,43 @@
+use crate::args::Args;
+use crate::versioned::has_written;
+use crate::{struct_generics, struct_vis, write_field_ty};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `into_parts()`, consuming the struct into a tuple of its guards, in the order of the
+/// fields, followed by the marker bumping the versions of the write locks once dropped, so the
+/// writes made through the guards are not missed. `into_guards()` leaves the marker out, for the
+/// invocations taking the guards of a struct into one of their own, which bumps the versions.
+pub fn write_into_parts(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+    let fields = args.items.iter().map(|t| &t.field).collect::<Vec<_>>();
+    let tys = args.items.iter().map(write_field_ty).collect::<Vec<_>>();
+    let fields = &fields;
+    let tys = &tys;
+
+    let (written, written_ty) = if has_written(args) {
+        (
+            Some(quote! { self.__written }),
+            Some(quote! { lock_derive_runtime::Written }),
+        )
+    } else {
+        (None, None)
+    };
+
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            /// Consumes the locks into their guards, so they can be moved and released
+            /// separately.
+            #vis fn into_parts(self) -> (#(#tys,)* #written_ty) {
+                (#(self.#fields,)* #written)
+            }
+
+            #[doc(hidden)]
+            #vis fn into_guards(self) -> (#(#tys,)*) {
+                (#(self.#fields,)*)
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::args::{Args, Item};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{Error, Ident};
+
+/// What to do when a `std::sync` lock is found poisoned, `poison: propagate`, `clear` or
+/// `ignore`.
+#[derive(Clone, Copy)]
+pub enum Poison {
+    /// Fails the invocation with an error naming the lock.
+    Propagate,
+    /// Recovers the guard with `into_inner()`.
+    Clear,
+    /// Panics, as `lock().unwrap()` does.
+    Ignore,
+}
+
+impl Parse for Poison {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let policy: Ident = stream.parse()?;
+
+        match policy.to_string().as_str() {
+            "propagate" => Ok(Poison::Propagate),
+            "clear" => Ok(Poison::Clear),
+            "ignore" => Ok(Poison::Ignore),
+            _ => Err(Error::new(
+                policy.span(),
+                "Expected `propagate`, `clear` or `ignore`.",
+            )),
+        }
+    }
+}
+
+impl Poison {
+    pub fn name(self) -> &'static str {
+        match self {
+            Poison::Propagate => "propagate",
+            Poison::Clear => "clear",
+            Poison::Ignore => "ignore",
+        }
+    }
+}
+
+/// Rejects `poison` on an invocation of `locks!` without any blocking lock to apply it to.
+pub fn write_unused(args: &Args) -> Option<TokenStream> {
+    if args.poison.is_none() || args.items.iter().any(|t| t.access.is_blocking()) {
+        return None;
+    }
+
+    Some(
+        Error::new(
+            Span::call_site(),
+            "`poison` applies to the `std::sync` locks of `blocking_read` and `blocking_write`, \
+             or of `locks_sync!`.",
+        )
+        .to_compile_error(),
+    )
+}
+
+/// Handles the poisoning of the `std::sync::LockResult` returned by the `resolve` arm of a
+/// blocking recipe, giving back the `Result` of the guard expected from the arm otherwise.
+pub fn write_poison(item: &Item, resolve: TokenStream) -> TokenStream {
+    let context = format!("acquiring {} lock `{}`", item.access_name(), item.id);
+
+    match item.poison {
+        None => resolve,
+        Some(Poison::Propagate) => quote! {
+            (#resolve).map_err(|_| failure::err_msg("lock poisoned"))
+        },
+        Some(Poison::Clear) => quote! {
+            Ok::<_, failure::Error>((#resolve).unwrap_or_else(std::sync::PoisonError::into_inner))
+        },
+        Some(Poison::Ignore) => quote! {
+            Ok::<_, failure::Error>(
+                (#resolve).unwrap_or_else(|_| panic!("{}: lock poisoned", #context)),
+            )
+        },
+    }
+}
@@ -0,0 +1,46 @@
+use crate::args::{Access, Item};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Emulates a writer-preferring lock for `policy: write_preferring`. A writer holds the
+/// turnstile of the lock while waiting for it, and a reader passes through the turnstile before
+/// reading, so the readers coming after a waiting writer queue behind it.
+pub fn write_preferring(item: &Item, resolve: TokenStream) -> TokenStream {
+    // keyed locks and borrowed locks have no identity shared by the invocations.
+    if item.key.is_some() || item.keys.is_some() || item.lock.is_some() {
+        return resolve;
+    }
+
+    let id = &item.id;
+    let capture = if item.owned {
+        quote! { move }
+    } else {
+        quote! {}
+    };
+
+    let turnstile = quote! {
+        lock_derive_runtime::turnstile::turnstile(module_path!(), #id)
+            .lock()
+            .map_err(|_| failure::format_err!("turnstile of lock `{}` error", #id))
+    };
+
+    match item.access {
+        Access::Write | Access::BlockingWrite | Access::FreezeBytes => quote! {
+            #turnstile.and_then(#capture |__turnstile| #resolve.map(move |__guard| {
+                drop(__turnstile);
+                __guard
+            }))
+        },
+        Access::Read
+        | Access::BlockingRead
+        | Access::Intent
+        | Access::Snapshot
+        | Access::Versioned => quote! {
+            #turnstile.and_then(#capture |__turnstile| {
+                drop(__turnstile);
+                #resolve
+            })
+        },
+        Access::Permits | Access::Checkout | Access::Init => resolve,
+    }
+}
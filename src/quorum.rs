@@ -0,0 +1,65 @@
+use crate::args::Item;
+use crate::runtime::write_adapter;
+use crate::timeout::Timeout;
+use crate::{error_ty, value_ident, write_supervised_resolve};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Resolves the replicas of `quorum: 2 of [..] @ 1s`, a single step of the chain. The replicas
+/// are acquired concurrently, each within the deadline, so two quorums racing for the replicas
+/// give up rather than wait on each other. A replica failing to resolve or timing out counts as
+/// failed; once all of them are settled, the step fails with the error of the first replica
+/// failed unless the threshold is met, and yields the guards in an `Option`, in the order of the
+/// items.
+pub fn write_quorum(step: &[(usize, &Item)], threshold: usize, deadline: &Timeout) -> TokenStream {
+    let error = error_ty();
+    let millis = deadline.millis;
+
+    let replicas = step.iter().map(|(_, item)| {
+        let adapter = write_adapter(&item.adapter);
+        let resolve = write_supervised_resolve(item);
+        let context = format!(
+            "acquiring write lock `{}`: timed out after {}",
+            item.id, deadline.text
+        );
+
+        quote! {
+            lock_derive_runtime::timeout::<#adapter, _, _>(
+                #resolve,
+                std::time::Duration::from_millis(#millis),
+                move || failure::format_err!("{}", #context),
+            )
+            .then(|__result| Ok::<_, #error>(__result))
+        }
+    });
+
+    // the replicas are joined pairwise, `((v0, v1), v2)`.
+    let mut replicas = replicas.collect::<Vec<_>>().into_iter();
+    let first = replicas.next().expect("replica");
+    let values = step.iter().map(|t| value_ident(t.0)).collect::<Vec<_>>();
+    let mut pattern = {
+        let v = &values[0];
+        quote! { #v }
+    };
+
+    for v in &values[1..] {
+        pattern = quote! { (#pattern, #v) };
+    }
+
+    let values = &values;
+
+    quote! {
+        #first
+            #(.join(#replicas))*
+            .and_then(move |#pattern| {
+                let __acquired = [#(#values.is_ok(),)*].iter().filter(|t| **t).count();
+
+                if __acquired < #threshold {
+                    let mut __failed = vec![#(#values.err(),)*].into_iter().flatten();
+                    return Err(__failed.next().expect("failed replica"));
+                }
+
+                Ok((#(#values.ok(),)*))
+            })
+    }
+}
@@ -0,0 +1,26 @@
+use crate::args::{Args, Item};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Records the acquisition of the lock in the recorder of the current task, if any, once its
+/// guard is resolved.
+pub fn write_recorded(item: &Item, resolve: TokenStream) -> TokenStream {
+    let id = &item.id;
+    let access = item.access_name();
+
+    quote! {
+        #resolve.map(|__guard| {
+            lock_derive_runtime::recorder::acquired(#id, #access);
+            __guard
+        })
+    }
+}
+
+/// The marker recording the release of the locks when the struct is dropped.
+pub fn write_hold(args: &Args) -> TokenStream {
+    let ids = args.items.iter().map(|t| &t.id);
+
+    quote! {
+        __recorded: lock_derive_runtime::recorder::hold(&[#(#ids),*]),
+    }
+}
@@ -0,0 +1,142 @@
+use crate::args::{Args, Item};
+use crate::value_ident;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The locks tracked for re-entrancy, by canonical identity. Keyed locks and batches of keys are
+/// only known at runtime and are not tracked, nor are the replicas of a quorum, which may be
+/// left out.
+fn ids(args: &Args) -> Vec<&str> {
+    args.items
+        .iter()
+        .filter(|t| t.key.is_none() && t.keys.is_none() && !t.quorum)
+        .map(|t: &Item| t.id.as_str())
+        .collect()
+}
+
+/// The locks of `reentrant: [..]`, by canonical identity, along with whether they are written.
+fn write_reentrant_ids(args: &Args) -> TokenStream {
+    let ids = args.items.iter().filter(|t| t.reentrant).map(|t| {
+        let id = &t.id;
+        let write = t.access.is_exclusive();
+        quote! { (#id, #write) }
+    });
+
+    quote! { &[#(#ids),*] }
+}
+
+/// The holder of the locks: the current task for `locks!`, the current thread for
+/// `locks_sync!`.
+fn write_owner(sync: bool) -> TokenStream {
+    if sync {
+        quote! { lock_derive_runtime::held::Owner::thread() }
+    } else {
+        quote! { lock_derive_runtime::held::Owner::task() }
+    }
+}
+
+/// Checks that the holder does not already hold one of the locks before acquiring them, for
+/// `locks!` inside the task polling the chain.
+pub fn write_check(args: &Args, resolve: TokenStream) -> TokenStream {
+    let ids = ids(args);
+    let reentrant = write_reentrant_ids(args);
+    let owner = write_owner(false);
+    let capture = if args.owned {
+        quote! { move }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        futures::future::lazy(#capture || {
+            lock_derive_runtime::held::check(module_path!(), &[#(#ids),*], #reentrant, &#owner);
+            #resolve
+        })
+    }
+}
+
+/// Checks that the holder does not already hold one of the locks, for `locks_sync!`.
+pub fn write_check_sync(args: &Args) -> TokenStream {
+    let ids = ids(args);
+    let reentrant = write_reentrant_ids(args);
+    let owner = write_owner(true);
+
+    quote! {
+        lock_derive_runtime::held::check(module_path!(), &[#(#ids),*], #reentrant, &#owner);
+    }
+}
+
+/// Registers the locks as held by the holder until the struct is dropped, along with the address
+/// of the value of the guards which can be lent to `reentrant: [..]`, but for the locks whose
+/// guard is already released. The guards are still in the `__v0..` bindings, the marker being
+/// given first. An `owned;` struct is not registered: it may be moved to another task, which
+/// would then be told apart from the holder of the locks.
+pub fn write_enter(args: &Args, sync: bool) -> TokenStream {
+    let owner = write_owner(sync);
+
+    let locks = args
+        .items
+        .iter()
+        .enumerate()
+        .filter(|t| {
+            !args.owned
+                && t.1.key.is_none()
+                && t.1.keys.is_none()
+                && !t.1.quorum
+                && !t.1.access.is_released()
+        })
+        .map(|(i, t)| {
+            let id = &t.id;
+            let write = t.access.is_exclusive();
+            let v = value_ident(i);
+
+            if t.is_lendable() {
+                quote! { (#id, #write, lock_derive_runtime::held::address(&#v)) }
+            } else {
+                quote! { (#id, #write, 0) }
+            }
+        });
+
+    quote! {
+        __held: lock_derive_runtime::held::enter(module_path!(), &[#(#locks),*], #owner),
+    }
+}
+
+/// Borrows the guard held by the holder for a lock of `reentrant: [..]` in a compatible access,
+/// or acquires the lock with `resolve` otherwise. `sync` tells whether `resolve` is the `Result`
+/// of `locks_sync!` rather than a future. The borrow is unchecked, the caller vouching for it by
+/// writing `unsafe reentrant: [..]`.
+pub fn write_reentrant(item: &Item, resolve: TokenStream, sync: bool) -> TokenStream {
+    let id = &item.id;
+    let write = item.access.is_exclusive();
+    let owner = write_owner(sync);
+    let capture = if item.owned {
+        quote! { move }
+    } else {
+        quote! {}
+    };
+
+    let lookup = quote! {
+        lock_derive_runtime::held::lookup(module_path!(), #id, #write, &#owner)
+    };
+
+    if sync {
+        quote! {
+            match #lookup {
+                Some(__value) => Ok(unsafe { lock_derive_runtime::held::Reentrant::held(__value) }),
+                None => #resolve.map(lock_derive_runtime::held::Reentrant::acquired),
+            }
+        }
+    } else {
+        quote! {
+            futures::future::lazy(#capture || match #lookup {
+                Some(__value) => futures::future::Either::A(futures::future::ok(
+                    unsafe { lock_derive_runtime::held::Reentrant::held(__value) },
+                )),
+                None => futures::future::Either::B(
+                    #resolve.map(lock_derive_runtime::held::Reentrant::acquired),
+                ),
+            })
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::args::{Access, Args};
+use crate::{error_ty, lifetime, respan, struct_generics, struct_vis, write_context};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Error;
+
+/// Writes `release()`, giving each guard to the `(release $access $guard)` arm of its recipe,
+/// for `release;`. The locks are released one after the other, the last one acquired first.
+pub fn write_release(args: &Args) -> TokenStream {
+    if let Some(item) = args.items.iter().find(|t| {
+        t.source.is_some()
+            || matches!(
+                t.access,
+                Access::Intent
+                    | Access::FreezeBytes
+                    | Access::Snapshot
+                    | Access::Versioned
+                    | Access::Init
+            )
+    }) {
+        return Error::new(
+            item.name.span(),
+            "`release` gives the guards back to their recipe, which lock sources, `intent`, \
+             `freeze_bytes`, `snapshot`, `versioned` and `init` do not have.",
+        )
+        .to_compile_error();
+    }
+
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+    let error = error_ty();
+    let fields = args.items.iter().map(|t| &t.field).collect::<Vec<_>>();
+    let fields = &fields;
+
+    let bound = if args.has_lifetime() {
+        let lifetime = lifetime();
+        quote! { + #lifetime }
+    } else {
+        quote! {}
+    };
+
+    // the futures chain acquires the last item first, so the first one is released first.
+    let releases = args.items.iter().map(|t| {
+        let field = &t.field;
+        let path = &t.path;
+        let generics = &t.generics;
+        let access = t.access.resolve_ident();
+        let arm = respan(quote! { release #access }, t.name.span());
+        let context = format!("releasing {} lock `{}`", t.access_name(), t.id);
+
+        let guard = match t.projection {
+            Some(_) => quote! { __guard.guard },
+            None => quote! { __guard },
+        };
+
+        let release = crate::bridge::write_bridged(quote! { #path!(#arm #generics #guard) });
+        let release = write_context(&context, release);
+
+        match t.keys {
+            // the keys are acquired in order, the last key is released first.
+            Some(_) => quote! {
+                futures::Stream::for_each(
+                    futures::stream::iter_ok::<_, #error>(#field.into_iter().rev()),
+                    |__guard| #release,
+                )
+            },
+            None => quote! {{
+                let __guard = #field;
+                #release
+            }},
+        }
+    });
+
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            /// Releases the locks through the `release` arm of their recipe, in the order of the
+            /// drop of the struct. The release stops at the first error.
+            #vis fn release(self) -> impl futures::Future<Item = (), Error = #error> #bound {
+                use futures::Future;
+
+                let Self { #(#fields,)* .. } = self;
+
+                futures::future::ok::<_, #error>(())
+                    #(.and_then(move |_| #releases))*
+            }
+        }
+    }
+}
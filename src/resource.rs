@@ -0,0 +1,108 @@
+use crate::args::Args;
+use crate::{lifetime, struct_generics, struct_vis, write_field_ty};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::Ident;
+
+/// Writes the `Resource` enum, a unified view over every guard held by the struct, and the
+/// `resources()` method listing them in acquisition order.
+pub fn write_resources(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+    let lifetime = lifetime();
+
+    // the struct resolved by `upgrade_<field>()` is defined next to the struct upgraded.
+    let resource = match &args.upgraded {
+        Some(field) => format!("Resource{}Write", camel_case(&field.to_string())),
+        None => "Resource".to_string(),
+    };
+
+    let resource = Ident::new(&resource, Span::call_site());
+
+    // the futures chain acquires the last item first
+    let items = args.items.iter().rev().collect::<Vec<_>>();
+
+    let variants = items
+        .iter()
+        .map(|t| Ident::new(&camel_case(&t.field.to_string()), Span::call_site()))
+        .collect::<Vec<_>>();
+
+    let tys = items.iter().map(|t| write_field_ty(t));
+    let fields = items.iter().map(|t| &t.field);
+    let names = items.iter().map(|t| t.field.to_string());
+    let accesses = items.iter().map(|t| t.access_name());
+
+    let (generics, resource_ty, marker, marker_arm) = if args.has_lifetime() {
+        (
+            quote! { <'r, #lifetime: 'r> },
+            quote! { #resource<'r, #lifetime> },
+            quote! {
+                #[doc(hidden)]
+                __Lifetime(core::marker::PhantomData<&'r &#lifetime ()>),
+            },
+            quote! { #resource::__Lifetime(_) => unreachable!(), },
+        )
+    } else {
+        (
+            quote! { <'r> },
+            quote! { #resource<'r> },
+            quote! {},
+            quote! {},
+        )
+    };
+
+    let variants = &variants;
+    let marker_arm = &marker_arm;
+    let paths = &variants
+        .iter()
+        .map(|variant| quote! { #resource::#variant })
+        .collect::<Vec<_>>();
+
+    quote! {
+        #[allow(dead_code)]
+        #vis enum #resource_ty {
+            #(#variants(&'r #tys),)*
+            #marker
+        }
+
+        #[allow(dead_code)]
+        impl #generics #resource_ty {
+            /// The name of the field holding the resource.
+            #vis fn name(&self) -> &'static str {
+                match self {
+                    #(#paths(_) => #names,)*
+                    #marker_arm
+                }
+            }
+
+            /// How the resource was acquired, `read` or `write`.
+            #vis fn access(&self) -> &'static str {
+                match self {
+                    #(#paths(_) => #accesses,)*
+                    #marker_arm
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            /// Every resource held, in acquisition order.
+            #vis fn resources<'r>(&'r self) -> Vec<#resource_ty> {
+                vec![#(#paths(&self.#fields),)*]
+            }
+        }
+    }
+}
+
+pub fn camel_case(s: &str) -> String {
+    s.split('_')
+        .map(|w| {
+            let mut chars = w.chars();
+
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,50 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Type;
+
+/// The `RuntimeAdapter` given by `adapter: Type`, or the default one of the runtime crate.
+pub fn write_adapter(adapter: &Option<Type>) -> TokenStream {
+    match adapter {
+        Some(adapter) => quote! { #adapter },
+        None => quote! { lock_derive_runtime::DefaultAdapter },
+    }
+}
+
+/// Writes the imports of the runtime at the root of the crate: the items of `lock_derive_runtime`
+/// used by the calling code, the state shared by the invocations being kept by the runtime crate
+/// itself, by crate.
+pub fn write_runtime() -> TokenStream {
+    let runtime = quote! {
+        #[allow(unused_imports)]
+        pub(crate) use lock_derive_runtime::{
+            set_hold_observer, Cancelled, ErasedLocks, LockFuture, LockManager, LockSource,
+            ManagedLocks,
+        };
+    };
+
+    #[cfg(feature = "graph")]
+    let runtime = quote! {
+        #runtime
+
+        #[allow(unused_imports)]
+        pub(crate) use lock_derive_runtime::{lock_graph_dot, lock_graph_json, lock_sites, LockSite};
+    };
+
+    #[cfg(feature = "owners")]
+    let runtime = quote! {
+        #runtime
+
+        #[allow(unused_imports)]
+        pub(crate) use lock_derive_runtime::name_task;
+    };
+
+    #[cfg(feature = "test-util")]
+    let runtime = quote! {
+        #runtime
+
+        #[allow(unused_imports)]
+        pub(crate) use lock_derive_runtime::Recorder;
+    };
+
+    runtime
+}
@@ -0,0 +1,28 @@
+use crate::args::{Access, Item, Source};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The guard type of a lock source.
+pub fn write_source_ty(item: &Item, source: &Source) -> TokenStream {
+    let ty = &source.ty;
+
+    match item.access {
+        Access::Write => quote! { <#ty as lock_derive_runtime::LockSource>::WriteGuard },
+        _ => quote! { <#ty as lock_derive_runtime::LockSource>::ReadGuard },
+    }
+}
+
+/// Acquires a lock source, or tries to acquire it without waiting when `try_` is set.
+pub fn write_source_call(item: &Item, source: &Source, try_: bool) -> TokenStream {
+    let ty = &source.ty;
+    let expr = &source.expr;
+
+    let method = match (item.access, try_) {
+        (Access::Write, false) => quote! { write },
+        (Access::Write, true) => quote! { try_write },
+        (_, false) => quote! { read },
+        (_, true) => quote! { try_read },
+    };
+
+    quote! { <#ty as lock_derive_runtime::LockSource>::#method(&(#expr)) }
+}
@@ -0,0 +1,56 @@
+use crate::args::{Args, Item};
+use crate::runtime::write_adapter;
+use crate::{struct_generics, struct_vis, write_recipe_future};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Races the acquisition of a lock against its supervisor, checked every 100 ms. Once the
+/// supervisor has invalidated the lock and the grace period has elapsed, the guard is taken from
+/// the `force_resolve` arm of the recipe instead of waiting on the stuck lock, and the runtime
+/// crate logs it. The acquisition losing the race is dropped while pending.
+pub fn write_supervised(item: &Item, resolve: TokenStream) -> TokenStream {
+    let path = &item.path;
+    let generics = &item.generics;
+    let lock = format!("{} ({})", item.field, item.access_name());
+    let force_resolve = write_recipe_future(item, quote! { force_resolve });
+    let adapter = write_adapter(&item.adapter);
+
+    quote! {{
+        use futures::Stream;
+
+        let __supervisor = #path!(supervisor #generics);
+        let __period = std::time::Duration::from_millis(100);
+        let __forced = lock_derive_runtime::interval::<#adapter>(__period)
+            .filter(move |_| __supervisor.grace_elapsed())
+            .into_future()
+            .map_err(|(e, _)| e)
+            .and_then(move |_| {
+                lock_derive_runtime::supervisor::forced(#lock);
+                #force_resolve
+            });
+
+        #resolve.select(__forced).map(|(g, _)| g).map_err(|(e, _)| e)
+    }}
+}
+
+/// Writes `is_invalidated()`, telling whether the supervisor of any lock held has invalidated it.
+pub fn write_is_invalidated(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+    let supervisors = args.items.iter().filter(|t| t.source.is_none()).map(|t| {
+        let path = &t.path;
+        let generics = &t.generics;
+        quote! { #path!(supervisor #generics) }
+    });
+
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            /// Whether a lock held has been invalidated by its supervisor, in which case the
+            /// critical section should be abandoned as soon as possible.
+            #vis fn is_invalidated(&self) -> bool {
+                false #(|| #supervisors.is_invalidated())*
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::args::{Access, Args, Item};
+use crate::{
+    error_ty, keyed_idents, struct_ident, struct_ty, value_ident, write_error, write_impls,
+    write_keyed_bindings, write_markers, write_recipe_result, write_struct, write_traits,
+};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse_quote;
+
+/// Writes the blocking variant of the locks. The recipes `resolve` arm returns a
+/// `Result<Guard, failure::Error>` and the locks are acquired one after the other, in the same
+/// order as the futures chain of `locks!`.
+pub fn write_all(args: &Args) -> TokenStream {
+    if args.write_preferring {
+        return syn::Error::new(
+            Span::call_site(),
+            "`policy` is not supported by `locks_sync!`, the backend decides of the fairness.",
+        )
+        .to_compile_error();
+    }
+
+    if args.builder {
+        return syn::Error::new(
+            Span::call_site(),
+            "`builder` is not supported by `locks_sync!`, a blocking lock cannot be given up.",
+        )
+        .to_compile_error();
+    }
+
+    if args.condvar {
+        return syn::Error::new(
+            Span::call_site(),
+            "`condvar` is not supported by `locks_sync!`, the wait is a future.",
+        )
+        .to_compile_error();
+    }
+
+    if args.quorum.is_some() {
+        return syn::Error::new(
+            Span::call_site(),
+            "`quorum` is not supported by `locks_sync!`, a blocking lock cannot be skipped.",
+        )
+        .to_compile_error();
+    }
+
+    if args.infallible {
+        return syn::Error::new(
+            Span::call_site(),
+            "`infallible` is not supported by `locks_sync!`, the locks are acquired on the spot.",
+        )
+        .to_compile_error();
+    }
+
+    if args.items.iter().any(|t| t.upgradable) {
+        return syn::Error::new(
+            Span::call_site(),
+            "`upgrade` is not supported by `locks_sync!`, the upgrade is a future.",
+        )
+        .to_compile_error();
+    }
+
+    if args.items.iter().any(|t| t.escalated) {
+        return syn::Error::new(
+            Span::call_site(),
+            "`escalate` is not supported by `locks_sync!`, the escalation is a future.",
+        )
+        .to_compile_error();
+    }
+
+    if args.release {
+        return syn::Error::new(
+            Span::call_site(),
+            "`release` is not supported by `locks_sync!`, the release is a future.",
+        )
+        .to_compile_error();
+    }
+
+    if args.items.iter().any(|t| t.source.is_some()) {
+        return syn::Error::new(
+            Span::call_site(),
+            "Lock sources are not supported by `locks_sync!`, their guards are futures.",
+        )
+        .to_compile_error();
+    }
+
+    if args
+        .items
+        .iter()
+        .any(|t| matches!(t.access, Access::Intent))
+    {
+        return syn::Error::new(
+            Span::call_site(),
+            "`intent` is not supported by `locks_sync!`, the upgrade of the guard is a future.",
+        )
+        .to_compile_error();
+    }
+
+    if args.items.iter().any(|t| matches!(t.access, Access::Init)) {
+        return syn::Error::new(
+            Span::call_site(),
+            "`init` is not supported by `locks_sync!`, the initialization token is a future.",
+        )
+        .to_compile_error();
+    }
+
+    if args.cancel.is_some() {
+        return syn::Error::new(
+            Span::call_site(),
+            "`cancel` is not supported by `locks_sync!`, a blocking lock cannot be given up.",
+        )
+        .to_compile_error();
+    }
+
+    if args.items.iter().any(|t| t.timeout.is_some()) {
+        return syn::Error::new(
+            Span::call_site(),
+            "Timeouts are not supported by `locks_sync!`, a blocking lock cannot be given up.",
+        )
+        .to_compile_error();
+    }
+
+    #[cfg(not(feature = "std"))]
+    {
+        if let Some(error) = crate::no_std::write_unsupported(args, true) {
+            return error;
+        }
+    }
+
+    // `tuple;` skips the struct, keeping the helpers of the chain.
+    let (locks, traits, impls) = if args.tuple {
+        if let Some(error) = crate::tuple::write_unsupported(args) {
+            return error;
+        }
+
+        (crate::tuple::write_helpers(args), quote! {}, quote! {})
+    } else {
+        (
+            write_struct(args),
+            write_traits(args),
+            write_impls(args, true),
+        )
+    };
+
+    let resolve = write_resolve(args);
+
+    #[cfg(feature = "diagnostics")]
+    let resolve = crate::diagnostics::write_slow_sync(args, resolve);
+
+    #[cfg(feature = "telemetry")]
+    let resolve = crate::telemetry::write_telemetry(args, resolve);
+
+    // the guards are dropped as soon as the closure returns, the closure borrowing the struct.
+    let resolve = match &args.closure {
+        Some(closure) => quote! {{
+            fn __scope<L, F: FnOnce(&mut L) -> R, R>(locks: &mut L, f: F) -> R {
+                f(locks)
+            }
+
+            (#resolve).map(|mut __locks| __scope(&mut __locks, #closure))
+        }},
+        None => resolve,
+    };
+
+    #[cfg(feature = "reentrancy")]
+    let resolve = {
+        let check = crate::reentrancy::write_check_sync(args);
+        quote! {{ #check #resolve }}
+    };
+
+    #[cfg(feature = "graph")]
+    let resolve = {
+        let site = crate::graph::write_site(args);
+        quote! {{ #site #resolve }}
+    };
+
+    let all = quote! {{
+        #locks
+        #impls
+        #traits
+        #resolve
+    }};
+
+    crate::assert::write_assert(args, all)
+}
+
+fn write_resolve(args: &Args) -> TokenStream {
+    let ty = struct_ty(args);
+    let fields = args.items.iter().enumerate().map(|(i, t)| {
+        let field = &t.field;
+        let v = value_ident(i);
+        quote! { #field: #v }
+    });
+
+    let marker = write_markers(args, true);
+    let ident = struct_ident(args);
+    let error = error_ty();
+
+    let (ty, value) = if args.tuple {
+        (quote! { _ }, crate::tuple::write_tuple(args))
+    } else {
+        (ty, quote! { #ident { #marker #(#fields,)* } })
+    };
+
+    // the futures chain acquires the last step first
+    let steps = args.steps().into_iter().rev().map(|step| {
+        if step.len() == 1 {
+            let (i, t) = step[0];
+            let resolve = write_item_resolve(t);
+            let v = value_ident(i);
+
+            quote! { let #v = #resolve; }
+        } else {
+            let resolve = write_keyed_resolve(&step);
+            let vs = step.iter().map(|t| value_ident(t.0));
+
+            quote! { let (#(#vs,)*) = #resolve; }
+        }
+    });
+
+    quote! {
+        (|| -> Result<#ty, #error> {
+            #(#steps)*
+            Ok(#value)
+        })()
+    }
+}
+
+fn write_item_resolve(item: &Item) -> TokenStream {
+    if item.keys.is_none() {
+        let resolve = write_recipe_result(item);
+
+        #[cfg(feature = "reentrancy")]
+        let resolve = if item.reentrant {
+            crate::reentrancy::write_reentrant(item, resolve, true)
+        } else {
+            resolve
+        };
+
+        return quote! { #resolve? };
+    }
+
+    let keys = &item.keys;
+    let error = error_ty();
+    let lock = item
+        .lock
+        .as_ref()
+        .map(|lock| quote! { let __lock = #lock; });
+    let resolve = write_recipe_result(&Item {
+        key: Some(parse_quote! { __key }),
+        lock: item.lock.as_ref().map(|_| parse_quote! { __lock }),
+        keys: None,
+        ..item.clone()
+    });
+
+    quote! {{
+        #lock
+        let mut __keys = (#keys).into_iter().collect::<Vec<_>>();
+        __keys.sort();
+        __keys.dedup();
+
+        __keys
+            .into_iter()
+            .map(|__key| #resolve)
+            .collect::<Result<Vec<_>, #error>>()?
+    }}
+}
+
+/// Acquires several keys of the same lock, or several instances of it, in the order of the
+/// keys sorted by `lock_derive_runtime`, yielding the guards as a tuple in the same order as the
+/// items.
+fn write_keyed_resolve(step: &[(usize, &Item)]) -> TokenStream {
+    let (bindings, message) = write_keyed_bindings(step);
+    let message = write_error(&message);
+    let keys = keyed_idents("__k", step.len());
+    let key_values = &keys;
+    let locks = keyed_idents("__l", step.len());
+    let indexes = (0..step.len()).collect::<Vec<_>>();
+
+    let resolves = step.iter().enumerate().map(|(i, t)| {
+        let (key, lock) = (&keys[i], &locks[i]);
+
+        write_recipe_result(&Item {
+            key: t
+                .1
+                .key
+                .as_ref()
+                .map(|_| parse_quote! { #key.take().expect("key") }),
+            lock: t.1.lock.as_ref().map(|_| parse_quote! { #lock }),
+            ..t.1.clone()
+        })
+    });
+
+    let values = (0..step.len()).map(value_ident).collect::<Vec<_>>();
+    let (keys, indexes, values) = (&keys, &indexes, &values);
+
+    quote! {{
+        #bindings
+        #(let mut #keys = Some(#key_values);)*
+
+        let mut __order = [#(#indexes,)*];
+        let __keys = [#(#keys.as_ref().expect("key"),)*];
+
+        if !lock_derive_runtime::keyed::order(&__keys, &mut __order) {
+            return Err(#message);
+        }
+
+        #(let mut #values = None;)*
+
+        for __i in __order {
+            match __i {
+                #(#indexes => #values = Some(#resolves?),)*
+                _ => unreachable!(),
+            }
+        }
+
+        (#(#values.expect("slot"),)*)
+    }}
+}
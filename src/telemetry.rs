@@ -0,0 +1,21 @@
+use crate::args::Args;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Wraps the resolve future to emit a structured `log` event when the lock bundle fails to
+/// resolve, labeled with the locks and the cause of the failure, from `lock_derive_runtime`.
+pub fn write_telemetry(args: &Args, resolve: TokenStream) -> TokenStream {
+    let locks = args
+        .items
+        .iter()
+        .map(|t| format!("{}:{}", t.field, t.access.ident()))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    quote! {
+        (#resolve).map_err(|e: failure::Error| {
+            lock_derive_runtime::telemetry::failed(#locks, &e);
+            e
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use crate::args::Item;
+use crate::runtime::write_adapter;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::{Error, Token};
+
+/// The time given to a lock to be acquired, `name @ 200ms` or `name @ 2s`.
+#[derive(Clone)]
+pub struct Timeout {
+    pub millis: u64,
+    /// The timeout as spelled in the invocation, for the error message.
+    pub text: String,
+}
+
+impl Parse for Timeout {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let _: Token![@] = stream.parse()?;
+        let lit: proc_macro2::Literal = stream.parse()?;
+        let text = lit.to_string();
+
+        let (digits, scale) = if text.ends_with("ms") {
+            (&text[..text.len() - 2], 1)
+        } else if text.ends_with('s') {
+            (&text[..text.len() - 1], 1000)
+        } else {
+            (text.as_str(), 0)
+        };
+
+        match digits.replace('_', "").parse::<u64>() {
+            Ok(value) if scale > 0 => Ok(Self {
+                millis: value * scale,
+                text,
+            }),
+            _ => Err(Error::new(
+                lit.span(),
+                "Expected a duration, `200ms` or `2s`.",
+            )),
+        }
+    }
+}
+
+/// Fails the acquisition of the lock once its timeout elapses on the timer of its adapter.
+/// `since` tells whether the earlier locks of the chain are held, `__since` being the instant
+/// the first of them was acquired.
+pub fn write_timeout(item: &Item, resolve: TokenStream, since: bool) -> TokenStream {
+    let timeout = match &item.timeout {
+        Some(timeout) => timeout,
+        None => return resolve,
+    };
+
+    let millis = timeout.millis;
+    let context = format!(
+        "acquiring {} lock `{}`: timed out after {}",
+        item.access_name(),
+        item.id,
+        timeout.text
+    );
+
+    let elapsed = if since {
+        quote! {
+            failure::format_err!(
+                "{}, the earlier locks were held for {:?}",
+                #context,
+                __since.elapsed()
+            )
+        }
+    } else {
+        quote! { failure::format_err!("{}", #context) }
+    };
+
+    // with the `owners` feature, the error names the holder of the lock.
+    #[cfg(feature = "owners")]
+    let elapsed = crate::owners::write_holder(item, elapsed);
+
+    let adapter = write_adapter(&item.adapter);
+
+    quote! {
+        lock_derive_runtime::timeout::<#adapter, _, _>(
+            #resolve,
+            std::time::Duration::from_millis(#millis),
+            move || #elapsed,
+        )
+    }
+}
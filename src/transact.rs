@@ -0,0 +1,84 @@
+use crate::args::{Access, Args};
+use crate::{lifetime, struct_generics, struct_vis, write_field_ty, write_item_ty};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `LocksStaged`, the values of the write locks staged as clones next to the read locks,
+/// and `transact()`, applying the staged values to the write locks only when the closure
+/// succeeds, for `transact;`.
+pub fn write_transact(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+
+    let mut fields = Vec::new();
+    let mut stages = Vec::new();
+    let mut applies = Vec::new();
+
+    // the lifetime marker makes sure the lifetime is used, whatever the guard types are.
+    let (staged_generics, staged_ty) = if args.has_lifetime() {
+        let lifetime = lifetime();
+        fields.push(quote! { __lifetime: core::marker::PhantomData<&'s &#lifetime ()> });
+        stages.push(quote! { __lifetime: core::marker::PhantomData });
+
+        (
+            quote! { <'s, #lifetime> },
+            quote! { LocksStaged<'_, #lifetime> },
+        )
+    } else {
+        (quote! { <'s> }, quote! { LocksStaged<'_> })
+    };
+
+    for t in &args.items {
+        let field = &t.field;
+        let item_ty = write_item_ty(t);
+
+        match (t.access, &t.keys) {
+            (Access::Write, Some(_)) => {
+                fields.push(quote! { #vis #field: Vec<<#item_ty as core::ops::Deref>::Target> });
+                stages.push(quote! { #field: self.#field.iter().map(|g| (**g).clone()).collect() });
+                applies.push(quote! {
+                    for (g, v) in self.#field.iter_mut().zip(__staged.#field) {
+                        **g = v;
+                    }
+                });
+            }
+            (Access::Write, None) => {
+                fields.push(quote! { #vis #field: <#item_ty as core::ops::Deref>::Target });
+                stages.push(quote! { #field: (*self.#field).clone() });
+                applies.push(quote! { *self.#field = __staged.#field; });
+            }
+            _ => {
+                let field_ty = write_field_ty(t);
+                fields.push(quote! { #vis #field: &'s #field_ty });
+                stages.push(quote! { #field: &self.#field });
+            }
+        }
+    }
+
+    quote! {
+        #[allow(dead_code)]
+        #vis struct LocksStaged #staged_generics {
+            #(#fields,)*
+        }
+
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            /// Runs `f` against the staged values of the write locks, then applies them to the
+            /// locks only if `f` succeeds. The locks are released in both cases.
+            #vis fn transact<R, E>(
+                mut self,
+                f: impl FnOnce(&mut #staged_ty) -> Result<R, E>,
+            ) -> Result<R, E> {
+                let (__result, __staged) = {
+                    let mut __staged = LocksStaged { #(#stages,)* };
+                    let __result = f(&mut __staged);
+                    (__result, __staged)
+                };
+
+                let __result = __result?;
+                #(#applies)*
+                Ok(__result)
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::args::Args;
+use crate::{value_ident, write_projection};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::Error;
+
+/// The guards resolved by the chain, in the order of the sections rather than by identity, for
+/// `tuple;`, followed by the marker bumping the versions of the write locks once dropped.
+pub fn write_tuple(args: &Args) -> TokenStream {
+    let mut values = args.items.iter().enumerate().collect::<Vec<_>>();
+    values.sort_by_key(|t| t.1.index);
+
+    let values = values.into_iter().map(|t| value_ident(t.0));
+
+    let written = crate::versioned::write_written(args);
+    quote! { (#(#values,)* #written) }
+}
+
+/// Rejects what is generated on the struct, which `tuple;` skips.
+pub fn write_unsupported(args: &Args) -> Option<TokenStream> {
+    if args.blocking
+        || args.builder
+        || args.optimistic
+        || args.hierarchy
+        || args.transact
+        || args.condvar
+        || args.release
+        || args.erased
+        || args.report_hold.is_some()
+        || !args.impls.is_empty()
+    {
+        let error = Error::new(
+            Span::call_site(),
+            "`tuple` skips the struct, it cannot be combined with `blocking`, `builder`, \
+             `optimistic`, `hierarchy`, `transact`, `condvar`, `release`, `erased`, \
+             `report_hold` or `impl`.",
+        );
+
+        return Some(error.to_compile_error());
+    }
+
+    None
+}
+
+/// The helpers of the chain found in the struct otherwise, the projection of the guards.
+pub fn write_helpers(args: &Args) -> TokenStream {
+    let projection = if args.items.iter().any(|t| t.projection.is_some()) {
+        write_projection()
+    } else {
+        quote! {}
+    };
+
+    #[cfg(not(feature = "std"))]
+    let projection = {
+        let prelude = crate::no_std::write_prelude();
+        quote! { #prelude #projection }
+    };
+
+    projection
+}
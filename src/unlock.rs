@@ -0,0 +1,79 @@
+use crate::args::Args;
+use crate::{error_ty, lifetime, struct_generics, struct_vis};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `unlock_all()`, releasing the guards one by one in the order of their release, the
+/// reverse order of their acquisition unless given by `drop_order: [..]`. `sync` tells whether
+/// the locks are resolved by `locks_sync!`, where the guards are dropped on the spot, while
+/// `locks!` returns a future completing once they are all released, through their `release` arm
+/// for `release;`.
+pub fn write_unlock_all(args: &Args, sync: bool) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+    let error = error_ty();
+
+    let drops = args.release_order().into_iter().map(|t| {
+        let field = &t.field;
+
+        match t.keys {
+            // the keys are acquired in order, the last key is released first.
+            Some(_) => quote! {
+                for __guard in #field.into_iter().rev() {
+                    __release(__guard);
+                }
+            },
+            None => quote! { __release(#field); },
+        }
+    });
+
+    let fields = args.items.iter().map(|t| &t.field);
+
+    // a guard may be a reference or a copy, which `drop` would be linted for.
+    let drops = quote! {
+        fn __release<T>(_guard: T) {}
+        #(#drops)*
+    };
+
+    let unlock_all = if sync {
+        quote! {
+            /// Releases the locks in the order of the drop of the struct.
+            #vis fn unlock_all(self) {
+                let Self { #(#fields,)* .. } = self;
+                #drops
+            }
+        }
+    } else if args.release {
+        let bound = if args.has_lifetime() {
+            let lifetime = lifetime();
+            quote! { + #lifetime }
+        } else {
+            quote! {}
+        };
+
+        quote! {
+            /// Releases the locks through the `release` arm of their recipe, in the order of the
+            /// drop of the struct, see `release()`.
+            #vis fn unlock_all(self) -> impl futures::Future<Item = (), Error = #error> #bound {
+                self.release()
+            }
+        }
+    } else {
+        quote! {
+            /// Releases the locks in the order of the drop of the struct. The future completes
+            /// once all of them are released.
+            #vis fn unlock_all(self) -> impl futures::Future<Item = (), Error = #error> {
+                let Self { #(#fields,)* .. } = self;
+                #drops
+                futures::future::ok::<_, #error>(())
+            }
+        }
+    };
+
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            #unlock_all
+        }
+    }
+}
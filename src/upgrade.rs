@@ -0,0 +1,116 @@
+use crate::args::{Access, Args, Item};
+use crate::resource::camel_case;
+use crate::{error_ty, struct_ident, struct_vis, write_chain, write_struct_items};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::Ident;
+
+/// The locks given `upgrade_<field>()`: the read locks of `upgrade: [..]` and the locks of
+/// `intent: [..]`, but in the struct already upgraded.
+pub fn upgradable(args: &Args) -> impl Iterator<Item = &Item> {
+    args.items.iter().filter(move |t| {
+        t.upgradable || (matches!(t.access, Access::Intent) && args.upgraded.is_none())
+    })
+}
+
+/// The struct holding the locks once `item` is upgraded, the same locks with `item` written.
+/// The lock of an intent is written by an `Upgraded` guard, keeping the intent.
+pub fn upgraded_args(args: &Args, item: &Item) -> Args {
+    let mut upgraded = args.clone();
+    let field = &item.field;
+
+    for t in &mut upgraded.items {
+        t.upgradable = false;
+        t.escalated = false;
+
+        if t.id == item.id {
+            t.upgraded = matches!(t.access, Access::Intent);
+            t.access = Access::Write;
+        }
+    }
+
+    // a struct of its own, named after the struct upgraded.
+    upgraded.ident = args.ident.as_ref().map(|ident| {
+        Ident::new(
+            &format!("{}{}Write", ident, camel_case(&field.to_string())),
+            ident.span(),
+        )
+    });
+
+    let tokens = &args.tokens;
+    upgraded.tokens = quote! { #tokens upgrade #field };
+    upgraded.upgraded = Some(field.clone());
+    upgraded
+}
+
+/// Writes `upgrade_<field>()` for the read locks of `upgrade: [..]` and the locks of
+/// `intent: [..]`, with the structs they resolve to. A read guard cannot be traded for a write
+/// guard in place without risking a deadlock with another reader doing the same, so all the
+/// locks are released and acquired again in order, the lock upgraded being written. Since
+/// another task may write in between, the revalidation closure checks that what was read still
+/// holds. An intent is kept meanwhile instead, no other intent being able to upgrade.
+pub fn write_upgrades(args: &Args) -> TokenStream {
+    let ident = struct_ident(args);
+    let vis = struct_vis(args);
+    let error = error_ty();
+
+    let upgrades = upgradable(args).map(|item| {
+        let upgraded = upgraded_args(args, item);
+        let upgraded_ident = struct_ident(&upgraded);
+        let items = write_struct_items(&upgraded, write_chain(&upgraded));
+        let method = Ident::new(&format!("upgrade_{}", item.field), Span::call_site());
+
+        if let Access::Intent = item.access {
+            let field = &item.field;
+
+            return quote! {
+                #items
+
+                #[allow(dead_code)]
+                impl #ident {
+                    /// Releases the locks and acquires them again in order, the lock of the
+                    /// intent being written, the intent being kept meanwhile.
+                    #vis fn #method(
+                        mut self,
+                    ) -> impl futures::Future<Item = #upgraded_ident, Error = #error> {
+                        use futures::Future;
+
+                        let __token = self.#field.take_token();
+
+                        self.unlock_all()
+                            .and_then(|_| #upgraded_ident::resolve())
+                            .map(move |mut locks| {
+                                locks.#field.keep_token(__token);
+                                locks
+                            })
+                    }
+                }
+            };
+        }
+
+        quote! {
+            #items
+
+            #[allow(dead_code)]
+            impl #ident {
+                /// Releases the locks and acquires them again in order, the lock upgraded being
+                /// written, then runs `revalidate` on the guards.
+                #vis fn #method<R>(
+                    self,
+                    revalidate: R,
+                ) -> impl futures::Future<Item = #upgraded_ident, Error = #error>
+                where
+                    R: FnOnce(&#upgraded_ident) -> Result<(), #error>,
+                {
+                    use futures::Future;
+
+                    self.unlock_all()
+                        .and_then(|_| #upgraded_ident::resolve())
+                        .and_then(|locks| revalidate(&locks).map(|_| locks))
+                }
+            }
+        }
+    });
+
+    quote! { #(#upgrades)* }
+}
@@ -0,0 +1,114 @@
+use crate::args::{Access, Args, Item};
+use crate::{struct_generics, struct_vis};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `validate()` for the locks of `versioned: [..]`, and `commit()` bumping the versions
+/// of the write locks.
+pub fn write_versioned(args: &Args) -> TokenStream {
+    let (impl_generics, ty) = struct_generics(args);
+    let vis = struct_vis(args);
+
+    let versioned = args
+        .items
+        .iter()
+        .filter(|t| matches!(t.access, Access::Versioned))
+        .map(|t| &t.field)
+        .collect::<Vec<_>>();
+
+    if versioned.is_empty() && !has_written(args) {
+        return quote! {};
+    }
+
+    let validate = if versioned.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            /// Whether none of the locks of `versioned: [..]` was written since their values
+            /// were read.
+            #vis fn validate(&self) -> bool {
+                true #(&& self.#versioned.is_current())*
+            }
+        }
+    };
+
+    let commit = if has_written(args) {
+        let commit = write_commit(args);
+
+        quote! {
+            /// Bumps the versions of the write locks right away, telling the values read through
+            /// `versioned: [..]` that they are no longer current, as the struct does anyway once
+            /// dropped.
+            #vis fn commit(&self) {
+                #commit
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ty {
+            #validate
+            #commit
+        }
+    }
+}
+
+/// Whether the struct holds write locks with a version, which needs `std`.
+pub fn has_written(args: &Args) -> bool {
+    cfg!(feature = "std") && !written_ids(args).is_empty()
+}
+
+/// The marker bumping the versions of the write locks once dropped, `None` without them. The
+/// versions are kept by lock identity in the runtime crate. Borrowed, keyed and sourced locks
+/// have no identity of their own and are not versioned, nor are the replicas of a quorum.
+pub fn write_written(args: &Args) -> Option<TokenStream> {
+    if !has_written(args) {
+        return None;
+    }
+
+    let ids = written_ids(args);
+    Some(quote! { lock_derive_runtime::version::written(module_path!(), &[#(#ids),*]) })
+}
+
+/// Bumps the versions of the write locks, for `commit()`.
+fn write_commit(args: &Args) -> TokenStream {
+    let ids = written_ids(args);
+
+    quote! {
+        lock_derive_runtime::version::commit(module_path!(), &[#(#ids),*]);
+    }
+}
+
+/// The value of a lock of `versioned: [..]`, with the version of the lock read under its guard.
+pub fn write_versioned_value(item: &Item, resolve: TokenStream) -> TokenStream {
+    let id = write_id(item);
+
+    quote! {
+        #resolve.map(|__guard| {
+            lock_derive_runtime::Versioned::new((*__guard).clone(), module_path!(), #id)
+        })
+    }
+}
+
+fn written_ids(args: &Args) -> Vec<TokenStream> {
+    args.items
+        .iter()
+        .filter(|t| {
+            t.access.is_exclusive()
+                && t.lock.is_none()
+                && t.key.is_none()
+                && t.keys.is_none()
+                && t.source.is_none()
+                && !t.quorum
+        })
+        .map(write_id)
+        .collect()
+}
+
+fn write_id(item: &Item) -> TokenStream {
+    let id = &item.id;
+    quote! { #id }
+}
@@ -0,0 +1,202 @@
+use crate::args::{Access, Args};
+use crate::write_all;
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, token, Error, Ident, Token};
+
+/// The arguments of the attribute, `read(accounts), write(users)`, or a flag such as `owned`.
+pub struct Contract(Args);
+
+impl Parse for Contract {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let mut flags = TokenStream::new();
+        let mut sections = TokenStream::new();
+
+        for Clause(name, content) in Punctuated::<Clause, Token![,]>::parse_terminated(stream)? {
+            match content {
+                Some(content) => sections.extend(quote! { #name: [#content], }),
+                None => flags.extend(quote! { #name; }),
+            }
+        }
+
+        let args = syn::parse2(quote! { #flags #sections })?;
+        Ok(Contract(check_args(args)?))
+    }
+}
+
+/// The arguments of `with_locks_block!`, the sections of `locks!` followed by `=> { body }`.
+pub struct Block {
+    args: Args,
+    body: TokenTree,
+}
+
+impl Parse for Block {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let mut tokens = stream
+            .parse::<TokenStream>()?
+            .into_iter()
+            .collect::<Vec<_>>();
+        let body = tokens.pop();
+        let arrow = tokens.split_off(tokens.len().saturating_sub(2));
+
+        match (&body, arrow.as_slice()) {
+            (Some(TokenTree::Group(body)), [TokenTree::Punct(eq), TokenTree::Punct(gt)])
+                if body.delimiter() == Delimiter::Brace
+                    && eq.as_char() == '='
+                    && gt.as_char() == '>' => {}
+            _ => return Err(stream.error("Expected `=> { .. }` after the sections.")),
+        }
+
+        let args = syn::parse2(tokens.into_iter().collect())?;
+
+        Ok(Self {
+            args: check_args(args)?,
+            body: body.expect("body"),
+        })
+    }
+}
+
+fn check_args(mut args: Args) -> Result<Args> {
+    // the struct is not exposed, its guards are bound to locals.
+    args.no_traits = true;
+
+    if !args.impls.is_empty() {
+        return Err(Error::new(
+            Span::call_site(),
+            "`impl` is not supported by `with_locks`, the struct is not exposed.",
+        ));
+    }
+
+    if args.builder {
+        return Err(Error::new(
+            Span::call_site(),
+            "`builder` is not supported by `with_locks`, the locks are awaited on entry.",
+        ));
+    }
+
+    if args.tuple {
+        return Err(Error::new(
+            Span::call_site(),
+            "`tuple` is not supported by `with_locks`, the guards are bound by field.",
+        ));
+    }
+
+    if let Some(group) = args.includes.first() {
+        return Err(Error::new(
+            group.span(),
+            "Groups are not supported by `with_locks`, the guards could not be named.",
+        ));
+    }
+
+    Ok(args)
+}
+
+struct Clause(Ident, Option<TokenStream>);
+
+impl Parse for Clause {
+    fn parse(stream: ParseStream) -> Result<Self> {
+        let name = stream.parse()?;
+
+        let content = if stream.peek(token::Paren) {
+            let content;
+            parenthesized!(content in stream);
+            Some(content.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Clause(name, content))
+    }
+}
+
+/// Rewrites the body of an `async fn` to acquire the locks first and bind the guards to the
+/// names of their fields.
+pub fn write_with_locks(contract: &Contract, item: TokenStream) -> Result<TokenStream> {
+    let args = &contract.0;
+    let mut tokens = item.into_iter().collect::<Vec<_>>();
+
+    let body = match tokens.pop() {
+        Some(TokenTree::Group(ref body)) if body.delimiter() == Delimiter::Brace => body.clone(),
+        _ => {
+            return Err(Error::new_spanned(
+                tokens.into_iter().collect::<TokenStream>(),
+                "Expected an `async fn`.",
+            ))
+        }
+    };
+
+    let is_async = tokens
+        .iter()
+        .any(|t| matches!(t, TokenTree::Ident(ident) if ident == "async"));
+
+    if !is_async {
+        return Err(Error::new_spanned(
+            tokens.into_iter().collect::<TokenStream>(),
+            "Expected an `async fn`.",
+        ));
+    }
+
+    let acquire = write_acquire(args);
+
+    Ok(quote! {
+        #(#tokens)* {
+            #acquire
+            #body
+        }
+    })
+}
+
+/// Awaits the locks and runs the body with the guards bound to the names of their fields, the
+/// guards being dropped at its closing brace.
+pub fn write_block(block: &Block) -> TokenStream {
+    let acquire = write_acquire(&block.args);
+    let body = &block.body;
+
+    quote! {{
+        #acquire
+        #body
+    }}
+}
+
+/// Awaits the locks and binds the guards to the names of their fields.
+fn write_acquire(args: &Args) -> TokenStream {
+    let resolve = write_all(args);
+    let compat = write_compat();
+
+    let bindings = args.items.iter().map(|item| {
+        let field = &item.field;
+
+        match item.access {
+            Access::Write | Access::BlockingWrite | Access::Checkout => {
+                quote! { let mut #field = __locks.#field; }
+            }
+            _ => quote! { let #field = __locks.#field; },
+        }
+    });
+
+    // the locks of `infallible;` are already awaited from a `std::future::Future`.
+    let locks = if args.infallible {
+        quote! { #resolve.await }
+    } else {
+        quote! {{
+            #compat
+            __Compat(futures::executor::spawn(#resolve)).await?
+        }}
+    };
+
+    quote! {
+        let __locks = #locks;
+
+        #(#bindings)*
+    }
+}
+
+/// Brings `__Compat` of the runtime crate into scope, polling the futures 0.1 chain from a std
+/// future.
+pub fn write_compat() -> TokenStream {
+    quote! {
+        use lock_derive_runtime::Compat as __Compat;
+    }
+}
@@ -0,0 +1,50 @@
+use crate::args::Args;
+use crate::{error_ty, struct_ident, struct_vis};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Writes `yield_locks()` and `yield_locks_after()`, giving the other tasks a turn in a long
+/// critical section: the guards are released, then the chain resolves the locks again in the
+/// same order, so no guard is ever held out of order. Only the structs resolved from their
+/// recipes, with `resolve()`, can be resolved again.
+pub fn write_yield_locks(args: &Args) -> TokenStream {
+    let ident = struct_ident(args);
+    let vis = struct_vis(args);
+    let error = error_ty();
+
+    quote! {
+        #[allow(dead_code)]
+        impl #ident {
+            /// Releases the locks, yields to the scheduler and acquires the locks again.
+            #vis fn yield_locks(self) -> impl futures::Future<Item = Self, Error = #error> {
+                let mut yielded = false;
+
+                self.yield_locks_after(futures::future::poll_fn(move || {
+                    if yielded {
+                        return Ok::<_, #error>(futures::Async::Ready(()));
+                    }
+
+                    yielded = true;
+                    futures::task::current().notify();
+                    Ok(futures::Async::NotReady)
+                }))
+            }
+
+            /// Releases the locks, awaits `future` and acquires the locks again.
+            #vis fn yield_locks_after<F>(
+                self,
+                future: F,
+            ) -> impl futures::Future<Item = Self, Error = #error>
+            where
+                F: futures::IntoFuture<Item = ()>,
+                F::Error: Into<#error>,
+            {
+                use futures::Future;
+
+                self.unlock_all()
+                    .and_then(|_| future.into_future().map_err(Into::into))
+                    .and_then(|_| Self::resolve())
+            }
+        }
+    }
+}
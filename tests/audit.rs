@@ -0,0 +1,121 @@
+//! The events of the `audit` feature, logged by the runtime crate without `runtime!()`.
+#![cfg(feature = "audit")]
+
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks, locks_sync};
+use log::{Log, Metadata, Record};
+use std::sync::{Mutex, MutexGuard, Once};
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<i32> = RwLock::new(1);
+    static ref INVOICES: Mutex<i32> = Mutex::new(2);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<i32> = USERS);
+
+macro_rules! invoices {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { INVOICES.lock().map_err(|_| failure::format_err!("Lock poisoned")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+/// The events of the `lock_derive::audit` target: the message, the locks and the owner.
+static EVENTS: Mutex<Vec<(String, String, String)>> = Mutex::new(Vec::new());
+
+struct Audit;
+
+impl Log for Audit {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "lock_derive::audit"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let kv = |key: &str| {
+                record
+                    .key_values()
+                    .get(log::kv::Key::from(key))
+                    .map(|t| t.to_string())
+                    .unwrap_or_default()
+            };
+
+            EVENTS
+                .lock()
+                .unwrap()
+                .push((record.args().to_string(), kv("locks"), kv("owner")));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The events logged for the locks given, the tests running in parallel.
+fn events(locks: &str) -> Vec<(String, String)> {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        log::set_logger(&Audit).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+    });
+
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|t| t.1 == locks)
+        .map(|t| (t.0.clone(), t.2.clone()))
+        .collect()
+}
+
+#[test]
+fn logs_the_acquisition_and_the_release() {
+    events("");
+
+    let locks = block_on_all(locks!(no_traits; read: [users], write: [accounts])).unwrap();
+    let acquired = events("accounts:write,users:read");
+    assert_eq!(1, acquired.len());
+    assert_eq!("locks acquired", acquired[0].0);
+    assert!(acquired[0].1.starts_with("task "), "{}", acquired[0].1);
+
+    drop(locks);
+    let released = events("accounts:write,users:read");
+    assert_eq!(2, released.len());
+    assert_eq!("locks released", released[1].0);
+    assert_eq!(acquired[0].1, released[1].1);
+}
+
+#[test]
+fn names_the_thread_with_locks_sync() {
+    events("");
+
+    let locks = locks_sync!(write: [invoices]).unwrap();
+    drop(locks);
+
+    let owner = format!("thread {:?}", std::thread::current().id());
+    let expected = vec![
+        ("locks acquired".to_string(), owner.clone()),
+        ("locks released".to_string(), owner),
+    ];
+
+    assert_eq!(expected, events("invoices:write"));
+}
+
+#[test]
+fn logs_the_release_of_a_struct_dropped_by_the_chain() {
+    events("");
+
+    // the chain is built by a future resolving to the struct, dropped by `map()`.
+    let future = locks!(no_traits; write: [users]).map(drop);
+    block_on_all(future).unwrap();
+
+    let messages = events("users:write")
+        .into_iter()
+        .map(|t| t.0)
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec!["locks acquired", "locks released"], messages);
+}
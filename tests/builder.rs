@@ -0,0 +1,44 @@
+//! The attempts of `builder;` given up by its timeout, on the locks of `define_lock!`.
+
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::current_thread::Runtime;
+
+lazy_static::lazy_static! {
+    static ref ORDERS: RwLock<i32> = RwLock::new(10);
+    static ref STOCK: RwLock<u32> = RwLock::new(1);
+}
+
+define_lock!(orders: RwLock<i32> = ORDERS);
+define_lock!(stock: RwLock<u32> = STOCK);
+
+#[test]
+fn the_attempts_timed_out_leave_the_lock_usable() {
+    let mut runtime = Runtime::new().unwrap();
+    let held = STOCK.try_write().unwrap();
+
+    let failed = Arc::new(Mutex::new(0));
+    let observed = failed.clone();
+
+    let future = locks!(builder; write: [orders, stock])
+        .with_timeout(Duration::from_millis(50))
+        .with_retry(1)
+        .with_observer(move |e, _| *observed.lock().unwrap() += e.is_some() as usize)
+        .resolve();
+
+    let e = runtime.block_on(future).err().unwrap();
+    assert_eq!("resolving the locks timed out after 50ms", e.to_string());
+    assert_eq!(2, *failed.lock().unwrap());
+
+    // the release grants the lock to the attempts given up, which release it at once.
+    drop(held);
+
+    let future = locks!(builder; write: [orders, stock])
+        .with_timeout(Duration::from_secs(1))
+        .resolve()
+        .map(|l| (*l.orders, *l.stock));
+    assert_eq!((10, 1), runtime.block_on(future).unwrap());
+}
@@ -0,0 +1,47 @@
+//! The acquisitions abandoned by `cancel: token`, on the locks of `define_lock!`.
+
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use lock_derive_runtime::{Cancelled, Compat};
+use std::time::{Duration, Instant};
+use tokio::runtime::current_thread::Runtime;
+use tokio::timer::Delay;
+
+lazy_static::lazy_static! {
+    static ref INVOICES: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<u32> = RwLock::new(1);
+}
+
+define_lock!(invoices: RwLock<i32> = INVOICES);
+define_lock!(users: RwLock<u32> = USERS);
+
+// a token cancelled once its delay has elapsed.
+struct Shutdown(Duration);
+
+impl Shutdown {
+    async fn cancelled(&self) {
+        let _ = Compat::new(Delay::new(Instant::now() + self.0)).await;
+    }
+}
+
+#[test]
+fn the_acquisition_cancelled_leaves_the_lock_usable() {
+    let mut runtime = Runtime::new().unwrap();
+    let held = USERS.try_write().unwrap();
+
+    let token = Shutdown(Duration::from_millis(50));
+    let future = locks!(cancel: token, write: [invoices, users]);
+    let e = runtime.block_on(future).err().unwrap();
+
+    assert!(e.downcast_ref::<Cancelled>().is_some(), "{}", e);
+    assert!(INVOICES.try_write().is_ok());
+
+    // the release grants the lock to the acquisition cancelled, which releases it at once.
+    drop(held);
+
+    let locks = runtime
+        .block_on(locks!(write: [invoices, users]).map(|l| (*l.invoices, *l.users)))
+        .unwrap();
+    assert_eq!((10, 1), locks);
+}
@@ -0,0 +1,96 @@
+//! The warnings of the `diagnostics` feature, logged by the runtime crate without `runtime!()`.
+#![cfg(feature = "diagnostics")]
+
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use log::{Log, Metadata, Record};
+use std::sync::{Mutex, Once};
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::current_thread::Runtime;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<u32> = RwLock::new(1);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<u32> = USERS);
+
+/// The warnings of the `lock_derive::diagnostics` target.
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Diagnostics;
+
+impl Log for Diagnostics {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "lock_derive::diagnostics"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            WARNINGS.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The warnings logged about the locks given, the tests running in parallel.
+fn warnings(what: &str) -> Vec<String> {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        log::set_logger(&Diagnostics).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+    });
+
+    WARNINGS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|t| t.starts_with(what))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn warns_when_the_locks_are_slow_to_acquire() {
+    warnings("");
+
+    let mut runtime = Runtime::new().unwrap();
+    let held = USERS.try_write().unwrap();
+
+    let release = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(held);
+    });
+
+    let locks = runtime
+        .block_on(locks!(diagnostics: [acquire @ 20ms], write: [users]))
+        .unwrap();
+    release.join().unwrap();
+    drop(locks);
+
+    let logged = warnings("acquiring users:write took ");
+    assert_eq!(1, logged.len());
+    assert!(logged[0].ends_with(", over 20ms"), "{}", logged[0]);
+}
+
+#[test]
+fn warns_when_the_struct_is_held_for_long() {
+    warnings("");
+
+    let mut runtime = Runtime::new().unwrap();
+    let locks = runtime
+        .block_on(locks!(diagnostics: [hold @ 20ms], read: [accounts]))
+        .unwrap();
+
+    assert!(warnings("holding accounts:read").is_empty());
+    thread::sleep(Duration::from_millis(50));
+    drop(locks);
+
+    let logged = warnings("holding accounts:read took ");
+    assert_eq!(1, logged.len());
+    assert!(logged[0].ends_with(", over 20ms"), "{}", logged[0]);
+}
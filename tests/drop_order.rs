@@ -0,0 +1,116 @@
+//! The order in which the guards of the struct are released, reversed from their acquisition or
+//! given by `drop_order: [..]`.
+
+use futures::Future;
+use std::cell::RefCell;
+
+thread_local! {
+    static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn record(event: String) {
+    EVENTS.with(|e| e.borrow_mut().push(event));
+}
+
+fn take_events() -> Vec<String> {
+    EVENTS.with(|e| e.borrow_mut().drain(..).collect())
+}
+
+struct Guard(&'static str);
+
+impl Guard {
+    fn acquire(name: &'static str) -> futures::future::FutureResult<Self, failure::Error> {
+        record(format!("acquire {}", name));
+        futures::future::ok(Guard(name))
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        record(format!("release {}", self.0));
+    }
+}
+
+impl std::ops::Deref for Guard {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+macro_rules! accounts {
+    (ty write) => {
+        Guard
+    };
+    (resolve write) => {
+        Guard::acquire("accounts")
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! orders {
+    (ty write) => {
+        Guard
+    };
+    (resolve write) => {
+        Guard::acquire("orders")
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! users {
+    (ty write) => {
+        Guard
+    };
+    (resolve write) => {
+        Guard::acquire("users")
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+#[test]
+fn the_guards_are_released_in_the_reverse_order_of_their_acquisition() {
+    let locks = lock_derive::locks!(write: [users, accounts, orders])
+        .wait()
+        .unwrap();
+    assert_eq!(
+        take_events(),
+        ["acquire users", "acquire orders", "acquire accounts"]
+    );
+
+    drop(locks);
+    assert_eq!(
+        take_events(),
+        ["release accounts", "release orders", "release users"]
+    );
+}
+
+#[test]
+fn drop_order_releases_the_fields_named_first() {
+    let locks = lock_derive::locks!(drop_order: [users], write: [accounts, orders, users])
+        .wait()
+        .unwrap();
+    take_events();
+    drop(locks);
+
+    assert_eq!(
+        take_events(),
+        ["release users", "release accounts", "release orders"]
+    );
+}
+
+#[test]
+fn unlock_all_follows_the_order_of_the_drop() {
+    let locks = lock_derive::locks!(drop_order: [users], write: [accounts, orders, users])
+        .wait()
+        .unwrap();
+    take_events();
+
+    locks.unlock_all().wait().unwrap();
+
+    assert_eq!(
+        take_events(),
+        ["release users", "release accounts", "release orders"]
+    );
+}
@@ -0,0 +1,81 @@
+//! The call sites of the `graph` feature, registered through the runtime crate.
+#![cfg(feature = "graph")]
+
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use lock_derive_runtime::{lock_graph_dot, lock_graph_json, lock_sites};
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<i32> = RwLock::new(1);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<i32> = USERS);
+
+// the sites are registered at compile time, whether the functions run or not.
+#[allow(dead_code)]
+fn transfer() -> impl Future<Item = (), Error = failure::Error> {
+    locks!(no_traits; read: [users], write: [accounts]).map(drop)
+}
+
+#[allow(dead_code)]
+fn count() -> impl Future<Item = (), Error = failure::Error> {
+    locks!(read: [users]).map(drop)
+}
+
+#[test]
+fn registers_the_sites_of_the_crate() {
+    let sites = lock_sites();
+    assert_eq!(2, sites.len());
+
+    let locks = sites[0]
+        .locks
+        .iter()
+        .map(|t| (t.name, t.access))
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![("users", "read"), ("accounts", "write")], locks);
+    assert_eq!("tests/graph.rs", sites[0].file);
+    assert!(sites[0].line < sites[1].line);
+}
+
+#[test]
+fn renders_the_graph_as_dot() {
+    let line = lock_sites()[0].line;
+    let column = lock_sites()[0].column;
+
+    let expected = format!(
+        "digraph locks {{\n    \"users\";\n    \"accounts\";\n    \"users\" -> \"accounts\" \
+         [label=\"tests/graph.rs:{}:{} (read then write)\"];\n    \"users\";\n}}",
+        line, column
+    );
+
+    assert_eq!(expected, lock_graph_dot());
+}
+
+#[test]
+fn renders_the_sites_as_json() {
+    let json = lock_graph_json();
+
+    assert!(
+        json.starts_with("[{\"file\":\"tests/graph.rs\",\"line\":"),
+        "{}",
+        json
+    );
+    assert!(
+        json.contains(
+            "\"locks\":[{\"name\":\"users\",\"access\":\"read\"},\
+             {\"name\":\"accounts\",\"access\":\"write\"}]}"
+        ),
+        "{}",
+        json
+    );
+
+    assert!(
+        json.ends_with("\"locks\":[{\"name\":\"users\",\"access\":\"read\"}]}]"),
+        "{}",
+        json
+    );
+}
@@ -0,0 +1,36 @@
+//! The upgrade of the locks of `intent: [..]`, resolved again in order with the intent kept.
+
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<u32> = RwLock::new(1);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<u32> = USERS);
+
+#[test]
+fn the_upgrade_resolves_the_locks_again_keeping_the_intent() {
+    let intent = lock_derive_runtime::intent::intent(module_path!(), "accounts");
+
+    let future = locks!(intent: [accounts], write: [users]).and_then(|locks| {
+        assert!(intent.try_lock().is_err());
+        locks.upgrade_accounts()
+    });
+
+    let mut locks = block_on_all(future).unwrap();
+    *locks.accounts -= 5;
+    *locks.users += 1;
+
+    // the intent is kept until the upgraded struct is dropped.
+    assert!(intent.try_lock().is_err());
+    drop(locks);
+    assert!(intent.try_lock().is_ok());
+
+    assert_eq!(5, *ACCOUNTS.try_read().unwrap());
+    assert_eq!(2, *USERS.try_read().unwrap());
+}
@@ -0,0 +1,68 @@
+//! The acquisitions of `locks_sync!` model-checked by `loom`, run with
+//! `RUSTFLAGS="--cfg loom" cargo test --test loom`.
+#![cfg(loom)]
+
+use loom::sync::{Mutex, MutexGuard};
+
+lock_derive::runtime!();
+
+macro_rules! accounts {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { ACCOUNTS.lock().map_err(|_| failure::err_msg("Lock poisoned")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! users {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { USERS.lock().map_err(|_| failure::err_msg("Lock poisoned")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+loom::lazy_static! {
+    static ref ACCOUNTS: Mutex<i32> = Mutex::new(10);
+    static ref USERS: Mutex<i32> = Mutex::new(1);
+}
+
+#[test]
+fn invocations_listing_the_locks_in_any_order_never_deadlock() {
+    loom::model(|| {
+        let t = loom::thread::spawn(|| {
+            *lock_derive::locks_sync!(write: [accounts, users])
+                .unwrap()
+                .accounts += 1;
+        });
+
+        *lock_derive::locks_sync!(write: [users, accounts])
+            .unwrap()
+            .users += 1;
+
+        t.join().unwrap();
+    });
+}
+
+// in debug builds, the `reentrancy` feature panics on the out of order acquisition before the
+// deadlock.
+#[test]
+#[cfg_attr(
+    not(all(feature = "reentrancy", debug_assertions)),
+    should_panic(expected = "deadlock")
+)]
+#[cfg_attr(
+    all(feature = "reentrancy", debug_assertions),
+    should_panic(expected = "acquired while holding")
+)]
+fn nested_invocations_in_opposite_orders_deadlock() {
+    loom::model(|| {
+        let t = loom::thread::spawn(|| {
+            let accounts = lock_derive::locks_sync!(write: [accounts]).unwrap();
+            let users = lock_derive::locks_sync!(write: [users]).unwrap();
+            drop((users, accounts));
+        });
+
+        let users = lock_derive::locks_sync!(write: [users]).unwrap();
+        let accounts = lock_derive::locks_sync!(write: [accounts]).unwrap();
+        drop((accounts, users));
+
+        t.join().unwrap();
+    });
+}
@@ -0,0 +1,58 @@
+//! The code generated without the default `std` feature, run with
+//! `cargo test --no-default-features --test no_std`.
+#![cfg(not(feature = "std"))]
+
+extern crate alloc;
+
+use futures::Future;
+use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Debug, PartialEq)]
+pub struct LockError(&'static str);
+
+impl From<&'static str> for LockError {
+    fn from(message: &'static str) -> Self {
+        LockError(message)
+    }
+}
+
+lock_derive::runtime!(error: LockError);
+
+macro_rules! accounts {
+    (ty read) => { RwLockReadGuard<i32> };
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve read) => { ACCOUNTS.read().map_err(|_| LockError("accounts")) };
+    (resolve write) => { ACCOUNTS.write().map_err(|_| LockError("accounts")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! users {
+    (ty read) => { RwLockReadGuard<i32> };
+    (resolve read) => { USERS.read().map_err(|_| LockError("users")) };
+    (resolve read [$key:expr]) => { USERS.read().map_err(|_| LockError("users")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<i32> = RwLock::new(1);
+}
+
+#[test]
+fn locks_resolve_with_the_error_of_the_runtime() {
+    let mut locks = lock_derive::locks!(read: [users], write: [accounts])
+        .wait()
+        .unwrap();
+
+    *locks.accounts += *locks.users;
+    assert_eq!(11, *locks.accounts);
+    assert_eq!([("users", "read"), ("accounts", "write")], locks.order());
+}
+
+#[test]
+fn errors_of_the_chain_are_built_from_a_message() {
+    let key = 1;
+    let future = lock_derive::locks!(read: [users[1], users[key]]);
+    let e = future.wait().err().unwrap();
+    assert!(e.0.contains("users"));
+}
@@ -0,0 +1,111 @@
+//! The order in which the locks are acquired, whatever the order of their sections, their names
+//! and their keys in the invocation.
+
+use futures::Future;
+use std::cell::RefCell;
+
+thread_local! {
+    static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn take_events() -> Vec<String> {
+    EVENTS.with(|e| e.borrow_mut().drain(..).collect())
+}
+
+struct Guard(String);
+
+impl Guard {
+    fn acquire(name: String) -> futures::future::FutureResult<Self, failure::Error> {
+        EVENTS.with(|e| e.borrow_mut().push(name.clone()));
+        futures::future::ok(Guard(name))
+    }
+}
+
+impl std::ops::Deref for Guard {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+macro_rules! accounts {
+    (ty $access:ident) => {
+        Guard
+    };
+    (resolve $access:ident [$key:expr]) => {
+        Guard::acquire(format!("{} accounts[{}]", stringify!($access), $key))
+    };
+    (resolve $access:ident) => {
+        Guard::acquire(format!("{} accounts", stringify!($access)))
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! orders {
+    (ty $access:ident) => {
+        Guard
+    };
+    (resolve $access:ident) => {
+        Guard::acquire(format!("{} orders", stringify!($access)))
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! users {
+    (ty $access:ident) => {
+        Guard
+    };
+    (resolve $access:ident) => {
+        Guard::acquire(format!("{} users", stringify!($access)))
+    };
+    (traits $access:ident $struct:ty) => {};
+}
+
+#[test]
+fn the_order_of_the_sections_does_not_change_the_order_of_acquisition() {
+    lock_derive::locks!(read: [accounts], write: [users, orders])
+        .wait()
+        .unwrap();
+    let first = take_events();
+
+    lock_derive::locks!(write: [orders, users], read: [accounts])
+        .wait()
+        .unwrap();
+    let second = take_events();
+
+    assert_eq!(first, ["write users", "write orders", "read accounts"]);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn the_keys_are_acquired_in_order_at_runtime() {
+    let (from, to) = (2, 1);
+    lock_derive::locks!(write: [accounts[from], accounts[to]])
+        .wait()
+        .unwrap();
+    let first = take_events();
+
+    lock_derive::locks!(write: [accounts[to], accounts[from]])
+        .wait()
+        .unwrap();
+    let second = take_events();
+
+    assert_eq!(first, second);
+    assert_eq!(first, ["write accounts[1]", "write accounts[2]"]);
+}
+
+#[test]
+fn the_same_key_twice_is_an_error() {
+    let (from, to) = (1, 1);
+    let e = lock_derive::locks!(write: [accounts[from], accounts[to]])
+        .wait()
+        .err()
+        .unwrap();
+
+    assert_eq!(
+        "Lock `accounts` requested more than once with the same key.",
+        e.to_string()
+    );
+    assert!(take_events().is_empty());
+}
@@ -0,0 +1,81 @@
+//! The holders of the `owners` feature, kept by the runtime crate without `runtime!()`.
+#![cfg(feature = "owners")]
+
+use futures::future::{lazy, poll_fn};
+use futures::{task, Async};
+use std::sync::{Mutex, MutexGuard};
+use tokio::runtime::current_thread::Runtime;
+
+// the lock given up by a timeout must support a waiter dropping out of its queue, which a lock
+// polled until it is free does.
+macro_rules! polled {
+    ($lock:ident) => {
+        poll_fn(|| match $lock.try_lock() {
+            Ok(guard) => Ok::<_, failure::Error>(Async::Ready(guard)),
+            Err(_) => {
+                task::current().notify();
+                Ok(Async::NotReady)
+            }
+        })
+    };
+}
+
+macro_rules! accounts {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { polled!(ACCOUNTS) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+macro_rules! users {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { polled!(USERS) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: Mutex<i32> = Mutex::new(10);
+    static ref USERS: Mutex<i32> = Mutex::new(1);
+}
+
+#[test]
+fn names_the_holder_in_the_error_of_the_timeout() {
+    let mut runtime = Runtime::new().unwrap();
+
+    let held = runtime
+        .block_on(lazy(|| {
+            lock_derive_runtime::name_task("billing-worker-3");
+            lock_derive::locks!(write: [accounts])
+        }))
+        .unwrap();
+
+    let future = lock_derive::locks!(write: [accounts @ 100ms]);
+    let e = runtime.block_on(future).err().unwrap().to_string();
+
+    assert!(
+        e.starts_with(
+            "acquiring write lock `accounts`: timed out after 100ms, held by task \
+             `billing-worker-3` acquired at tests/owners.rs:"
+        ),
+        "{}",
+        e
+    );
+
+    // the holder is forgotten once the struct is dropped.
+    drop(held);
+    let locks = runtime.block_on(lock_derive::locks!(write: [accounts @ 100ms]));
+    assert_eq!(10, *locks.unwrap().accounts);
+}
+
+#[test]
+fn numbers_the_tasks_not_named() {
+    let mut runtime = Runtime::new().unwrap();
+    let held = runtime
+        .block_on(lock_derive::locks!(write: [users]))
+        .unwrap();
+
+    let future = lock_derive::locks!(write: [users @ 100ms]);
+    let e = runtime.block_on(future).err().unwrap().to_string();
+    assert!(e.contains(", held by task `"), "{}", e);
+    assert!(!e.contains("billing-worker-3"), "{}", e);
+    drop(held);
+}
@@ -0,0 +1,56 @@
+//! The replicas of `quorum: 2 of [..] @ <duration>`, acquired concurrently, a replica held
+//! elsewhere timing out.
+
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use tokio::runtime::current_thread::Runtime;
+
+lazy_static::lazy_static! {
+    static ref PRIMARY: RwLock<i32> = RwLock::new(1);
+    static ref SECONDARY: RwLock<i32> = RwLock::new(2);
+    static ref TERTIARY: RwLock<i32> = RwLock::new(3);
+}
+
+define_lock!(primary: RwLock<i32> = PRIMARY);
+define_lock!(secondary: RwLock<i32> = SECONDARY);
+define_lock!(tertiary: RwLock<i32> = TERTIARY);
+
+#[test]
+fn a_replica_timing_out_counts_as_failed() {
+    let mut runtime = Runtime::new().unwrap();
+    let held = SECONDARY.try_write().unwrap();
+
+    let future = locks!(quorum: 2 of [primary, secondary, tertiary] @ 50ms);
+    let locks = runtime.block_on(future).unwrap();
+
+    assert_eq!(Some(1), locks.primary.as_ref().map(|g| **g));
+    assert!(locks.secondary.is_none());
+    assert_eq!(Some(3), locks.tertiary.as_ref().map(|g| **g));
+
+    drop(locks);
+    drop(held);
+
+    // the replicas being shared by the whole file, the quorum missed is checked in the same test.
+    let primary = PRIMARY.try_write().unwrap();
+    let tertiary = TERTIARY.try_write().unwrap();
+
+    let future = locks!(quorum: 2 of [primary, secondary, tertiary] @ 50ms);
+    let e = runtime.block_on(future).err().unwrap();
+
+    assert!(
+        e.to_string()
+            .starts_with("acquiring write lock `primary`: timed out after 50ms"),
+        "{}",
+        e
+    );
+
+    // the replica acquired was released along with the failure.
+    assert!(SECONDARY.try_write().is_ok());
+
+    drop((primary, tertiary));
+
+    let locks = runtime
+        .block_on(locks!(quorum: 3 of [primary, secondary, tertiary]))
+        .unwrap();
+    assert!(locks.primary.is_some() && locks.secondary.is_some() && locks.tertiary.is_some());
+}
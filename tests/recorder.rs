@@ -0,0 +1,57 @@
+//! The `Recorder` of the `test-util` feature, recording the acquisitions without `runtime!()`.
+#![cfg(feature = "test-util")]
+
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use lock_derive_runtime::Recorder;
+use tokio::executor::current_thread::block_on_all;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<i32> = RwLock::new(1);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<i32> = USERS);
+
+#[test]
+fn records_the_acquisition_order() {
+    let recorder = Recorder::new();
+    let future = locks!(no_traits; read: [users], write: [accounts]).map(drop);
+    block_on_all(recorder.record(future)).unwrap();
+
+    // the locks are acquired by decreasing name, whatever the order of the invocation.
+    assert_eq!(vec!["users", "accounts"], recorder.locks());
+    recorder.assert_order(&["users", "accounts"]);
+    assert!(recorder.held_together("users", "accounts"));
+
+    let accesses = recorder
+        .acquisitions()
+        .iter()
+        .map(|t| (t.access, t.released.is_some()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![("read", true), ("write", true)], accesses);
+}
+
+#[test]
+fn records_only_the_futures_it_wraps() {
+    let recorder = Recorder::new();
+    let locks = block_on_all(locks!(read: [users])).unwrap();
+    drop(locks);
+
+    let future = locks!(write: [accounts]).map(drop);
+    block_on_all(recorder.record(future)).unwrap();
+    assert_eq!(vec!["accounts"], recorder.locks());
+    assert!(!recorder.held_together("users", "accounts"));
+}
+
+#[test]
+#[should_panic(expected = "expected the locks to be acquired in the order")]
+fn assert_order_panics_on_another_order() {
+    let recorder = Recorder::new();
+    let future = locks!(no_traits; read: [accounts, users]).map(drop);
+    block_on_all(recorder.record(future)).unwrap();
+    recorder.assert_order(&["accounts", "users"]);
+}
@@ -0,0 +1,79 @@
+//! The registry of the `reentrancy` feature, kept by the runtime crate without `runtime!()`.
+#![cfg(feature = "reentrancy")]
+
+use futures::sync::oneshot;
+use futures::Future;
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use tokio::executor::current_thread::block_on_all;
+use tokio::runtime::current_thread::Runtime;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<i32> = RwLock::new(1);
+    static ref LEDGER: RwLock<i32> = RwLock::new(100);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<i32> = USERS);
+define_lock!(ledger: RwLock<i32> = LEDGER);
+
+#[test]
+#[should_panic(expected = "lock `users` acquired again while already held by the same task")]
+fn acquiring_a_held_lock_again_panics() {
+    let future = locks!(read: [users])
+        .and_then(|outer| locks!(read: [users]).map(move |inner| *outer.users + *inner.users));
+
+    let _ = block_on_all(future);
+}
+
+#[test]
+fn reentrant_locks_borrow_the_outer_guard() {
+    // the outer struct is not used until the inner one is dropped.
+    fn credit(amount: i32) -> impl Future<Item = (), Error = failure::Error> {
+        locks!(write: [accounts], unsafe reentrant: [accounts]).map(move |mut locks| {
+            *locks.accounts += amount;
+        })
+    }
+
+    let future = locks!(write: [accounts]).and_then(|locks| credit(5).map(move |_| locks));
+    let locks = block_on_all(future).unwrap();
+    assert_eq!(15, *locks.accounts);
+    drop(locks);
+
+    // the lock is acquired as usual once released.
+    block_on_all(credit(5)).unwrap();
+    assert_eq!(20, *ACCOUNTS.try_read().unwrap());
+}
+
+#[test]
+fn optimistic_futures_are_built_outside_of_a_task() {
+    // the holder of the locks is only taken once the future is polled, inside the task.
+    let future = locks!(optimistic; read: [users]);
+    assert_eq!(1, *block_on_all(future).unwrap().users);
+}
+
+#[test]
+fn owned_structs_move_to_another_task() {
+    let mut runtime = Runtime::new().unwrap();
+    let (sender, receiver) = oneshot::channel();
+
+    // the spawned task moves the struct to the main one, then nests an acquisition of its own,
+    // holding no lock anymore.
+    runtime.spawn(
+        locks!(owned; write: [ledger])
+            .and_then(move |locks| {
+                let _ = sender.send(locks);
+                locks!(read: [users])
+            })
+            .map(|users| assert_eq!(1, *users.users))
+            .map_err(|e| panic!("{}", e)),
+    );
+
+    let mut locks = runtime.block_on(receiver).unwrap();
+    *locks.ledger += 1;
+    drop(locks);
+
+    runtime.run().unwrap();
+    assert_eq!(101, *LEDGER.try_read().unwrap());
+}
@@ -0,0 +1,23 @@
+//! The closures ending an invocation of `locks_sync!`, run with the struct borrowed.
+
+use lock_derive::locks_sync;
+use std::sync::{Mutex, MutexGuard};
+
+macro_rules! users {
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve write) => { USERS.lock().map_err(|_| failure::err_msg("Lock poisoned")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+static USERS: Mutex<i32> = Mutex::new(1);
+
+#[test]
+fn the_guards_are_released_once_the_closure_returns() {
+    let users = locks_sync!(write: [users], |l| {
+        *l.users += 1;
+        *l.users
+    });
+
+    assert_eq!(2, users.unwrap());
+    assert!(USERS.try_lock().is_ok());
+}
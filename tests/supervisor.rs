@@ -0,0 +1,72 @@
+//! The `supervised;` invocations of the `supervisor` feature.
+#![cfg(feature = "supervisor")]
+
+use futures::Future;
+use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use lock_derive::locks;
+use lock_derive_runtime::Supervisor;
+use std::time::Duration;
+use tokio::runtime::current_thread::Runtime;
+
+// the acquisition losing the race against `force_resolve` is kept alive until it is granted.
+macro_rules! ledger {
+    (ty write) => { RwLockWriteGuard<i32> };
+    (resolve write) => {
+        lock_derive_runtime::detach(LEDGER.write()).map_err(|_| failure::err_msg("Lock error"))
+    };
+    (force_resolve write) => { REPLACEMENT.write().map_err(|_| failure::err_msg("Lock error")) };
+    (supervisor) => { &LEDGER_SUPERVISOR };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref LEDGER: RwLock<i32> = RwLock::new(1);
+    static ref REPLACEMENT: RwLock<i32> = RwLock::new(2);
+}
+
+static LEDGER_SUPERVISOR: Supervisor = Supervisor::new();
+
+// the recipe has neither a `supervisor` nor a `force_resolve` arm.
+macro_rules! audit {
+    (ty read) => { RwLockReadGuard<i32> };
+    (resolve read) => { AUDIT.read().map_err(|_| failure::err_msg("Lock error")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+lazy_static::lazy_static! {
+    static ref AUDIT: RwLock<i32> = RwLock::new(3);
+}
+
+#[test]
+fn invocations_not_supervised_are_left_as_they_are() {
+    let locks = locks!(read: [audit]).wait().unwrap();
+    assert_eq!(3, *locks.audit);
+}
+
+#[test]
+fn forces_the_acquisition_once_the_grace_period_has_elapsed() {
+    let mut runtime = Runtime::new().unwrap();
+    let held = runtime
+        .block_on(locks!(supervised; write: [ledger]))
+        .unwrap();
+    assert!(!held.is_invalidated());
+
+    ledger!(supervisor).invalidate(Duration::from_millis(200));
+    assert!(held.is_invalidated());
+
+    let forced = runtime
+        .block_on(locks!(supervised; write: [ledger]))
+        .unwrap();
+    assert_eq!(2, *forced.ledger);
+    assert_eq!(1, *held.ledger);
+
+    // the release grants the lock to the acquisition which lost the race, released at once.
+    drop((forced, held));
+    ledger!(supervisor).restore();
+
+    let locks = runtime
+        .block_on(locks!(supervised; write: [ledger]))
+        .unwrap();
+    assert_eq!(1, *locks.ledger);
+    assert!(!locks.is_invalidated());
+}
@@ -0,0 +1,115 @@
+//! The events of the `telemetry` feature, logged by the runtime crate without `runtime!()`.
+#![cfg(feature = "telemetry")]
+
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use log::{Log, Metadata, Record};
+use std::sync::{Mutex, Once};
+use tokio::runtime::current_thread::Runtime;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+    static ref USERS: RwLock<u32> = RwLock::new(1);
+}
+
+define_lock!(accounts: RwLock<i32> = ACCOUNTS);
+define_lock!(users: RwLock<u32> = USERS);
+
+// a recipe out of reach.
+macro_rules! ledger {
+    (ty write) => { futures_locks::RwLockWriteGuard<i32> };
+    (resolve write) => { futures::future::err(failure::format_err!("Ledger down")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+/// The events of the `lock_derive::telemetry` target: the message, the locks and the cause.
+static EVENTS: Mutex<Vec<(String, String, String)>> = Mutex::new(Vec::new());
+
+struct Telemetry;
+
+impl Log for Telemetry {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == "lock_derive::telemetry"
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let kv = |key: &str| {
+                record
+                    .key_values()
+                    .get(log::kv::Key::from(key))
+                    .map(|t| t.to_string())
+                    .unwrap_or_default()
+            };
+
+            EVENTS
+                .lock()
+                .unwrap()
+                .push((record.args().to_string(), kv("locks"), kv("cause")));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The events logged for the locks given, the tests running in parallel.
+fn events(locks: &str) -> Vec<(String, String)> {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        log::set_logger(&Telemetry).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+    });
+
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|t| t.1 == locks)
+        .map(|t| (t.0.clone(), t.2.clone()))
+        .collect()
+}
+
+#[test]
+fn labels_the_failure_of_a_recipe_as_backend() {
+    events("");
+
+    let mut runtime = Runtime::new().unwrap();
+    let e = runtime
+        .block_on(locks!(write: [accounts, ledger]))
+        .err()
+        .unwrap();
+
+    let logged = events("accounts:write,ledger:write");
+    assert_eq!(1, logged.len());
+    assert_eq!(format!("lock bundle resolve failed: {}", e), logged[0].0);
+    assert_eq!("backend", logged[0].1);
+}
+
+#[test]
+fn labels_a_timeout() {
+    events("");
+
+    let mut runtime = Runtime::new().unwrap();
+    let held = USERS.try_write().unwrap();
+
+    runtime
+        .block_on(locks!(write: [users @ 50ms]))
+        .err()
+        .unwrap();
+    drop(held);
+
+    let logged = events("users:write");
+    assert_eq!(1, logged.len());
+    assert_eq!("timeout", logged[0].1);
+}
+
+#[test]
+fn logs_nothing_once_resolved() {
+    events("");
+
+    let mut runtime = Runtime::new().unwrap();
+    runtime.block_on(locks!(read: [accounts])).unwrap();
+
+    assert!(events("accounts:read").is_empty());
+}
@@ -0,0 +1,37 @@
+//! The timeouts of `@ <duration>`, given up on the locks of `define_lock!`.
+
+use futures_locks::RwLock;
+use lock_derive::{define_lock, locks};
+use tokio::runtime::current_thread::Runtime;
+
+lazy_static::lazy_static! {
+    static ref LEDGER: RwLock<i32> = RwLock::new(10);
+    static ref PAYMENTS: RwLock<u32> = RwLock::new(1);
+}
+
+define_lock!(ledger: RwLock<i32> = LEDGER);
+define_lock!(payments: RwLock<u32> = PAYMENTS);
+
+#[test]
+fn the_acquisition_given_up_leaves_the_lock_usable() {
+    let mut runtime = Runtime::new().unwrap();
+    let held = PAYMENTS.try_write().unwrap();
+
+    let future = locks!(write: [ledger @ 50ms, payments @ 50ms]);
+    let e = runtime.block_on(future).err().unwrap();
+
+    assert!(
+        e.to_string()
+            .starts_with("acquiring write lock `payments`: timed out after 50ms"),
+        "{}",
+        e
+    );
+
+    // the release grants the lock to the acquisition given up, which releases it at once.
+    drop(held);
+
+    let locks = runtime
+        .block_on(locks!(write: [ledger @ 1s, payments @ 1s]))
+        .unwrap();
+    assert_eq!((10, 1), (*locks.ledger, *locks.payments));
+}
@@ -0,0 +1,78 @@
+//! The versions of the locks read by `versioned: [..]`.
+
+use futures::Future;
+use futures_locks::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use lock_derive::{locks, locks_sync};
+use std::sync::{Mutex, MutexGuard};
+
+mod storage {
+    macro_rules! accounts {
+        (ty read) => { RwLockReadGuard<i32> };
+        (ty write) => { RwLockWriteGuard<i32> };
+        (resolve read) => { ACCOUNTS.read().map_err(|_| failure::err_msg("Lock error")) };
+        (resolve write) => { ACCOUNTS.write().map_err(|_| failure::err_msg("Lock error")) };
+        (traits $access:ident $struct:ty) => {};
+    }
+
+    pub(crate) use accounts;
+}
+
+lazy_static::lazy_static! {
+    static ref ACCOUNTS: RwLock<i32> = RwLock::new(10);
+}
+
+macro_rules! users {
+    (ty read) => { MutexGuard<'static, i32> };
+    (ty write) => { MutexGuard<'static, i32> };
+    (resolve read) => { USERS.lock().map_err(|_| failure::err_msg("Lock poisoned")) };
+    (resolve write) => { USERS.lock().map_err(|_| failure::err_msg("Lock poisoned")) };
+    (traits $access:ident $struct:ty) => {};
+}
+
+static USERS: Mutex<i32> = Mutex::new(1);
+
+#[test]
+fn writes_invalidate_the_values_read_once_released() {
+    let read = locks!(versioned: [storage::accounts]).wait().unwrap();
+    assert!(read.validate());
+
+    let mut write = locks!(write: [storage::accounts]).wait().unwrap();
+    *write.accounts += 1;
+    assert!(read.validate());
+
+    drop(write);
+    assert!(!read.validate());
+    assert!(!read.accounts.is_current());
+    assert_eq!(10, *read.accounts);
+
+    let read = locks!(versioned: [storage::accounts]).wait().unwrap();
+    assert!(read.validate());
+    assert_eq!(11, *read.accounts);
+
+    // `commit()` does not wait for the guards to be released.
+    let write = locks!(write: [storage::accounts]).wait().unwrap();
+    write.commit();
+    assert!(!read.validate());
+}
+
+#[test]
+fn the_parts_invalidate_the_values_read_once_dropped() {
+    let read = locks_sync!(versioned: [users]).unwrap();
+
+    let (mut guard, written) = locks_sync!(write: [users]).unwrap().into_parts();
+    *guard += 1;
+    assert!(read.validate());
+    drop(written);
+    assert!(!read.validate());
+    drop(guard);
+
+    let read = locks_sync!(versioned: [users]).unwrap();
+    let (mut guard, written) = locks_sync!(tuple; write: [users]).unwrap();
+    *guard += 1;
+    drop((written, guard));
+    assert!(!read.validate());
+
+    let read = locks_sync!(versioned: [users]).unwrap();
+    assert_eq!(3, *read.users);
+    assert!(read.validate());
+}